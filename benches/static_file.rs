@@ -0,0 +1,82 @@
+// compares the throughput of sending a file body to a socket.
+// run with 'cargo bench --bench static_file'
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lite_ws::http::{ContentType, HttpResponseBuilder};
+
+const FILE_SIZE: usize = 64 * 1024 * 1024;
+const ROUNDS: usize = 10;
+
+// read everything the server sends and throw it away
+fn start_sink() -> (SocketAddr, thread::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let local_addr = listener.local_addr().unwrap();
+    let join_handle = thread::spawn(move || {
+        for _ in 0..ROUNDS {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = vec![0_u8; 256 * 1024];
+            while stream.read(&mut buffer).unwrap() > 0 {}
+        }
+    });
+
+    (local_addr, join_handle)
+}
+
+fn measure<F: Fn(&mut TcpStream)>(name: &str, send: F) {
+    let (local_addr, join_handle) = start_sink();
+    let mut elapsed = Duration::from_secs(0);
+
+    for _ in 0..ROUNDS {
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        let started = Instant::now();
+        send(&mut stream);
+        stream.flush().unwrap();
+        elapsed += started.elapsed();
+    }
+    join_handle.join().unwrap();
+
+    let megabytes = (FILE_SIZE * ROUNDS) as f64 / (1024.0 * 1024.0);
+    println!("{:<24} {:>10.1} MB/s", name, megabytes / elapsed.as_secs_f64());
+}
+
+fn main() {
+    let path = env::temp_dir().join("lite-ws-bench-static-file");
+    let content: Vec<u8> = (0..FILE_SIZE).map(|i| (i % 251) as u8).collect();
+    fs::write(&path, &content).unwrap();
+    let content_type = || ContentType::new("application", "octet-stream");
+
+    // the path before sendfile, the whole file is read into the memory
+    measure("read_to_end + write_all", |stream| {
+        let mut body = vec![];
+        File::open(&path).unwrap().read_to_end(&mut body).unwrap();
+        HttpResponseBuilder::new()
+            .body(content_type(), body)
+            .build()
+            .unwrap()
+            .respond(stream);
+    });
+
+    measure("buffered copy", |stream| {
+        HttpResponseBuilder::new()
+            .file(content_type(), File::open(&path).unwrap())
+            .build()
+            .unwrap()
+            .respond(stream);
+    });
+
+    measure("sendfile", |stream| {
+        HttpResponseBuilder::new()
+            .file(content_type(), File::open(&path).unwrap())
+            .build()
+            .unwrap()
+            .respond_to_socket(stream);
+    });
+
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,5 @@
+// the statics of the demo server are bundled into the binary
+fn main() {
+    println!("cargo:rerun-if-changed=../../statics");
+    lite_ws::embed::embed_dir("../../statics", "statics.rs").expect("fail to embed statics");
+}
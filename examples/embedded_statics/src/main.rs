@@ -0,0 +1,17 @@
+use lite_ws::embed::EmbeddedFile;
+use lite_ws::route::EmbeddedRoute;
+
+// bundled by the build script, so the binary does not need the statics directory
+static STATICS: &[EmbeddedFile] = lite_ws::include_assets!("statics.rs");
+
+// e.g. GET /st/tayo.png
+fn main() {
+    lite_ws::server::ServerBuilder::default()
+        .ip_addr("127.0.0.1")
+        .port_num(8888)
+        .handle_signals(true)
+        .build()
+        .mount_route(EmbeddedRoute::new("/st", STATICS))
+        .run()
+        .expect("fail to run server");
+}
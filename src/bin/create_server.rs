@@ -1,25 +1,47 @@
-use lite_ws::http::{ContentType, HttpRequest, HttpResponse, HttpResponseBuilder, HttpStatus};
+use std::collections::HashMap;
+
+use lite_ws::extract::{Path, State};
+use lite_ws::http::{ContentType, HttpError, HttpResponse, HttpResponseBuilder, HttpStatus};
 use lite_ws::route::{ActionRoute, StaticRoute};
+use lite_ws::session::{MemorySessionStore, SessionMiddleware};
+
+// shared with every handler through the application state
+struct Greeting(String);
 
 fn main() {
     let server = lite_ws::server::ServerBuilder::default()
         .ip_addr("127.0.0.1")
         .port_num(8888)
         .thread_count(16)
+        .handle_signals(true)
         .build();
 
     server
+        .manage_state(Greeting("you say hello".to_string()))
+        .mount_middleware(SessionMiddleware::new(MemorySessionStore::new()))
         .mount_route(StaticRoute::new(
             "/statics".to_string(),
             Some("/st".to_string()),
             Some(vec!["png".to_string()]),
         ))
-        .mount_route(ActionRoute::new_get("/", root_handler).unwrap())
+        .mount_route(ActionRoute::get("/", root_handler).unwrap())
+        .mount_route(
+            ActionRoute::get("/hello", |State(greeting): State<Greeting>| greeting.0.clone()).unwrap(),
+        )
+        .mount_route(ActionRoute::get("/hello/:name", greet_handler).unwrap())
         .mount_route(
-            ActionRoute::new_get("/hello", |req, builder| {
+            ActionRoute::new_get("/visits", |mut req, builder| {
+                let visits = if let Some(session) = req.get_session_mut() {
+                    let visits = session.get::<u32>("visits").unwrap_or(0) + 1;
+                    session.insert("visits", visits);
+                    visits
+                } else {
+                    0
+                };
+
                 let builder = builder
                     .set_status(HttpStatus::OK)
-                    .body(ContentType::TEXT_PLAIN, Vec::from("you say hello"));
+                    .body(ContentType::TEXT_PLAIN, format!("visits: {}", visits).into_bytes());
 
                 (req, builder.build().unwrap())
             })
@@ -29,8 +51,9 @@ fn main() {
         .expect("fail to run server");
 }
 
-fn root_handler(req: HttpRequest, res_builder: HttpResponseBuilder) -> (HttpRequest, HttpResponse) {
-    let res = res_builder
+// a failure of the build is answered by the error handler, instead of panicking the worker
+fn root_handler(res_builder: HttpResponseBuilder) -> Result<HttpResponse, HttpError> {
+    res_builder
         .set_status(HttpStatus::OK)
         .html(
             "
@@ -45,7 +68,12 @@ fn root_handler(req: HttpRequest, res_builder: HttpResponseBuilder) -> (HttpRequ
     "
                 .to_string(),
         )
-        .build();
+        .build()
+}
+
+// arguments are extracted from the request, e.g. GET /hello/tayo
+fn greet_handler(Path(name): Path<HashMap<String, String>>, State(greeting): State<Greeting>) -> String {
+    let name = name.get("name").cloned().unwrap_or_default();
 
-    (req, res.unwrap())
+    format!("{}, {}", greeting.0, name)
 }
@@ -0,0 +1,9 @@
+// a file bundled into the binary by 'embed_dir'
+#[derive(Debug)]
+pub struct EmbeddedFile {
+    // url path from the root of the embedded directory, e.g. '/nested/n_tayo.png'
+    pub path: &'static str,
+    pub content: &'static [u8],
+    pub gzipped: Option<&'static [u8]>,
+    pub etag: &'static str,
+}
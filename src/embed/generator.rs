@@ -0,0 +1,139 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+// generate a rust file which bundles every file under 'dir', to be called from the build script of
+// an application which has lite-ws in its build-dependencies, e.g. 'examples/embedded_statics'.
+// the generated file is an expression of '&[EmbeddedFile]', which is included by 'include_assets!'.
+//
+//     // build.rs
+//     lite_ws::embed::embed_dir("statics", "statics.rs").unwrap();
+//
+//     // main.rs
+//     static STATICS: &[EmbeddedFile] = lite_ws::include_assets!("statics.rs");
+//
+// 'out_file' is relative to OUT_DIR, the compressed variants are written next to it.
+pub fn embed_dir<P: AsRef<Path>, Q: AsRef<Path>>(dir: P, out_file: Q) -> io::Result<()> {
+    let out_dir = std::env::var_os("OUT_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "OUT_DIR is not set"))?;
+
+    embed_dir_to(dir.as_ref(), &out_dir.join(out_file))
+}
+
+pub fn embed_dir_to(dir: &Path, out_path: &Path) -> io::Result<()> {
+    let dir = fs::canonicalize(dir)?;
+    let out_path = if out_path.is_absolute() {
+        out_path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(out_path)
+    };
+    let gz_dir = out_path.with_extension("gz.d");
+    fs::create_dir_all(&gz_dir)?;
+
+    let mut files = vec![];
+    collect_files(&dir, &mut files)?;
+    files.sort();
+
+    let mut source = String::from("&[\n");
+    for (index, file_path) in files.iter().enumerate() {
+        let relative_path = file_path.strip_prefix(&dir).unwrap_or(file_path);
+        let url_path: Vec<String> = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect();
+        let content = fs::read(file_path)?;
+
+        // only a variant which is worth it is kept, e.g. images are already compressed
+        let gzipped = gzip(&content)?;
+        let gzipped = if gzipped.len() * 10 < content.len() * 9 {
+            let gz_path = gz_dir.join(format!("{}.gz", index));
+            fs::write(&gz_path, gzipped)?;
+            format!("Some(include_bytes!({:?}))", gz_path)
+        } else {
+            "None".to_string()
+        };
+
+        let _ = write!(
+            source,
+            "    ::lite_ws::embed::EmbeddedFile {{\n        \
+            path: {:?},\n        \
+            content: include_bytes!({:?}),\n        \
+            gzipped: {},\n        \
+            etag: {:?},\n    \
+            }},\n",
+            format!("/{}", url_path.join("/")),
+            file_path,
+            gzipped,
+            format!("\"{:016x}\"", fnv_hash(&content)),
+        );
+    }
+    source.push(']');
+
+    fs::write(&out_path, source)?;
+    // cargo checks the files under the directory
+    println!("cargo:rerun-if-changed={}", dir.display());
+
+    Ok(())
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+fn gzip(content: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(content)?;
+
+    encoder.finish()
+}
+
+// a stable hash of the content, so the etag stays the same across builds
+fn fnv_hash(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::util::random::random_hex;
+
+    use super::*;
+
+    #[test]
+    fn test_embed_dir_to() {
+        let dir = env::temp_dir().join(format!("lite-ws-embed-{}", random_hex(8)));
+        fs::create_dir_all(dir.join("css")).unwrap();
+        fs::write(dir.join("css/style.css"), "body { color: red; }\n".repeat(50)).unwrap();
+        fs::write(dir.join("index.html"), "<html></html>").unwrap();
+
+        let out_path = dir.join("out/assets.rs");
+        fs::create_dir_all(out_path.parent().unwrap()).unwrap();
+        embed_dir_to(&dir.join("."), &out_path).unwrap();
+
+        let source = fs::read_to_string(&out_path).unwrap();
+        assert!(source.contains("path: \"/css/style.css\""));
+        assert!(source.contains("path: \"/index.html\""));
+        // only the css is worth compressing
+        assert_eq!(source.matches("Some(include_bytes!").count(), 1);
+        assert!(out_path.with_extension("gz.d").join("0.gz").is_file());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,13 @@
+pub use embedded_file::EmbeddedFile;
+pub use generator::{embed_dir, embed_dir_to};
+
+mod embedded_file;
+mod generator;
+
+// include the files generated by 'embed_dir' in the build script
+#[macro_export]
+macro_rules! include_assets {
+    ($out_file:expr) => {
+        include!(concat!(env!("OUT_DIR"), "/", $out_file))
+    };
+}
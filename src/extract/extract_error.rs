@@ -0,0 +1,50 @@
+use crate::http::{ContentType, HttpResponse, HttpResponseBuilder, HttpStatus};
+
+// the reason why an extractor could not be made from the request.
+// the handler is not called, and the client gets the response of 'to_response' instead.
+#[derive(Debug)]
+pub enum ExtractError {
+    InvalidPath(String),
+    InvalidQuery(String),
+    MissingHeader(&'static str),
+    InvalidHeader(&'static str),
+    InvalidBody(String),
+    // holds the content type which the extractor expects
+    UnsupportedMediaType(&'static str),
+    // holds the type name of the state which is not registered
+    MissingState(&'static str),
+}
+
+impl ExtractError {
+    pub fn get_status(&self) -> HttpStatus {
+        match self {
+            ExtractError::UnsupportedMediaType(_) => HttpStatus::UNSUPPORTED_MEDIA_TYPE,
+            ExtractError::MissingState(_) => HttpStatus::INTERNAL_SERVER_ERROR,
+            _ => HttpStatus::BAD_REQUEST,
+        }
+    }
+
+    pub fn to_response(&self) -> HttpResponse {
+        let message = match self {
+            ExtractError::InvalidPath(reason) => format!("invalid path parameter: {}", reason),
+            ExtractError::InvalidQuery(reason) => format!("invalid query string: {}", reason),
+            ExtractError::MissingHeader(name) => format!("missing header: {}", name),
+            ExtractError::InvalidHeader(name) => format!("invalid header: {}", name),
+            ExtractError::InvalidBody(reason) => format!("invalid body: {}", reason),
+            ExtractError::UnsupportedMediaType(expected) => {
+                format!("content type must be {}", expected)
+            }
+            ExtractError::MissingState(type_name) => {
+                // it is a mistake of the server, so the detail is not exposed
+                eprintln!("[error] state is not registered: {}", type_name);
+                "internal server error".to_string()
+            }
+        };
+
+        HttpResponseBuilder::new()
+            .set_status(self.get_status())
+            .body(ContentType::TEXT_PLAIN, message.into_bytes())
+            .build()
+            .unwrap_or_else(|_| HttpResponse::new_with(self.get_status()))
+    }
+}
@@ -0,0 +1,69 @@
+use std::ops::Deref;
+
+use serde::de::DeserializeOwned;
+
+use crate::extract::{ExtractError, FromRequest};
+use crate::extract::from_request::{body_bytes, expect_content_type};
+use crate::http::HttpRequest;
+
+const FORM_CONTENT_TYPE: &str = "application/x-www-form-urlencoded";
+
+// url encoded form body
+#[derive(Debug)]
+pub struct Form<T>(pub T);
+
+impl<T> Form<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Form<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Form<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        expect_content_type(http_request, FORM_CONTENT_TYPE)?;
+
+        serde_urlencoded::from_bytes(body_bytes(http_request))
+            .map(Form)
+            .map_err(|error| ExtractError::InvalidBody(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Login {
+        name: String,
+        age: u8,
+    }
+
+    #[test]
+    fn test_from_request() {
+        let header = "POST /login HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\n";
+        let request = make_request(header, Some(b"name=foo+bar&age=20"));
+        let login = Form::<Login>::from_request(&request).unwrap();
+        assert_eq!(login.name, "foo bar");
+        assert_eq!(login.age, 20);
+
+        let request = make_request(header, Some(b"name=foo&age=old"));
+        let error = Form::<Login>::from_request(&request).unwrap_err();
+        assert_eq!(error.get_status().code, 400);
+
+        let request = make_request("POST /login HTTP/1.1\r\n", Some(b"name=foo&age=20"));
+        let error = Form::<Login>::from_request(&request).unwrap_err();
+        assert_eq!(error.get_status().code, 415);
+    }
+}
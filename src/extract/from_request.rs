@@ -0,0 +1,46 @@
+use crate::extract::ExtractError;
+use crate::http::{HttpRequest, HttpResponseBuilder, ReadHeaderAs};
+
+// a type which can be made from the request, to be an argument of a handler function.
+pub trait FromRequest: Sized {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError>;
+}
+
+// handlers which build the response by themselves
+impl FromRequest for HttpResponseBuilder {
+    fn from_request(_http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        Ok(HttpResponseBuilder::new())
+    }
+}
+
+// makes an extractor optional, the failure is ignored
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        Ok(T::from_request(http_request).ok())
+    }
+}
+
+// check the media type of the body, parameters like 'charset' are ignored
+pub(crate) fn expect_content_type(
+    http_request: &HttpRequest,
+    expected: &'static str,
+) -> Result<(), ExtractError> {
+    let content_type: Option<String> = http_request.get_header("content-type");
+    let media_type = content_type
+        .as_deref()
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|media_type| media_type.trim().to_lowercase());
+
+    if media_type.as_deref() == Some(expected) {
+        Ok(())
+    } else {
+        Err(ExtractError::UnsupportedMediaType(expected))
+    }
+}
+
+pub(crate) fn body_bytes(http_request: &HttpRequest) -> &[u8] {
+    http_request
+        .get_body()
+        .map(|body| body.get_raw())
+        .unwrap_or(&[])
+}
@@ -0,0 +1,129 @@
+use std::ops::Deref;
+use std::str::FromStr;
+
+use crate::extract::{ExtractError, FromRequest};
+use crate::http::{HttpRequest, ReadHeaderAs};
+
+// a header value type which knows its header name
+pub trait NamedHeader: FromStr {
+    // lowercase header name
+    const NAME: &'static str;
+}
+
+// a typed request header, the request is rejected when the header is missing or malformed.
+// wrap it with Option to make the header optional.
+#[derive(Debug)]
+pub struct Header<T>(pub T);
+
+impl<T> Header<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Header<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: NamedHeader> FromRequest for Header<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        let raw: String = http_request
+            .get_header(T::NAME)
+            .ok_or(ExtractError::MissingHeader(T::NAME))?;
+
+        raw.parse::<T>()
+            .map(Header)
+            .map_err(|_| ExtractError::InvalidHeader(T::NAME))
+    }
+}
+
+// predefined headers
+#[derive(Debug, Eq, PartialEq)]
+pub struct Host(pub String);
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct UserAgent(pub String);
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ContentLength(pub usize);
+
+// the id of the last event which a client of Server-Sent Events has received before reconnecting
+#[derive(Debug, Eq, PartialEq)]
+pub struct LastEventId(pub String);
+
+impl NamedHeader for Host {
+    const NAME: &'static str = "host";
+}
+
+impl FromStr for Host {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Host(s.to_string()))
+    }
+}
+
+impl NamedHeader for UserAgent {
+    const NAME: &'static str = "user-agent";
+}
+
+impl FromStr for UserAgent {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(UserAgent(s.to_string()))
+    }
+}
+
+impl NamedHeader for ContentLength {
+    const NAME: &'static str = "content-length";
+}
+
+impl FromStr for ContentLength {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(ContentLength)
+    }
+}
+
+impl NamedHeader for LastEventId {
+    const NAME: &'static str = "last-event-id";
+}
+
+impl FromStr for LastEventId {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(LastEventId(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::make_unchecked_request;
+
+    use super::*;
+
+    #[test]
+    fn test_from_request() {
+        let request = make_unchecked_request("GET / HTTP/1.1\r\nUser-Agent: curl/7.68.0\r\nContent-Length: abc\r\n");
+
+        let Header(user_agent) = Header::<UserAgent>::from_request(&request).unwrap();
+        assert_eq!(user_agent, UserAgent("curl/7.68.0".to_string()));
+
+        assert!(matches!(
+            Header::<ContentLength>::from_request(&request),
+            Err(ExtractError::InvalidHeader("content-length"))
+        ));
+        assert!(matches!(
+            Header::<Host>::from_request(&request),
+            Err(ExtractError::MissingHeader("host"))
+        ));
+        assert!(Option::<Header<Host>>::from_request(&request).unwrap().is_none());
+    }
+}
@@ -0,0 +1,91 @@
+use std::ops::Deref;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::extract::{ExtractError, FromRequest};
+use crate::extract::from_request::{body_bytes, expect_content_type};
+use crate::http::{ContentType, HandlerError, HttpRequest, HttpResponse, HttpResponseBuilder, IntoResponse};
+
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+// json body, it also can be returned by a handler to respond with json
+#[derive(Debug)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        expect_content_type(http_request, JSON_CONTENT_TYPE)?;
+
+        serde_json::from_slice(body_bytes(http_request))
+            .map(Json)
+            .map_err(|error| ExtractError::InvalidBody(error.to_string()))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        let body = serde_json::to_vec(&self.0)?;
+
+        Ok(HttpResponseBuilder::new()
+            .body(ContentType::APPLICATION_JSON, body)
+            .build()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Post {
+        title: String,
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn test_from_request() {
+        let header = "POST /posts HTTP/1.1\r\nContent-Type: application/json; charset=utf-8\r\n";
+        let request = make_request(header, Some(br#"{"title": "hi", "tags": ["a", "b"]}"#));
+        let post = Json::<Post>::from_request(&request).unwrap();
+        assert_eq!(post.title, "hi");
+        assert_eq!(post.tags, vec!["a", "b"]);
+
+        let request = make_request(header, Some(b"{"));
+        let error = Json::<Post>::from_request(&request).unwrap_err();
+        assert_eq!(error.get_status().code, 400);
+
+        let header = "POST /posts HTTP/1.1\r\nContent-Type: text/plain\r\n";
+        let request = make_request(header, Some(b"{}"));
+        let error = Json::<Post>::from_request(&request).unwrap_err();
+        assert_eq!(error.get_status().code, 415);
+    }
+
+    #[test]
+    fn test_into_response() {
+        let mut written = vec![];
+        Json(vec!["a", "b"]).into_response().unwrap().respond(&mut written);
+        let written = String::from_utf8(written).unwrap();
+
+        assert!(written.contains("Content-Type: application/json\r\n"));
+        assert!(written.ends_with(r#"["a","b"]"#));
+    }
+}
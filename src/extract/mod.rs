@@ -0,0 +1,18 @@
+pub use extract_error::ExtractError;
+pub use form::Form;
+pub use from_request::FromRequest;
+pub use header::{ContentLength, Header, Host, LastEventId, NamedHeader, UserAgent};
+pub use json::Json;
+pub use path::Path;
+pub use query::Query;
+pub use state::State;
+
+mod extract_error;
+mod from_request;
+
+mod form;
+mod header;
+mod json;
+mod path;
+mod query;
+mod state;
@@ -0,0 +1,73 @@
+use std::ops::Deref;
+
+use serde::de::DeserializeOwned;
+
+use crate::extract::{ExtractError, FromRequest};
+use crate::http::HttpRequest;
+
+// path parameters of the route (e.g. '/users/:id'), T is a struct whose fields are named after the parameters
+#[derive(Debug)]
+pub struct Path<T>(pub T);
+
+impl<T> Path<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Path<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Path<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        // parameters are already decoded, so they are encoded again to reuse the form deserializer
+        let pairs: Vec<(&String, &String)> = http_request.get_path_params().iter().collect();
+        let encoded = serde_urlencoded::to_string(pairs)
+            .map_err(|error| ExtractError::InvalidPath(error.to_string()))?;
+
+        serde_urlencoded::from_str(&encoded)
+            .map(Path)
+            .map_err(|error| ExtractError::InvalidPath(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::test_support::make_request;
+    use crate::route::PathParams;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Params {
+        id: u32,
+        name: String,
+    }
+
+    #[test]
+    fn test_from_request() {
+        let mut request = make_request("GET /users/7/a%20b HTTP/1.1\r\n", None);
+        let mut params = PathParams::new();
+        params.insert("id".to_string(), "7".to_string());
+        params.insert("name".to_string(), "a b".to_string());
+        request.set_path_params(params.clone());
+
+        let Path(extracted) = Path::<Params>::from_request(&request).unwrap();
+        assert_eq!(extracted.id, 7);
+        assert_eq!(extracted.name, "a b");
+
+        params.insert("id".to_string(), "seven".to_string());
+        request.set_path_params(params);
+        assert!(matches!(
+            Path::<Params>::from_request(&request),
+            Err(ExtractError::InvalidPath(_))
+        ));
+    }
+}
@@ -0,0 +1,61 @@
+use std::ops::Deref;
+
+use serde::de::DeserializeOwned;
+
+use crate::extract::{ExtractError, FromRequest};
+use crate::http::HttpRequest;
+
+// query string of the url (e.g. '/search?q=rust&page=2')
+#[derive(Debug)]
+pub struct Query<T>(pub T);
+
+impl<T> Query<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Query<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        let query = http_request.get_req_path().get_query().unwrap_or("");
+
+        serde_urlencoded::from_str(query)
+            .map(Query)
+            .map_err(|error| ExtractError::InvalidQuery(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Search {
+        q: String,
+        page: Option<u32>,
+    }
+
+    #[test]
+    fn test_from_request() {
+        let request = make_request("GET /search?q=hello%20world&page=2 HTTP/1.1\r\n", None);
+        let search = Query::<Search>::from_request(&request).unwrap();
+        assert_eq!(search.q, "hello world");
+        assert_eq!(search.page, Some(2));
+
+        let request = make_request("GET /search HTTP/1.1\r\n", None);
+        let error = Query::<Search>::from_request(&request).unwrap_err();
+        assert_eq!(error.get_status().code, 400);
+    }
+}
@@ -0,0 +1,55 @@
+use std::any::type_name;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::extract::{ExtractError, FromRequest};
+use crate::http::HttpRequest;
+
+// application state registered by 'Server::manage_state'
+#[derive(Debug)]
+pub struct State<T>(pub Arc<T>);
+
+impl<T> State<T> {
+    pub fn into_inner(self) -> Arc<T> {
+        self.0
+    }
+}
+
+impl<T> Deref for State<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(http_request: &HttpRequest) -> Result<Self, ExtractError> {
+        http_request
+            .get_state::<T>()
+            .map(State)
+            .ok_or_else(|| ExtractError::MissingState(type_name::<T>()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::make_request;
+    use crate::server::AppState;
+
+    use super::*;
+
+    #[test]
+    fn test_from_request() {
+        let mut request = make_request("GET / HTTP/1.1\r\n", None);
+        assert_eq!(
+            State::<u32>::from_request(&request).unwrap_err().get_status().code,
+            500
+        );
+
+        let mut app_state = AppState::new();
+        app_state.insert(7_u32);
+        request.set_app_state(Arc::new(app_state));
+        assert_eq!(*State::<u32>::from_request(&request).unwrap(), 7);
+    }
+}
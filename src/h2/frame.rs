@@ -0,0 +1,198 @@
+use crate::h2::h2_error::{ErrorCode, H2Error};
+
+// ref <https://www.rfc-editor.org/rfc/rfc9113#section-4>
+pub(crate) const FRAME_HEADER_SIZE: usize = 9;
+// the largest payload which can be sent or received without SETTINGS_MAX_FRAME_SIZE
+pub(crate) const DEFAULT_MAX_FRAME_SIZE: usize = 16_384;
+pub(crate) const MAX_FRAME_SIZE_LIMIT: usize = (1 << 24) - 1;
+
+pub(crate) const DATA: u8 = 0x0;
+pub(crate) const HEADERS: u8 = 0x1;
+pub(crate) const PRIORITY: u8 = 0x2;
+pub(crate) const RST_STREAM: u8 = 0x3;
+pub(crate) const SETTINGS: u8 = 0x4;
+pub(crate) const PUSH_PROMISE: u8 = 0x5;
+pub(crate) const PING: u8 = 0x6;
+pub(crate) const GOAWAY: u8 = 0x7;
+pub(crate) const WINDOW_UPDATE: u8 = 0x8;
+pub(crate) const CONTINUATION: u8 = 0x9;
+
+pub(crate) const FLAG_END_STREAM: u8 = 0x1;
+pub(crate) const FLAG_ACK: u8 = 0x1;
+pub(crate) const FLAG_END_HEADERS: u8 = 0x4;
+pub(crate) const FLAG_PADDED: u8 = 0x8;
+pub(crate) const FLAG_PRIORITY: u8 = 0x20;
+
+pub(crate) const SETTINGS_HEADER_TABLE_SIZE: u16 = 0x1;
+pub(crate) const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+pub(crate) const SETTINGS_MAX_CONCURRENT_STREAMS: u16 = 0x3;
+pub(crate) const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+pub(crate) const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+pub(crate) const SETTINGS_MAX_HEADER_LIST_SIZE: u16 = 0x6;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct FrameHeader {
+    pub length: usize,
+    pub frame_type: u8,
+    pub flags: u8,
+    pub stream_id: u32,
+}
+
+impl FrameHeader {
+    pub fn parse(src: &[u8]) -> Option<FrameHeader> {
+        if src.len() < FRAME_HEADER_SIZE {
+            return None;
+        }
+
+        Some(FrameHeader {
+            length: (src[0] as usize) << 16 | (src[1] as usize) << 8 | src[2] as usize,
+            frame_type: src[3],
+            flags: src[4],
+            // the reserved bit is ignored
+            stream_id: read_u32(&src[5..9]) & 0x7fff_ffff,
+        })
+    }
+
+    pub fn has_flag(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+}
+
+pub(crate) fn read_u32(src: &[u8]) -> u32 {
+    u32::from_be_bytes([src[0], src[1], src[2], src[3]])
+}
+
+// the payload without the padding of DATA or HEADERS
+pub(crate) fn strip_padding<'a>(
+    frame_header: &FrameHeader,
+    payload: &'a [u8],
+) -> Result<&'a [u8], H2Error> {
+    if !frame_header.has_flag(FLAG_PADDED) {
+        return Ok(payload);
+    }
+
+    let error = H2Error::Connection(ErrorCode::ProtocolError);
+    let pad_length = *payload.first().ok_or(error)? as usize;
+    if pad_length >= payload.len() {
+        return Err(error);
+    }
+
+    Ok(&payload[1..payload.len() - pad_length])
+}
+
+pub(crate) fn write_frame(
+    dst: &mut Vec<u8>,
+    frame_type: u8,
+    flags: u8,
+    stream_id: u32,
+    payload: &[u8],
+) {
+    let length = payload.len() as u32;
+    dst.extend_from_slice(&length.to_be_bytes()[1..]);
+    dst.push(frame_type);
+    dst.push(flags);
+    dst.extend_from_slice(&stream_id.to_be_bytes());
+    dst.extend_from_slice(payload);
+}
+
+// a block larger than the frame size is continued with CONTINUATION frames
+pub(crate) fn write_headers(
+    dst: &mut Vec<u8>,
+    stream_id: u32,
+    block: &[u8],
+    end_stream: bool,
+    max_frame_size: usize,
+) {
+    let mut chunks = block.chunks(max_frame_size).peekable();
+    let mut frame_type = HEADERS;
+    let mut flags = if end_stream { FLAG_END_STREAM } else { 0 };
+
+    // an empty block is still sent in a frame
+    if chunks.peek().is_none() {
+        write_frame(dst, frame_type, flags | FLAG_END_HEADERS, stream_id, &[]);
+        return;
+    }
+
+    while let Some(chunk) = chunks.next() {
+        if chunks.peek().is_none() {
+            flags |= FLAG_END_HEADERS;
+        }
+        write_frame(dst, frame_type, flags, stream_id, chunk);
+
+        frame_type = CONTINUATION;
+        flags = 0;
+    }
+}
+
+pub(crate) fn write_settings(dst: &mut Vec<u8>, settings: &[(u16, u32)]) {
+    let mut payload = Vec::with_capacity(settings.len() * 6);
+    for (id, value) in settings {
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(&value.to_be_bytes());
+    }
+
+    write_frame(dst, SETTINGS, 0, 0, &payload);
+}
+
+pub(crate) fn write_window_update(dst: &mut Vec<u8>, stream_id: u32, increment: u32) {
+    write_frame(dst, WINDOW_UPDATE, 0, stream_id, &increment.to_be_bytes());
+}
+
+pub(crate) fn write_rst_stream(dst: &mut Vec<u8>, stream_id: u32, error_code: ErrorCode) {
+    write_frame(dst, RST_STREAM, 0, stream_id, &(error_code as u32).to_be_bytes());
+}
+
+pub(crate) fn write_goaway(dst: &mut Vec<u8>, last_stream_id: u32, error_code: ErrorCode) {
+    let mut payload = Vec::with_capacity(8);
+    payload.extend_from_slice(&last_stream_id.to_be_bytes());
+    payload.extend_from_slice(&(error_code as u32).to_be_bytes());
+
+    write_frame(dst, GOAWAY, 0, 0, &payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame() {
+        let mut dst = vec![];
+        write_frame(&mut dst, DATA, FLAG_END_STREAM, 3, b"hello");
+        assert_eq!(dst[..FRAME_HEADER_SIZE], [0, 0, 5, DATA, FLAG_END_STREAM, 0, 0, 0, 3]);
+
+        let frame_header = FrameHeader::parse(&dst).unwrap();
+        assert_eq!(frame_header.length, 5);
+        assert_eq!(frame_header.stream_id, 3);
+        assert!(frame_header.has_flag(FLAG_END_STREAM));
+
+        // pad length, data and padding
+        let padded = FrameHeader {
+            flags: FLAG_PADDED,
+            ..frame_header
+        };
+        assert_eq!(strip_padding(&padded, &[2, b'a', 0, 0]).unwrap(), b"a");
+        assert_eq!(strip_padding(&padded, &[3, 0, 0, 0]).unwrap(), b"");
+        assert!(strip_padding(&padded, &[4, b'a', 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_write_headers() {
+        let mut dst = vec![];
+        write_headers(&mut dst, 1, &[0; 10], true, 4);
+
+        let mut offset = 0;
+        let mut frames = vec![];
+        while let Some(frame_header) = FrameHeader::parse(&dst[offset..]) {
+            frames.push((frame_header.frame_type, frame_header.flags, frame_header.length));
+            offset += FRAME_HEADER_SIZE + frame_header.length;
+        }
+        assert_eq!(
+            frames,
+            [
+                (HEADERS, FLAG_END_STREAM, 4),
+                (CONTINUATION, 0, 4),
+                (CONTINUATION, FLAG_END_HEADERS, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1160 @@
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryInto;
+use std::iter;
+use std::sync::Arc;
+
+use crate::h2::frame::{
+    self, FrameHeader, CONTINUATION, DATA, DEFAULT_MAX_FRAME_SIZE, FLAG_ACK, FLAG_END_HEADERS,
+    FLAG_END_STREAM, FLAG_PRIORITY, FRAME_HEADER_SIZE, GOAWAY, HEADERS, MAX_FRAME_SIZE_LIMIT, PING,
+    PRIORITY, PUSH_PROMISE, RST_STREAM, SETTINGS, SETTINGS_ENABLE_PUSH, SETTINGS_HEADER_TABLE_SIZE,
+    SETTINGS_INITIAL_WINDOW_SIZE, SETTINGS_MAX_CONCURRENT_STREAMS, SETTINGS_MAX_FRAME_SIZE,
+    SETTINGS_MAX_HEADER_LIST_SIZE, WINDOW_UPDATE,
+};
+use crate::h2::h2_error::{ErrorCode, H2Error};
+use crate::h2::h2_response::CONNECTION_HEADERS;
+use crate::h2::h2_stream::{H2Stream, StreamState};
+use crate::h2::hpack::{HeaderList, HpackDecoder, HpackEncoder};
+use crate::h2::H2Response;
+use crate::http::method::HttpMethod;
+use crate::http::{
+    HttpError, HttpRequest, HttpRequestBody, HttpRequestHeader, HttpStatus, HttpVersion, Protocol,
+    ReadHeaderAs,
+};
+use crate::route::Router;
+use crate::server::ServerConfig;
+use crate::util::base64::base64_url_decode;
+use crate::worker::request_parser::get_max_body_size;
+
+// sent by the client before any frame
+pub(crate) const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+// the answer to 'Upgrade: h2c', which is followed by the frames
+pub(crate) const SWITCHING_TO_H2C: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n";
+
+const DEFAULT_WINDOW_SIZE: i64 = 65_535;
+const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
+// the dynamic table of the decoder, the default of SETTINGS_HEADER_TABLE_SIZE
+const HEADER_TABLE_SIZE: usize = 4096;
+// the output is made up to this size at once, so a large body is not copied into the memory
+const OUTPUT_CHUNK_SIZE: usize = 64 * 1024;
+// the frames in flight for the streams reset by the server are ignored
+const MAX_RESET_STREAMS: usize = 128;
+
+// a header block which is continued with CONTINUATION
+struct PendingHeaders {
+    stream_id: u32,
+    block: Vec<u8>,
+    end_stream: bool,
+}
+
+// the pseudo headers and the headers of a request
+struct RequestFields {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    count: usize,
+}
+
+// the server side of a HTTP/2 connection, without any I/O.
+// the bytes from the client are pushed, the complete requests are taken and answered, and the
+// frames to send are polled. so both of the worker and the reactor can drive it.
+// ref <https://www.rfc-editor.org/rfc/rfc9113>
+pub(crate) struct H2Connection {
+    server_config: Arc<ServerConfig>,
+    router: Arc<Router>,
+    input: Vec<u8>,
+    is_preface_received: bool,
+    decoder: HpackDecoder,
+    encoder: HpackEncoder,
+    streams: HashMap<u32, H2Stream>,
+    // the streams which have the response body to send, in the order of their turns
+    sending_streams: VecDeque<u32>,
+    reset_streams: VecDeque<u32>,
+    // the largest stream id opened by the client
+    last_stream_id: u32,
+    pending_headers: Option<PendingHeaders>,
+    ready_requests: Vec<(u32, HttpRequest)>,
+    // how many bytes the client can receive on the connection
+    send_window: i64,
+    // the settings of the client
+    initial_window_size: i64,
+    max_frame_size: usize,
+    output: Vec<u8>,
+    // GOAWAY has been sent with the error code
+    go_away: Option<ErrorCode>,
+    is_peer_going_away: bool,
+}
+
+impl H2Connection {
+    pub fn new(server_config: Arc<ServerConfig>, router: Arc<Router>) -> Self {
+        let mut output = vec![];
+        frame::write_settings(
+            &mut output,
+            &[
+                (SETTINGS_MAX_CONCURRENT_STREAMS, server_config.max_concurrent_streams),
+                (SETTINGS_MAX_HEADER_LIST_SIZE, server_config.max_header_size as u32),
+            ],
+        );
+
+        H2Connection {
+            server_config,
+            router,
+            input: vec![],
+            is_preface_received: false,
+            decoder: HpackDecoder::new(HEADER_TABLE_SIZE),
+            encoder: HpackEncoder::new(),
+            streams: HashMap::new(),
+            sending_streams: VecDeque::new(),
+            reset_streams: VecDeque::new(),
+            last_stream_id: 0,
+            pending_headers: None,
+            ready_requests: vec![],
+            send_window: DEFAULT_WINDOW_SIZE,
+            initial_window_size: DEFAULT_WINDOW_SIZE,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            output,
+            go_away: None,
+            is_peer_going_away: false,
+        }
+    }
+
+    // the request asks to upgrade from HTTP/1.1 with 'Upgrade: h2c', which is honored only over TCP.
+    // none if HTTP/2 is disabled or the request is not a valid upgrade.
+    pub fn upgrade(
+        server_config: Arc<ServerConfig>,
+        router: Arc<Router>,
+        http_request: &HttpRequest,
+    ) -> Option<Self> {
+        if !server_config.http2 {
+            return None;
+        }
+
+        let settings = get_upgrade_settings(http_request)?;
+        let mut connection = H2Connection::new(server_config, router);
+        connection.apply_settings(&settings).ok()?;
+
+        Some(connection)
+    }
+
+    // the request which has asked to upgrade is answered on the stream 1
+    pub fn open_upgraded_stream(&mut self, http_request: HttpRequest) {
+        let mut stream = H2Stream::new(self.initial_window_size);
+        stream.state = StreamState::HalfClosedRemote;
+        self.streams.insert(1, stream);
+        self.last_stream_id = 1;
+        self.ready_requests.push((1, http_request));
+    }
+
+    // the bytes from the client, the frames are handled as soon as they are complete
+    pub fn push(&mut self, bytes: &[u8]) {
+        if self.is_closing() {
+            return;
+        }
+
+        self.input.extend_from_slice(bytes);
+        if let Err(error) = self.process_input() {
+            eprintln!("[error] HTTP/2 {}", error);
+            let error_code = match error {
+                H2Error::Connection(error_code) | H2Error::Stream(_, error_code) => error_code,
+            };
+            self.close_with(error_code);
+        }
+    }
+
+    // the requests whose bodies have arrived, each must be answered by 'respond'
+    pub fn take_requests(&mut self) -> Vec<(u32, HttpRequest)> {
+        std::mem::take(&mut self.ready_requests)
+    }
+
+    // the response is dropped if the client has reset the stream
+    pub fn respond(&mut self, stream_id: u32, response: H2Response) {
+        let stream = match self.streams.get_mut(&stream_id) {
+            Some(stream) if stream.response.is_none() => stream,
+            _ => return,
+        };
+
+        let status = response.get_status().to_string();
+        let fields = iter::once((":status", status.as_str())).chain(
+            response
+                .get_headers()
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+        let mut block = vec![];
+        self.encoder.encode(fields, &mut block);
+
+        let end_stream = response.get_remaining() == 0;
+        frame::write_headers(&mut self.output, stream_id, &block, end_stream, self.max_frame_size);
+        if end_stream {
+            self.close_stream(stream_id);
+        } else {
+            stream.response = Some(response);
+            self.sending_streams.push_back(stream_id);
+        }
+    }
+
+    // the frames to send, the response bodies are added as far as the flow control allows
+    pub fn poll_output(&mut self) -> Vec<u8> {
+        self.write_data();
+        std::mem::take(&mut self.output)
+    }
+
+    // the frames which are made but not polled yet, without the response bodies
+    pub fn get_output_size(&self) -> usize {
+        self.output.len()
+    }
+
+    // no stream is open, e.g. to close the connection after the keep-alive timeout
+    pub fn is_idle(&self) -> bool {
+        self.streams.is_empty() && self.pending_headers.is_none()
+    }
+
+    // the server is shutting down, the streams which have started are still answered
+    pub fn go_away(&mut self) {
+        if self.go_away.is_none() {
+            frame::write_goaway(&mut self.output, self.last_stream_id, ErrorCode::NoError);
+            self.go_away = Some(ErrorCode::NoError);
+        }
+    }
+
+    // the connection can be closed once the output is written
+    pub fn is_finished(&self) -> bool {
+        if !self.output.is_empty() {
+            return false;
+        }
+
+        self.is_closing()
+            || ((self.go_away.is_some() || self.is_peer_going_away) && self.streams.is_empty())
+    }
+
+    // closed by an error, nothing is read or answered any more
+    fn is_closing(&self) -> bool {
+        matches!(self.go_away, Some(error_code) if error_code != ErrorCode::NoError)
+    }
+
+    fn close_with(&mut self, error_code: ErrorCode) {
+        frame::write_goaway(&mut self.output, self.last_stream_id, error_code);
+        self.go_away = Some(error_code);
+
+        self.streams.clear();
+        self.sending_streams.clear();
+        self.ready_requests.clear();
+        self.pending_headers = None;
+    }
+
+    fn process_input(&mut self) -> Result<(), H2Error> {
+        let input = std::mem::take(&mut self.input);
+        let mut offset = 0;
+
+        if !self.is_preface_received {
+            let size = input.len().min(PREFACE.len());
+            if input[..size] != PREFACE[..size] {
+                return Err(H2Error::Connection(ErrorCode::ProtocolError));
+            }
+            if size < PREFACE.len() {
+                self.input = input;
+                return Ok(());
+            }
+
+            offset = PREFACE.len();
+            self.is_preface_received = true;
+        }
+
+        let result = loop {
+            let frame_header = if let Some(frame_header) = FrameHeader::parse(&input[offset..]) {
+                frame_header
+            } else {
+                break Ok(());
+            };
+            if frame_header.length > DEFAULT_MAX_FRAME_SIZE {
+                break Err(H2Error::Connection(ErrorCode::FrameSizeError));
+            }
+
+            let end = offset + FRAME_HEADER_SIZE + frame_header.length;
+            if input.len() < end {
+                break Ok(());
+            }
+            let payload = &input[offset + FRAME_HEADER_SIZE..end];
+            offset = end;
+
+            match self.handle_frame(&frame_header, payload) {
+                Ok(()) => {}
+                Err(H2Error::Stream(stream_id, error_code)) => {
+                    self.reset_stream(stream_id, error_code)
+                }
+                Err(error) => break Err(error),
+            }
+        };
+
+        self.input = input;
+        self.input.drain(..offset);
+        result
+    }
+
+    fn handle_frame(&mut self, frame_header: &FrameHeader, payload: &[u8]) -> Result<(), H2Error> {
+        // a header block must not be interleaved with any other frame
+        if let Some(pending_headers) = &self.pending_headers {
+            if frame_header.frame_type != CONTINUATION
+                || frame_header.stream_id != pending_headers.stream_id
+            {
+                return Err(H2Error::Connection(ErrorCode::ProtocolError));
+            }
+        }
+
+        match frame_header.frame_type {
+            DATA => self.handle_data(frame_header, payload),
+            HEADERS => self.handle_headers(frame_header, payload),
+            PRIORITY => handle_priority(frame_header),
+            RST_STREAM => self.handle_rst_stream(frame_header),
+            SETTINGS => self.handle_settings(frame_header, payload),
+            // a client must not push
+            PUSH_PROMISE => Err(H2Error::Connection(ErrorCode::ProtocolError)),
+            PING => self.handle_ping(frame_header, payload),
+            GOAWAY => self.handle_goaway(frame_header),
+            WINDOW_UPDATE => self.handle_window_update(frame_header, payload),
+            CONTINUATION => self.handle_continuation(frame_header, payload),
+            // the unknown types are ignored
+            _ => Ok(()),
+        }
+    }
+
+    fn handle_data(&mut self, frame_header: &FrameHeader, payload: &[u8]) -> Result<(), H2Error> {
+        let stream_id = frame_header.stream_id;
+        if stream_id == 0 {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+
+        // the body size is limited by itself, so the window of the connection is given back at once
+        let length = frame_header.length as u32;
+        if length > 0 {
+            frame::write_window_update(&mut self.output, 0, length);
+        }
+
+        let data = frame::strip_padding(frame_header, payload)?;
+        let stream = match self.streams.get_mut(&stream_id) {
+            Some(stream) if stream.state == StreamState::Open => stream,
+            Some(_) => return Err(H2Error::Stream(stream_id, ErrorCode::StreamClosed)),
+            None => return self.check_closed_stream(stream_id),
+        };
+
+        let end_stream = frame_header.has_flag(FLAG_END_STREAM);
+        if !stream.is_rejected {
+            if stream.content_length.is_some_and(|length| stream.body.len() + data.len() > length) {
+                return Err(H2Error::Stream(stream_id, ErrorCode::ProtocolError));
+            }
+
+            if stream.body.len() + data.len() > stream.max_body_size {
+                self.reject(stream_id, HttpStatus::PAYLOAD_TOO_LARGE);
+            } else {
+                stream.body.extend_from_slice(data);
+            }
+        }
+
+        if end_stream {
+            return self.finish_request(stream_id);
+        }
+        if length > 0 && self.streams.contains_key(&stream_id) {
+            frame::write_window_update(&mut self.output, stream_id, length);
+        }
+
+        Ok(())
+    }
+
+    fn handle_headers(&mut self, frame_header: &FrameHeader, payload: &[u8]) -> Result<(), H2Error> {
+        let stream_id = frame_header.stream_id;
+        // the streams of a client have odd ids
+        if stream_id == 0 || stream_id.is_multiple_of(2) {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+
+        let mut fragment = frame::strip_padding(frame_header, payload)?;
+        if frame_header.has_flag(FLAG_PRIORITY) {
+            if fragment.len() < 5 {
+                return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+            }
+            // the priority is not used, but a stream can not depend on itself
+            if frame::read_u32(fragment) & 0x7fff_ffff == stream_id {
+                return Err(H2Error::Connection(ErrorCode::ProtocolError));
+            }
+            fragment = &fragment[5..];
+        }
+
+        let end_stream = frame_header.has_flag(FLAG_END_STREAM);
+        if frame_header.has_flag(FLAG_END_HEADERS) {
+            return self.handle_header_block(stream_id, fragment, end_stream);
+        }
+
+        self.pending_headers = Some(PendingHeaders {
+            stream_id,
+            block: fragment.to_vec(),
+            end_stream,
+        });
+        Ok(())
+    }
+
+    fn handle_continuation(
+        &mut self,
+        frame_header: &FrameHeader,
+        payload: &[u8],
+    ) -> Result<(), H2Error> {
+        let mut pending_headers = self
+            .pending_headers
+            .take()
+            .ok_or(H2Error::Connection(ErrorCode::ProtocolError))?;
+
+        pending_headers.block.extend_from_slice(payload);
+        // the block is decoded only when it is complete, so its size is limited while it arrives
+        if pending_headers.block.len() > self.server_config.max_header_size {
+            return Err(H2Error::Connection(ErrorCode::EnhanceYourCalm));
+        }
+
+        if frame_header.has_flag(FLAG_END_HEADERS) {
+            return self.handle_header_block(
+                pending_headers.stream_id,
+                &pending_headers.block,
+                pending_headers.end_stream,
+            );
+        }
+
+        self.pending_headers = Some(pending_headers);
+        Ok(())
+    }
+
+    fn handle_header_block(
+        &mut self,
+        stream_id: u32,
+        block: &[u8],
+        end_stream: bool,
+    ) -> Result<(), H2Error> {
+        // every block is decoded to keep the table in sync with the client
+        let header_list = self.decoder.decode(block, self.server_config.max_header_size)?;
+
+        // the trailers, which are not used
+        if let Some(stream) = self.streams.get(&stream_id) {
+            if stream.state != StreamState::Open {
+                return Err(H2Error::Stream(stream_id, ErrorCode::StreamClosed));
+            }
+            if !end_stream || header_list.fields.iter().any(|(name, _)| name.starts_with(b":")) {
+                return Err(H2Error::Stream(stream_id, ErrorCode::ProtocolError));
+            }
+            return self.finish_request(stream_id);
+        }
+
+        if stream_id <= self.last_stream_id {
+            if self.reset_streams.contains(&stream_id) {
+                return Ok(());
+            }
+            return Err(H2Error::Connection(ErrorCode::StreamClosed));
+        }
+
+        self.last_stream_id = stream_id;
+        if self.go_away.is_some()
+            || self.streams.len() >= self.server_config.max_concurrent_streams as usize
+        {
+            return Err(H2Error::Stream(stream_id, ErrorCode::RefusedStream));
+        }
+
+        self.streams.insert(stream_id, H2Stream::new(self.initial_window_size));
+        self.open_request(stream_id, header_list)?;
+
+        if end_stream {
+            return self.finish_request(stream_id);
+        }
+        Ok(())
+    }
+
+    // the header of a new stream, a request over the limits is answered with an error at once
+    fn open_request(&mut self, stream_id: u32, header_list: HeaderList) -> Result<(), H2Error> {
+        if header_list.is_too_large {
+            self.reject(stream_id, HttpStatus::REQUEST_HEADER_FIELDS_TOO_LARGE);
+            return Ok(());
+        }
+
+        let request_fields = parse_request_fields(stream_id, header_list)?;
+        match self.make_request_header(request_fields) {
+            Ok((header, max_body_size)) => {
+                if let Some(stream) = self.streams.get_mut(&stream_id) {
+                    stream.content_length = header.get_content_length();
+                    stream.max_body_size = max_body_size;
+                    stream.request_header = Some(header);
+                }
+            }
+            Err(error) => self.reject(stream_id, error.get_status()),
+        }
+
+        Ok(())
+    }
+
+    // returns the header and the body limit of the route
+    fn make_request_header(
+        &self,
+        request_fields: RequestFields,
+    ) -> Result<(HttpRequestHeader, usize), HttpError> {
+        if request_fields.count > self.server_config.max_header_count {
+            return Err(HttpError::HeaderTooLarge);
+        }
+        if request_fields.path.len() > self.server_config.max_uri_length {
+            return Err(HttpError::UriTooLong);
+        }
+
+        let method: HttpMethod = request_fields.method.try_into()?;
+        let header = HttpRequestHeader::new(
+            method,
+            request_fields.path,
+            HttpVersion::new(Protocol::HTTP, 2, 0),
+            request_fields.headers,
+        );
+
+        let max_body_size = get_max_body_size(&self.router, &self.server_config, &header)?;
+        if header.get_content_length().unwrap_or(0) > max_body_size {
+            return Err(HttpError::PayloadTooLarge);
+        }
+
+        Ok((header, max_body_size))
+    }
+
+    // the client has ended the stream
+    fn finish_request(&mut self, stream_id: u32) -> Result<(), H2Error> {
+        let stream = if let Some(stream) = self.streams.get_mut(&stream_id) {
+            stream
+        } else {
+            return Ok(());
+        };
+
+        stream.state = StreamState::HalfClosedRemote;
+        let header = if let Some(header) = stream.request_header.take() {
+            header
+        } else {
+            // rejected already
+            return Ok(());
+        };
+
+        let body = std::mem::take(&mut stream.body);
+        if stream.content_length.is_some_and(|length| length != body.len()) {
+            return Err(H2Error::Stream(stream_id, ErrorCode::ProtocolError));
+        }
+
+        let body = if body.is_empty() {
+            None
+        } else {
+            Some(HttpRequestBody::new(body))
+        };
+        match HttpRequest::new(header, body) {
+            Ok(http_request) => self.ready_requests.push((stream_id, http_request)),
+            Err(error) => self.reject(stream_id, error.get_status()),
+        }
+
+        Ok(())
+    }
+
+    // answer with an error, the rest of the request is discarded
+    fn reject(&mut self, stream_id: u32, status: HttpStatus) {
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            stream.is_rejected = true;
+            stream.request_header = None;
+            stream.body = vec![];
+        }
+
+        self.respond(stream_id, H2Response::new_with(status));
+    }
+
+    fn handle_rst_stream(&mut self, frame_header: &FrameHeader) -> Result<(), H2Error> {
+        let stream_id = frame_header.stream_id;
+        if stream_id == 0 || stream_id > self.last_stream_id {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+        if frame_header.length != 4 {
+            return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+        }
+
+        self.streams.remove(&stream_id);
+        self.ready_requests.retain(|(ready_id, _)| *ready_id != stream_id);
+        Ok(())
+    }
+
+    fn handle_settings(&mut self, frame_header: &FrameHeader, payload: &[u8]) -> Result<(), H2Error> {
+        if frame_header.stream_id != 0 {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+
+        if frame_header.has_flag(FLAG_ACK) {
+            if frame_header.length != 0 {
+                return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+            }
+            return Ok(());
+        }
+
+        self.apply_settings(payload)?;
+        frame::write_frame(&mut self.output, SETTINGS, FLAG_ACK, 0, &[]);
+        Ok(())
+    }
+
+    fn apply_settings(&mut self, payload: &[u8]) -> Result<(), H2Error> {
+        if !payload.len().is_multiple_of(6) {
+            return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+        }
+
+        for setting in payload.chunks(6) {
+            let id = u16::from_be_bytes([setting[0], setting[1]]);
+            let value = frame::read_u32(&setting[2..]);
+
+            match id {
+                SETTINGS_HEADER_TABLE_SIZE => self.encoder.set_max_table_size(value as usize),
+                SETTINGS_ENABLE_PUSH if value > 1 => {
+                    return Err(H2Error::Connection(ErrorCode::ProtocolError));
+                }
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    if value as i64 > MAX_WINDOW_SIZE {
+                        return Err(H2Error::Connection(ErrorCode::FlowControlError));
+                    }
+
+                    // the windows of the open streams are moved by the difference
+                    let delta = value as i64 - self.initial_window_size;
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                        if stream.send_window > MAX_WINDOW_SIZE {
+                            return Err(H2Error::Connection(ErrorCode::FlowControlError));
+                        }
+                    }
+                    self.initial_window_size = value as i64;
+                }
+                SETTINGS_MAX_FRAME_SIZE => {
+                    let value = value as usize;
+                    if !(DEFAULT_MAX_FRAME_SIZE..=MAX_FRAME_SIZE_LIMIT).contains(&value) {
+                        return Err(H2Error::Connection(ErrorCode::ProtocolError));
+                    }
+                    self.max_frame_size = value;
+                }
+                // the others do not matter to a server
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_ping(&mut self, frame_header: &FrameHeader, payload: &[u8]) -> Result<(), H2Error> {
+        if frame_header.stream_id != 0 {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+        if frame_header.length != 8 {
+            return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+        }
+
+        if !frame_header.has_flag(FLAG_ACK) {
+            frame::write_frame(&mut self.output, PING, FLAG_ACK, 0, payload);
+        }
+        Ok(())
+    }
+
+    // the client will not open any stream, the open ones are still answered
+    fn handle_goaway(&mut self, frame_header: &FrameHeader) -> Result<(), H2Error> {
+        if frame_header.stream_id != 0 {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+        if frame_header.length < 8 {
+            return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+        }
+
+        self.is_peer_going_away = true;
+        Ok(())
+    }
+
+    fn handle_window_update(
+        &mut self,
+        frame_header: &FrameHeader,
+        payload: &[u8],
+    ) -> Result<(), H2Error> {
+        if frame_header.length != 4 {
+            return Err(H2Error::Connection(ErrorCode::FrameSizeError));
+        }
+
+        let stream_id = frame_header.stream_id;
+        let increment = (frame::read_u32(payload) & 0x7fff_ffff) as i64;
+        if stream_id == 0 {
+            if increment == 0 {
+                return Err(H2Error::Connection(ErrorCode::ProtocolError));
+            }
+            self.send_window += increment;
+            if self.send_window > MAX_WINDOW_SIZE {
+                return Err(H2Error::Connection(ErrorCode::FlowControlError));
+            }
+            return Ok(());
+        }
+
+        if increment == 0 {
+            return Err(H2Error::Stream(stream_id, ErrorCode::ProtocolError));
+        }
+        match self.streams.get_mut(&stream_id) {
+            Some(stream) => {
+                stream.send_window += increment;
+                if stream.send_window > MAX_WINDOW_SIZE {
+                    return Err(H2Error::Stream(stream_id, ErrorCode::FlowControlError));
+                }
+            }
+            None if stream_id > self.last_stream_id => {
+                return Err(H2Error::Connection(ErrorCode::ProtocolError));
+            }
+            // the response may have been sent already
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    // a frame for a stream which is not open
+    fn check_closed_stream(&self, stream_id: u32) -> Result<(), H2Error> {
+        if stream_id > self.last_stream_id {
+            return Err(H2Error::Connection(ErrorCode::ProtocolError));
+        }
+        // the client has sent it before the reset arrives
+        if self.reset_streams.contains(&stream_id) {
+            return Ok(());
+        }
+
+        Err(H2Error::Stream(stream_id, ErrorCode::StreamClosed))
+    }
+
+    fn reset_stream(&mut self, stream_id: u32, error_code: ErrorCode) {
+        frame::write_rst_stream(&mut self.output, stream_id, error_code);
+        self.streams.remove(&stream_id);
+        self.ready_requests.retain(|(ready_id, _)| *ready_id != stream_id);
+        self.remember_reset(stream_id);
+    }
+
+    fn remember_reset(&mut self, stream_id: u32) {
+        self.reset_streams.push_back(stream_id);
+        if self.reset_streams.len() > MAX_RESET_STREAMS {
+            self.reset_streams.pop_front();
+        }
+    }
+
+    // the response has been sent
+    fn close_stream(&mut self, stream_id: u32) {
+        if let Some(stream) = self.streams.remove(&stream_id) {
+            // the rest of the request is not needed
+            if stream.state == StreamState::Open {
+                frame::write_rst_stream(&mut self.output, stream_id, ErrorCode::NoError);
+                self.remember_reset(stream_id);
+            }
+        }
+    }
+
+    // the streams take turns to send a frame, until the windows or the chunk are used up
+    fn write_data(&mut self) {
+        let mut blocked_count = 0;
+
+        while self.output.len() < OUTPUT_CHUNK_SIZE
+            && self.send_window > 0
+            && blocked_count < self.sending_streams.len()
+        {
+            let stream_id = if let Some(stream_id) = self.sending_streams.pop_front() {
+                stream_id
+            } else {
+                break;
+            };
+
+            let stream = match self.streams.get_mut(&stream_id) {
+                Some(stream) => stream,
+                // reset by the client
+                None => continue,
+            };
+            let response = if let Some(response) = &mut stream.response {
+                response
+            } else {
+                continue;
+            };
+
+            let size = self
+                .send_window
+                .min(stream.send_window)
+                .min(self.max_frame_size as i64);
+            if size <= 0 {
+                blocked_count += 1;
+                self.sending_streams.push_back(stream_id);
+                continue;
+            }
+            blocked_count = 0;
+
+            let chunk = match response.read_chunk(size as usize) {
+                Ok(chunk) => chunk,
+                Err(error) => {
+                    eprintln!("[error] error while respond: {:?}", error);
+                    self.reset_stream(stream_id, ErrorCode::InternalError);
+                    continue;
+                }
+            };
+
+            let end_stream = response.get_remaining() == 0;
+            let flags = if end_stream { FLAG_END_STREAM } else { 0 };
+            frame::write_frame(&mut self.output, DATA, flags, stream_id, &chunk);
+            self.send_window -= chunk.len() as i64;
+            stream.send_window -= chunk.len() as i64;
+
+            if end_stream {
+                self.close_stream(stream_id);
+            } else {
+                self.sending_streams.push_back(stream_id);
+            }
+        }
+    }
+}
+
+// the SETTINGS payload sent with 'HTTP2-Settings'
+// ref <https://www.rfc-editor.org/rfc/rfc7540#section-3.2>
+fn get_upgrade_settings(http_request: &HttpRequest) -> Option<Vec<u8>> {
+    let upgrade: String = http_request.get_header("upgrade")?;
+    let connection: String = http_request.get_header("connection")?;
+    let settings: String = http_request.get_header("http2-settings")?;
+
+    let has_token = |value: &str, token: &str| {
+        value.split(',').any(|item| item.trim().eq_ignore_ascii_case(token))
+    };
+    if !has_token(&upgrade, "h2c")
+        || !has_token(&connection, "upgrade")
+        || !has_token(&connection, "http2-settings")
+    {
+        return None;
+    }
+
+    base64_url_decode(&settings)
+}
+
+fn handle_priority(frame_header: &FrameHeader) -> Result<(), H2Error> {
+    if frame_header.stream_id == 0 {
+        return Err(H2Error::Connection(ErrorCode::ProtocolError));
+    }
+    if frame_header.length != 5 {
+        return Err(H2Error::Stream(frame_header.stream_id, ErrorCode::FrameSizeError));
+    }
+
+    Ok(())
+}
+
+// a malformed request resets the stream.
+// ref <https://www.rfc-editor.org/rfc/rfc9113#section-8.2>
+fn parse_request_fields(stream_id: u32, header_list: HeaderList) -> Result<RequestFields, H2Error> {
+    let malformed = H2Error::Stream(stream_id, ErrorCode::ProtocolError);
+    let (mut method, mut scheme, mut path, mut authority) = (None, None, None, None);
+    let mut headers: HashMap<String, String> = HashMap::new();
+    // the pseudo headers take the place of the request line, which is not counted in HTTP/1
+    let count = header_list
+        .fields
+        .iter()
+        .filter(|(name, _)| !name.starts_with(b":"))
+        .count();
+    let mut is_pseudo_ended = false;
+
+    for (name, value) in header_list.fields {
+        if value.iter().any(|byte| matches!(byte, b'\0' | b'\r' | b'\n')) {
+            return Err(malformed);
+        }
+        let value = String::from_utf8_lossy(&value).to_string();
+
+        if let Some(pseudo_name) = name.strip_prefix(b":") {
+            if is_pseudo_ended {
+                return Err(malformed);
+            }
+
+            let field = match pseudo_name {
+                b"method" => &mut method,
+                b"scheme" => &mut scheme,
+                b"path" => &mut path,
+                b"authority" => &mut authority,
+                _ => return Err(malformed),
+            };
+            if field.replace(value).is_some() {
+                return Err(malformed);
+            }
+            continue;
+        }
+        is_pseudo_ended = true;
+
+        // the names must be tokens in lower case
+        if name.is_empty() || !name.iter().all(|byte| is_token_char(*byte) && !byte.is_ascii_uppercase())
+        {
+            return Err(malformed);
+        }
+        let name = String::from_utf8_lossy(&name).to_string();
+        if CONNECTION_HEADERS.contains(&name.as_str()) || (name == "te" && value != "trailers") {
+            return Err(malformed);
+        }
+
+        match headers.entry(name) {
+            Entry::Occupied(mut entry) => {
+                let separator = if entry.key() == "cookie" { "; " } else { ", " };
+                entry.get_mut().push_str(separator);
+                entry.get_mut().push_str(&value);
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+            }
+        }
+    }
+
+    let (method, path) = match (method, scheme, path) {
+        (Some(method), Some(_), Some(path)) if !path.is_empty() => (method, path),
+        _ => return Err(malformed),
+    };
+    if let Some(authority) = authority {
+        headers.entry("host".to_string()).or_insert(authority);
+    }
+
+    Ok(RequestFields {
+        method,
+        path,
+        headers,
+        count,
+    })
+}
+
+fn is_token_char(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::h2::test_client::TestClient;
+    use crate::http::{ContentType, HttpResponseBuilder};
+    use crate::route::{ActionRoute, RouterBuilder};
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    fn make_router() -> Arc<Router> {
+        let echo = ActionRoute::post_async("/echo", |http_request| async move {
+            let body = http_request.get_body().map(|body| body.get_raw().to_vec());
+            HttpResponseBuilder::new()
+                .body(ContentType::TEXT_PLAIN, body.unwrap_or_default())
+                .build()
+                .unwrap()
+        });
+
+        let router = RouterBuilder::new()
+            .append_route(Box::new(ActionRoute::get("/hello", || "hello").unwrap()))
+            .append_route(Box::new(ActionRoute::get("/big", || vec![7_u8; 100_000]).unwrap()))
+            .append_route(Box::new(echo.unwrap().max_body_size(10)))
+            .build();
+        Arc::new(router)
+    }
+
+    fn make_connection(server_config: ServerConfig) -> H2Connection {
+        H2Connection::new(Arc::new(server_config), make_router())
+    }
+
+    fn respond_all(connection: &mut H2Connection) {
+        for (stream_id, http_request) in connection.take_requests() {
+            let (_, mut http_response) = connection.router.execute_route(http_request);
+            connection.respond(stream_id, H2Response::new(&mut http_response).unwrap());
+        }
+    }
+
+    // the frame types and the payloads which are not a part of the responses
+    fn receive_others(client: &mut TestClient, connection: &mut H2Connection) -> Vec<(u8, Vec<u8>)> {
+        client
+            .receive(&connection.poll_output())
+            .into_iter()
+            .map(|(frame_header, payload)| (frame_header.frame_type, payload))
+            .collect()
+    }
+
+    #[test]
+    fn test_request() {
+        let mut connection = make_connection(ServerConfig::default());
+        let mut client = TestClient::new();
+
+        let mut input = client.start(&[]);
+        input.extend(client.request(1, "GET", "/hello", &[("cookie", "a=1"), ("cookie", "b=2")], b""));
+        input.extend(client.request(3, "POST", "/echo", &[("content-length", "3")], b"abc"));
+        // the frames may arrive in any pieces
+        for byte in input.iter() {
+            connection.push(&[*byte]);
+        }
+
+        let requests = connection.take_requests();
+        assert_eq!(requests.len(), 2);
+        let (stream_id, http_request) = &requests[0];
+        assert_eq!(*stream_id, 1);
+        assert_eq!(http_request.get_req_path().get_pathname(), "/hello");
+        assert_eq!(http_request.get_header("host"), Some("localhost".to_string()));
+        assert_eq!(http_request.get_header("cookie"), Some("a=1; b=2".to_string()));
+        assert_eq!(http_request.get_request_header().get_version().to_string(), "HTTP/2.0");
+        assert_eq!(requests[1].1.get_body().unwrap().get_raw(), b"abc");
+
+        for (stream_id, http_request) in requests {
+            let (_, mut http_response) = connection.router.execute_route(http_request);
+            connection.respond(stream_id, H2Response::new(&mut http_response).unwrap());
+        }
+        let others = receive_others(&mut client, &mut connection);
+        assert_eq!(others[0].0, SETTINGS);
+        // the settings of the client are acknowledged
+        assert!(others.contains(&(SETTINGS, vec![])));
+
+        let response = client.take_response(1).unwrap();
+        assert!(response.is_ended);
+        assert_eq!(response.status, 200);
+        assert_eq!(response.get_header("content-type"), Some("text/plain"));
+        assert_eq!(response.get_header("content-length"), Some("5"));
+        assert_eq!(response.body, b"hello");
+        assert_eq!(client.take_response(3).unwrap().body, b"abc");
+        assert!(connection.is_idle());
+
+        connection.go_away();
+        let others = receive_others(&mut client, &mut connection);
+        assert_eq!(others, vec![(GOAWAY, vec![0, 0, 0, 3, 0, 0, 0, 0])]);
+        assert!(connection.is_finished());
+    }
+
+    #[test]
+    fn test_flow_control() {
+        let mut connection = make_connection(ServerConfig::default());
+        let mut client = TestClient::new();
+
+        connection.push(&client.start(&[(SETTINGS_INITIAL_WINDOW_SIZE, 10)]));
+        connection.push(&client.request(1, "GET", "/big", &[], b""));
+        respond_all(&mut connection);
+        receive_others(&mut client, &mut connection);
+        assert_eq!(client.take_response(1).unwrap().body.len(), 10);
+        assert!(connection.poll_output().is_empty());
+
+        // the window of the connection is still limited to the default
+        let mut window_update = vec![];
+        frame::write_window_update(&mut window_update, 1, 200_000);
+        connection.push(&window_update);
+        receive_others(&mut client, &mut connection);
+        let response = client.take_response(1).unwrap();
+        assert_eq!(response.body.len(), DEFAULT_WINDOW_SIZE as usize - 10);
+        assert!(!response.is_ended);
+
+        let mut window_update = vec![];
+        frame::write_window_update(&mut window_update, 0, 200_000);
+        connection.push(&window_update);
+        receive_others(&mut client, &mut connection);
+        let response = client.take_response(1).unwrap();
+        assert_eq!(response.body.len(), 100_000 - DEFAULT_WINDOW_SIZE as usize);
+        assert!(response.is_ended);
+        assert!(connection.is_idle());
+
+        // the window can not exceed 2^31-1
+        let mut window_update = vec![];
+        frame::write_window_update(&mut window_update, 0, MAX_WINDOW_SIZE as u32);
+        connection.push(&window_update);
+        let others = receive_others(&mut client, &mut connection);
+        assert_eq!(others.last().unwrap().0, GOAWAY);
+        assert_eq!(others.last().unwrap().1[7], ErrorCode::FlowControlError as u8);
+        assert!(connection.is_finished());
+    }
+
+    #[test]
+    fn test_connection_error() {
+        let mut connection = make_connection(ServerConfig::default());
+        connection.push(b"GET / HTTP/1.1\r\n\r\n");
+        let mut client = TestClient::new();
+        let others = receive_others(&mut client, &mut connection);
+        assert_eq!(others.last().unwrap().0, GOAWAY);
+        assert_eq!(others.last().unwrap().1[7], ErrorCode::ProtocolError as u8);
+        assert!(connection.is_finished());
+
+        // a frame of another stream between HEADERS and CONTINUATION
+        let mut connection = make_connection(ServerConfig::default());
+        let mut input = client.start(&[]);
+        frame::write_frame(&mut input, HEADERS, 0, 1, &[0x82]);
+        input.extend(client.request(3, "GET", "/hello", &[], b""));
+        connection.push(&input);
+        let others = receive_others(&mut client, &mut connection);
+        assert_eq!(others.last().unwrap().0, GOAWAY);
+        assert!(connection.take_requests().is_empty());
+    }
+
+    #[test]
+    fn test_stream_error() {
+        let server_config = ServerConfig {
+            max_concurrent_streams: 1,
+            ..ServerConfig::default()
+        };
+        let mut connection = make_connection(server_config);
+        let mut client = TestClient::new();
+
+        let mut input = client.start(&[]);
+        // uppercase, a connection specific header, and no path
+        input.extend(client.request(1, "GET", "/hello", &[("X-Upper", "1")], b""));
+        input.extend(client.request(3, "GET", "/hello", &[("connection", "close")], b""));
+        let mut block = vec![];
+        HpackEncoder::new().encode(iter::once((":method", "GET")), &mut block);
+        frame::write_headers(&mut input, 5, &block, true, DEFAULT_MAX_FRAME_SIZE);
+        // the stream 7 is open while the stream 9 arrives
+        input.extend(client.request(7, "POST", "/echo", &[], b"a"));
+        input.truncate(input.len() - FRAME_HEADER_SIZE - 1);
+        input.extend(client.request(9, "GET", "/hello", &[], b""));
+        connection.push(&input);
+
+        let reset_streams: Vec<(u32, u8)> = client
+            .receive(&connection.poll_output())
+            .into_iter()
+            .filter(|(frame_header, _)| frame_header.frame_type == RST_STREAM)
+            .map(|(frame_header, payload)| (frame_header.stream_id, payload[3]))
+            .collect();
+        assert_eq!(
+            reset_streams,
+            vec![
+                (1, ErrorCode::ProtocolError as u8),
+                (3, ErrorCode::ProtocolError as u8),
+                (5, ErrorCode::ProtocolError as u8),
+                (9, ErrorCode::RefusedStream as u8),
+            ]
+        );
+        assert!(connection.take_requests().is_empty());
+        assert!(!connection.is_finished());
+    }
+
+    #[test]
+    fn test_reject() {
+        let server_config = ServerConfig {
+            max_header_count: 2,
+            ..ServerConfig::default()
+        };
+        let mut connection = make_connection(server_config);
+        let mut client = TestClient::new();
+
+        let mut input = client.start(&[]);
+        input.extend(client.request(1, "POST", "/echo", &[("content-length", "11")], b"01234567890"));
+        input.extend(client.request(3, "POST", "/echo", &[], b"01234567890"));
+        input.extend(client.request(5, "GET", "/hello", &[("a", "1"), ("b", "2"), ("c", "3")], b""));
+        connection.push(&input);
+        assert!(connection.take_requests().is_empty());
+
+        receive_others(&mut client, &mut connection);
+        assert_eq!(client.take_response(1).unwrap().status, 413);
+        assert_eq!(client.take_response(3).unwrap().status, 413);
+        assert_eq!(client.take_response(5).unwrap().status, 431);
+    }
+
+    #[test]
+    fn test_upgrade() {
+        let raw = "GET /hello HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, HTTP2-Settings\r\n\
+            Upgrade: h2c\r\nHTTP2-Settings: AAQAAAAK\r\n\r\n";
+        let http_request = make_request(raw, None);
+
+        let server_config = Arc::new(ServerConfig::default());
+        assert!(H2Connection::upgrade(server_config, make_router(), &http_request).is_none());
+
+        let server_config = Arc::new(ServerConfig {
+            http2: true,
+            ..ServerConfig::default()
+        });
+        let mut connection =
+            H2Connection::upgrade(server_config, make_router(), &http_request).unwrap();
+        connection.open_upgraded_stream(http_request);
+        let mut client = TestClient::new();
+        connection.push(&client.start(&[]));
+        respond_all(&mut connection);
+        receive_others(&mut client, &mut connection);
+
+        // the initial window of the client is 10 bytes
+        let response = client.take_response(1).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, b"hello");
+        assert!(connection.is_idle());
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+// ref <https://www.rfc-editor.org/rfc/rfc9113#section-7>
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum ErrorCode {
+    NoError = 0x0,
+    ProtocolError = 0x1,
+    InternalError = 0x2,
+    FlowControlError = 0x3,
+    StreamClosed = 0x5,
+    FrameSizeError = 0x6,
+    RefusedStream = 0x7,
+    CompressionError = 0x9,
+    EnhanceYourCalm = 0xb,
+}
+
+// a violation of the protocol by the client
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum H2Error {
+    // the connection is closed with GOAWAY
+    Connection(ErrorCode),
+    // only the stream is closed with RST_STREAM
+    Stream(u32, ErrorCode),
+}
+
+impl fmt::Display for H2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            H2Error::Connection(error_code) => write!(f, "connection error {:?}", error_code),
+            H2Error::Stream(stream_id, error_code) => {
+                write!(f, "stream {} error {:?}", stream_id, error_code)
+            }
+        }
+    }
+}
@@ -0,0 +1,95 @@
+use std::io;
+
+use crate::http::{FileBody, HttpResponse, HttpStatus};
+
+// the headers which only make sense for a HTTP/1.1 connection, they must not be sent over HTTP/2
+pub(crate) const CONNECTION_HEADERS: [&str; 5] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+// a response which is sent as the frames of a stream.
+// the body is taken by the chunks which fit in the flow control windows.
+pub(crate) struct H2Response {
+    status: u16,
+    // the names are in lower case
+    headers: Vec<(String, String)>,
+    content: Vec<u8>,
+    file_body: Option<FileBody>,
+    sent: u64,
+}
+
+impl H2Response {
+    pub fn new(http_response: &mut HttpResponse) -> io::Result<Self> {
+        // a stream can not take over the connection, e.g. for Server-Sent Events
+        if http_response.take_upgrade().is_some() {
+            return Ok(H2Response::new_with(HttpStatus::HTTP_VERSION_NOT_SUPPORTED));
+        }
+
+        let (headers, content, file_body) = http_response.take_parts()?;
+        let headers = headers
+            .into_iter()
+            .map(|(key, value)| (key.to_lowercase(), value))
+            .filter(|(key, _)| !CONNECTION_HEADERS.contains(&key.as_str()))
+            .collect();
+
+        Ok(H2Response {
+            status: http_response.get_status().code,
+            headers,
+            content,
+            file_body,
+            sent: 0,
+        })
+    }
+
+    // e.g. for the errors found by the connection
+    pub fn new_with(status: HttpStatus) -> Self {
+        H2Response {
+            status: status.code,
+            headers: vec![("content-length".to_string(), "0".to_string())],
+            content: vec![],
+            file_body: None,
+            sent: 0,
+        }
+    }
+
+    pub fn get_status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn get_headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    pub fn get_remaining(&self) -> u64 {
+        let length = match &self.file_body {
+            Some(file_body) => file_body.get_length(),
+            None => self.content.len() as u64,
+        };
+
+        length - self.sent
+    }
+
+    // the next chunk of the body, which is at most 'max_size' bytes
+    pub fn read_chunk(&mut self, max_size: usize) -> io::Result<Vec<u8>> {
+        let size = self.get_remaining().min(max_size as u64) as usize;
+        let chunk = match &self.file_body {
+            Some(file_body) => {
+                let mut buffer = vec![0_u8; size];
+                let nbytes = file_body.read_chunk(self.sent, &mut buffer)?;
+                buffer.truncate(nbytes);
+                buffer
+            }
+            None => {
+                let offset = self.sent as usize;
+                self.content[offset..offset + size].to_vec()
+            }
+        };
+
+        self.sent += chunk.len() as u64;
+        Ok(chunk)
+    }
+}
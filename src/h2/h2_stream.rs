@@ -0,0 +1,42 @@
+use crate::h2::H2Response;
+use crate::http::HttpRequestHeader;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum StreamState {
+    // the client is sending the request
+    Open,
+    // the request has arrived, the response is being made or sent
+    HalfClosedRemote,
+}
+
+// a request and its response on a connection.
+// ref <https://www.rfc-editor.org/rfc/rfc9113#section-5.1>
+pub(crate) struct H2Stream {
+    pub state: StreamState,
+    // kept until the body has arrived
+    pub request_header: Option<HttpRequestHeader>,
+    pub body: Vec<u8>,
+    pub max_body_size: usize,
+    pub content_length: Option<usize>,
+    // how many bytes the client can receive, it can be negative by SETTINGS_INITIAL_WINDOW_SIZE
+    pub send_window: i64,
+    // the body which waits for the flow control windows
+    pub response: Option<H2Response>,
+    // answered with an error, the rest of the request is discarded
+    pub is_rejected: bool,
+}
+
+impl H2Stream {
+    pub fn new(send_window: i64) -> Self {
+        H2Stream {
+            state: StreamState::Open,
+            request_header: None,
+            body: vec![],
+            max_body_size: 0,
+            content_length: None,
+            send_window,
+            response: None,
+            is_rejected: false,
+        }
+    }
+}
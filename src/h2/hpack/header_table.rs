@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+
+// ref <https://www.rfc-editor.org/rfc/rfc7541#appendix-A>
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+// each entry is counted with this overhead in the table size
+const ENTRY_OVERHEAD: usize = 32;
+
+pub(crate) type HeaderField = (Vec<u8>, Vec<u8>);
+
+// the static table followed by the dynamic table, which is shared by the encoder and the decoder
+// of the peer. the index starts from 1, and the newest dynamic entry comes first.
+pub(crate) struct HeaderTable {
+    entries: VecDeque<HeaderField>,
+    size: usize,
+    max_size: usize,
+}
+
+// how a header field is found in the table
+pub(crate) enum TableMatch {
+    // both of the name and the value
+    Field(usize),
+    Name(usize),
+}
+
+impl HeaderTable {
+    pub fn new(max_size: usize) -> Self {
+        HeaderTable {
+            entries: VecDeque::new(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub fn get(&self, index: usize) -> Option<(&[u8], &[u8])> {
+        if index == 0 {
+            return None;
+        }
+
+        if index <= STATIC_TABLE.len() {
+            let (name, value) = STATIC_TABLE[index - 1];
+            return Some((name.as_bytes(), value.as_bytes()));
+        }
+
+        self.entries
+            .get(index - STATIC_TABLE.len() - 1)
+            .map(|(name, value)| (name.as_slice(), value.as_slice()))
+    }
+
+    pub fn find(&self, name: &[u8], value: &[u8]) -> Option<TableMatch> {
+        let mut name_index = None;
+
+        let static_entries = STATIC_TABLE
+            .iter()
+            .map(|(name, value)| (name.as_bytes(), value.as_bytes()));
+        let dynamic_entries = self
+            .entries
+            .iter()
+            .map(|(name, value)| (name.as_slice(), value.as_slice()));
+        for (index, (entry_name, entry_value)) in static_entries.chain(dynamic_entries).enumerate() {
+            if entry_name != name {
+                continue;
+            }
+            if entry_value == value {
+                return Some(TableMatch::Field(index + 1));
+            }
+            name_index.get_or_insert(index + 1);
+        }
+
+        name_index.map(TableMatch::Name)
+    }
+
+    // an entry larger than the table empties the table
+    pub fn insert(&mut self, name: Vec<u8>, value: Vec<u8>) {
+        let entry_size = name.len() + value.len() + ENTRY_OVERHEAD;
+        self.evict(self.max_size.saturating_sub(entry_size));
+
+        if entry_size <= self.max_size {
+            self.size += entry_size;
+            self.entries.push_front((name, value));
+        }
+    }
+
+    pub fn set_max_size(&mut self, max_size: usize) {
+        self.max_size = max_size;
+        self.evict(max_size);
+    }
+
+    fn evict(&mut self, target_size: usize) {
+        while self.size > target_size {
+            if let Some((name, value)) = self.entries.pop_back() {
+                self.size -= name.len() + value.len() + ENTRY_OVERHEAD;
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_table() {
+        let mut table = HeaderTable::new(100);
+        assert_eq!(table.get(2), Some((&b":method"[..], &b"GET"[..])));
+        assert!(table.get(62).is_none());
+
+        // 10 + 13 + 32 = 55
+        table.insert(b"custom-key".to_vec(), b"custom-header".to_vec());
+        assert_eq!(table.get(62), Some((&b"custom-key"[..], &b"custom-header"[..])));
+        assert!(matches!(table.find(b"custom-key", b"custom-header"), Some(TableMatch::Field(62))));
+        assert!(matches!(table.find(b"content-type", b"text/html"), Some(TableMatch::Name(31))));
+
+        // the oldest entry is evicted
+        table.insert(b"custom-key2".to_vec(), b"value".to_vec());
+        assert_eq!(table.get(62), Some((&b"custom-key2"[..], &b"value"[..])));
+        assert!(table.get(63).is_none());
+
+        table.set_max_size(0);
+        assert!(table.get(62).is_none());
+    }
+}
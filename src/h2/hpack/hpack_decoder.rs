@@ -0,0 +1,185 @@
+use crate::h2::h2_error::{ErrorCode, H2Error};
+use crate::h2::hpack::header_table::{HeaderField, HeaderTable};
+use crate::h2::hpack::primitive::{decode_integer, decode_string};
+
+// each header field is counted with this overhead in the header list size
+const FIELD_OVERHEAD: usize = 32;
+
+// the decoded header fields of a block
+pub(crate) struct HeaderList {
+    pub fields: Vec<HeaderField>,
+    // the fields beyond the limit are dropped, the block is decoded only to keep the table
+    pub is_too_large: bool,
+}
+
+// decodes the header blocks of a connection, the blocks must be decoded in the order of arrival.
+// ref <https://www.rfc-editor.org/rfc/rfc7541#section-6>
+pub(crate) struct HpackDecoder {
+    table: HeaderTable,
+    // the limit which is announced to the peer with SETTINGS_HEADER_TABLE_SIZE
+    max_table_size: usize,
+}
+
+impl HpackDecoder {
+    pub fn new(max_table_size: usize) -> Self {
+        HpackDecoder {
+            table: HeaderTable::new(max_table_size),
+            max_table_size,
+        }
+    }
+
+    pub fn decode(&mut self, block: &[u8], max_list_size: usize) -> Result<HeaderList, H2Error> {
+        let error = H2Error::Connection(ErrorCode::CompressionError);
+        let mut header_list = HeaderList {
+            fields: vec![],
+            is_too_large: false,
+        };
+        let mut list_size = 0;
+        let mut offset = 0;
+
+        while offset < block.len() {
+            let src = &block[offset..];
+            let first = src[0];
+
+            let (field, consumed) = if first & 0x80 != 0 {
+                // indexed
+                let (index, consumed) = decode_integer(src, 7)?;
+                let (name, value) = self.table.get(index).ok_or(error)?;
+                ((name.to_vec(), value.to_vec()), consumed)
+            } else if first & 0x40 != 0 {
+                // literal with incremental indexing
+                let (field, consumed) = self.decode_literal(src, 6)?;
+                self.table.insert(field.0.clone(), field.1.clone());
+                (field, consumed)
+            } else if first & 0x20 != 0 {
+                // dynamic table size update, only at the beginning of a block
+                if !header_list.fields.is_empty() {
+                    return Err(error);
+                }
+                let (max_size, consumed) = decode_integer(src, 5)?;
+                if max_size > self.max_table_size {
+                    return Err(error);
+                }
+                self.table.set_max_size(max_size);
+                offset += consumed;
+                continue;
+            } else {
+                // literal without indexing, or never indexed
+                self.decode_literal(src, 4)?
+            };
+            offset += consumed;
+
+            list_size += field.0.len() + field.1.len() + FIELD_OVERHEAD;
+            if list_size > max_list_size {
+                header_list.is_too_large = true;
+            }
+            if !header_list.is_too_large {
+                header_list.fields.push(field);
+            }
+        }
+
+        Ok(header_list)
+    }
+
+    fn decode_literal(&self, src: &[u8], prefix_bits: u8) -> Result<(HeaderField, usize), H2Error> {
+        let (index, mut offset) = decode_integer(src, prefix_bits)?;
+        let name = if index == 0 {
+            let (name, consumed) = decode_string(&src[offset..])?;
+            offset += consumed;
+            name
+        } else {
+            let (name, _) = self
+                .table
+                .get(index)
+                .ok_or(H2Error::Connection(ErrorCode::CompressionError))?;
+            name.to_vec()
+        };
+
+        let (value, consumed) = decode_string(&src[offset..])?;
+        Ok(((name, value), offset + consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_strings(header_list: &HeaderList) -> Vec<(&str, &str)> {
+        header_list
+            .fields
+            .iter()
+            .map(|(name, value)| {
+                (std::str::from_utf8(name).unwrap(), std::str::from_utf8(value).unwrap())
+            })
+            .collect()
+    }
+
+    // ref <https://www.rfc-editor.org/rfc/rfc7541#appendix-C.4>
+    #[test]
+    fn test_decode_requests() {
+        let mut decoder = HpackDecoder::new(4096);
+
+        let block = [
+            0x82, 0x86, 0x84, 0x41, 0x8c, 0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab,
+            0x90, 0xf4, 0xff,
+        ];
+        let header_list = decoder.decode(&block, 1000).unwrap();
+        assert_eq!(
+            to_strings(&header_list),
+            [
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+            ]
+        );
+
+        let block = [0x82, 0x86, 0x84, 0xbe, 0x58, 0x86, 0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf];
+        let header_list = decoder.decode(&block, 1000).unwrap();
+        assert_eq!(
+            to_strings(&header_list),
+            [
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":path", "/"),
+                (":authority", "www.example.com"),
+                ("cache-control", "no-cache"),
+            ]
+        );
+
+        let block = [
+            0x82, 0x87, 0x85, 0xbf, 0x40, 0x88, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xa9, 0x7d, 0x7f,
+            0x89, 0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8, 0xb4, 0xbf,
+        ];
+        let header_list = decoder.decode(&block, 1000).unwrap();
+        assert_eq!(
+            to_strings(&header_list),
+            [
+                (":method", "GET"),
+                (":scheme", "https"),
+                (":path", "/index.html"),
+                (":authority", "www.example.com"),
+                ("custom-key", "custom-value"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let mut decoder = HpackDecoder::new(4096);
+        // index 0, an index out of the table, and a size update over the limit
+        assert!(decoder.decode(&[0x80], 1000).is_err());
+        assert!(decoder.decode(&[0xbe], 1000).is_err());
+        assert!(decoder.decode(&[0x3f, 0xe2, 0x1f], 1000).is_err());
+        // a size update after a field
+        assert!(decoder.decode(&[0x82, 0x20], 1000).is_err());
+
+        // the table is still updated after the limit is exceeded
+        let block = [0x82, 0x40, 0x01, b'a', 0x01, b'b'];
+        let header_list = decoder.decode(&block, 50).unwrap();
+        assert!(header_list.is_too_large);
+        assert_eq!(header_list.fields.len(), 1);
+        let header_list = decoder.decode(&[0xbe], 1000).unwrap();
+        assert_eq!(to_strings(&header_list), [("a", "b")]);
+    }
+}
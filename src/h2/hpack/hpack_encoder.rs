@@ -0,0 +1,130 @@
+use crate::h2::hpack::header_table::{HeaderTable, TableMatch};
+use crate::h2::hpack::primitive::{encode_integer, encode_string};
+
+// the table size which is used by default, and at most
+const DEFAULT_TABLE_SIZE: usize = 4096;
+
+// the values change with every response, so they are not worth a table entry
+const UNINDEXED_NAMES: [&str; 6] = [
+    "content-length",
+    "content-range",
+    "date",
+    "etag",
+    "last-modified",
+    "location",
+];
+// the values are secrets, so the intermediaries must not index them either
+const NEVER_INDEXED_NAMES: [&str; 2] = ["set-cookie", "authorization"];
+
+// encodes the header blocks of a connection, the blocks must be sent in the order of encoding.
+// ref <https://www.rfc-editor.org/rfc/rfc7541#section-6>
+pub(crate) struct HpackEncoder {
+    table: HeaderTable,
+    // the peer has changed SETTINGS_HEADER_TABLE_SIZE, which is told at the next block
+    pending_size_update: Option<usize>,
+}
+
+impl HpackEncoder {
+    pub fn new() -> Self {
+        HpackEncoder {
+            table: HeaderTable::new(DEFAULT_TABLE_SIZE),
+            pending_size_update: None,
+        }
+    }
+
+    // the table size allowed by the peer
+    pub fn set_max_table_size(&mut self, max_table_size: usize) {
+        let max_table_size = max_table_size.min(DEFAULT_TABLE_SIZE);
+        if max_table_size != self.table.get_max_size() {
+            self.table.set_max_size(max_table_size);
+            self.pending_size_update = Some(max_table_size);
+        }
+    }
+
+    // the names must be in lower case
+    pub fn encode<'a, I>(&mut self, fields: I, dst: &mut Vec<u8>)
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        if let Some(max_size) = self.pending_size_update.take() {
+            encode_integer(max_size, 5, 0x20, dst);
+        }
+
+        for (name, value) in fields {
+            let (name, value) = (name.as_bytes(), value.as_bytes());
+            let table_match = self.table.find(name, value);
+            if let Some(TableMatch::Field(index)) = table_match {
+                encode_integer(index, 7, 0x80, dst);
+                continue;
+            }
+
+            let name_index = match table_match {
+                Some(TableMatch::Name(index)) => index,
+                _ => 0,
+            };
+            let (prefix_bits, flags) = if NEVER_INDEXED_NAMES.iter().any(|n| n.as_bytes() == name) {
+                (4, 0x10)
+            } else if UNINDEXED_NAMES.iter().any(|n| n.as_bytes() == name) {
+                (4, 0x00)
+            } else {
+                self.table.insert(name.to_vec(), value.to_vec());
+                (6, 0x40)
+            };
+
+            encode_integer(name_index, prefix_bits, flags, dst);
+            if name_index == 0 {
+                encode_string(name, dst);
+            }
+            encode_string(value, dst);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::h2::hpack::HpackDecoder;
+
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        let mut encoder = HpackEncoder::new();
+        let mut decoder = HpackDecoder::new(4096);
+        let fields = [
+            (":status", "200"),
+            ("content-type", "text/html"),
+            ("content-length", "1234"),
+            ("set-cookie", "id=secret"),
+            ("x-custom", "value"),
+        ];
+
+        let mut first = vec![];
+        encoder.encode(fields.iter().copied(), &mut first);
+        let mut second = vec![];
+        encoder.encode(fields.iter().copied(), &mut second);
+        // the indexed fields take a byte each
+        assert!(second.len() < first.len());
+
+        for block in [first, second] {
+            let header_list = decoder.decode(&block, 10_000).unwrap();
+            let decoded: Vec<(&[u8], &[u8])> = header_list
+                .fields
+                .iter()
+                .map(|(name, value)| (name.as_slice(), value.as_slice()))
+                .collect();
+            let expected: Vec<(&[u8], &[u8])> = fields
+                .iter()
+                .map(|(name, value)| (name.as_bytes(), value.as_bytes()))
+                .collect();
+            assert_eq!(decoded, expected);
+        }
+
+        // the peer shrinks the table
+        encoder.set_max_table_size(0);
+        let mut block = vec![];
+        encoder.encode([("x-custom", "value")], &mut block);
+        assert_eq!(block[0], 0x20);
+        let header_list = decoder.decode(&block, 10_000).unwrap();
+        assert_eq!(header_list.fields, [(b"x-custom".to_vec(), b"value".to_vec())]);
+    }
+}
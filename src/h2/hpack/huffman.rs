@@ -0,0 +1,137 @@
+use lazy_static::lazy_static;
+
+use crate::h2::h2_error::{ErrorCode, H2Error};
+use crate::h2::hpack::huffman_codes::HUFFMAN_CODES;
+
+const EOS: u16 = 256;
+
+#[derive(Clone, Copy)]
+enum Node {
+    Empty,
+    Branch(usize),
+    Symbol(u16),
+}
+
+lazy_static! {
+    // a binary tree of the codes, each node has the children of the bit 0 and the bit 1
+    static ref DECODE_TREE: Vec<[Node; 2]> = build_decode_tree();
+}
+
+fn build_decode_tree() -> Vec<[Node; 2]> {
+    let mut tree = vec![[Node::Empty; 2]];
+
+    for (symbol, (code, bit_length)) in HUFFMAN_CODES.iter().enumerate() {
+        let mut node = 0;
+        for shift in (0..*bit_length).rev() {
+            let bit = ((code >> shift) & 1) as usize;
+            if shift == 0 {
+                tree[node][bit] = Node::Symbol(symbol as u16);
+                break;
+            }
+
+            node = match tree[node][bit] {
+                Node::Branch(next) => next,
+                _ => {
+                    tree.push([Node::Empty; 2]);
+                    tree[node][bit] = Node::Branch(tree.len() - 1);
+                    tree.len() - 1
+                }
+            };
+        }
+    }
+
+    tree
+}
+
+pub(crate) fn encoded_len(bytes: &[u8]) -> usize {
+    let bit_length: usize = bytes
+        .iter()
+        .map(|byte| HUFFMAN_CODES[*byte as usize].1 as usize)
+        .sum();
+    bit_length.div_ceil(8)
+}
+
+pub(crate) fn encode(bytes: &[u8], dst: &mut Vec<u8>) {
+    let mut bits: u64 = 0;
+    let mut bit_count = 0;
+
+    for byte in bytes {
+        let (code, bit_length) = HUFFMAN_CODES[*byte as usize];
+        bits = (bits << bit_length) | code as u64;
+        bit_count += bit_length as u32;
+
+        while bit_count >= 8 {
+            bit_count -= 8;
+            dst.push((bits >> bit_count) as u8);
+        }
+    }
+
+    // padded with the most significant bits of EOS
+    if bit_count > 0 {
+        let padding = 8 - bit_count;
+        dst.push(((bits << padding) as u8) | ((1 << padding) - 1) as u8);
+    }
+}
+
+// the padding must be shorter than 8 bits and filled with 1, and EOS must not appear
+pub(crate) fn decode(bytes: &[u8]) -> Result<Vec<u8>, H2Error> {
+    let error = H2Error::Connection(ErrorCode::CompressionError);
+    let mut decoded = Vec::with_capacity(bytes.len() * 8 / 5);
+    let mut node = 0;
+    // the bits since the last symbol, which may be the padding
+    let mut pending_bits = 0;
+    let mut is_all_ones = true;
+
+    for byte in bytes {
+        for shift in (0..8).rev() {
+            let bit = ((byte >> shift) & 1) as usize;
+            pending_bits += 1;
+            is_all_ones &= bit == 1;
+
+            match DECODE_TREE[node][bit] {
+                Node::Branch(next) => node = next,
+                Node::Symbol(EOS) | Node::Empty => return Err(error),
+                Node::Symbol(symbol) => {
+                    decoded.push(symbol as u8);
+                    node = 0;
+                    pending_bits = 0;
+                    is_all_ones = true;
+                }
+            }
+        }
+    }
+
+    if pending_bits >= 8 || !is_all_ones {
+        return Err(error);
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ref <https://www.rfc-editor.org/rfc/rfc7541#appendix-C.4.1>
+    #[test]
+    fn test_huffman() {
+        let encoded = [0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff];
+        assert_eq!(decode(&encoded).unwrap(), b"www.example.com");
+
+        let mut dst = vec![];
+        encode(b"www.example.com", &mut dst);
+        assert_eq!(dst, encoded);
+        assert_eq!(encoded_len(b"www.example.com"), encoded.len());
+
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        let mut dst = vec![];
+        encode(&all_bytes, &mut dst);
+        assert_eq!(decode(&dst).unwrap(), all_bytes);
+
+        // 'a' is '00011', which is padded with '111'
+        assert_eq!(decode(&[0x1f]).unwrap(), b"a");
+        // a padding of zeros, and a padding longer than 7 bits
+        assert!(decode(&[0x18]).is_err());
+        assert!(decode(&[0x1f, 0xff]).is_err());
+    }
+}
@@ -0,0 +1,9 @@
+pub(crate) use hpack_decoder::{HeaderList, HpackDecoder};
+pub(crate) use hpack_encoder::HpackEncoder;
+
+mod header_table;
+mod hpack_decoder;
+mod hpack_encoder;
+mod huffman;
+mod huffman_codes;
+mod primitive;
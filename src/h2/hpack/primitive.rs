@@ -0,0 +1,107 @@
+use crate::h2::h2_error::{ErrorCode, H2Error};
+use crate::h2::hpack::huffman;
+
+// ref <https://www.rfc-editor.org/rfc/rfc7541#section-5>
+
+// the first byte keeps the flags above the prefix
+pub(crate) fn encode_integer(value: usize, prefix_bits: u8, flags: u8, dst: &mut Vec<u8>) {
+    let max_prefix = (1_usize << prefix_bits) - 1;
+    if value < max_prefix {
+        dst.push(flags | value as u8);
+        return;
+    }
+
+    dst.push(flags | max_prefix as u8);
+    let mut rest = value - max_prefix;
+    while rest >= 0x80 {
+        dst.push((rest & 0x7f) as u8 | 0x80);
+        rest >>= 7;
+    }
+    dst.push(rest as u8);
+}
+
+// returns the value and the number of the bytes which are consumed
+pub(crate) fn decode_integer(src: &[u8], prefix_bits: u8) -> Result<(usize, usize), H2Error> {
+    let error = H2Error::Connection(ErrorCode::CompressionError);
+    let max_prefix = (1_usize << prefix_bits) - 1;
+
+    let first = *src.first().ok_or(error)? as usize & max_prefix;
+    if first < max_prefix {
+        return Ok((first, 1));
+    }
+
+    let mut value = max_prefix;
+    for (index, byte) in src.iter().enumerate().skip(1) {
+        // a value which does not fit in 32 bits is not used by any sane peer
+        let shift = (index - 1) * 7;
+        if shift > 28 {
+            return Err(error);
+        }
+
+        value += ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((value, index + 1));
+        }
+    }
+
+    Err(error)
+}
+
+// the Huffman code is used when it is shorter
+pub(crate) fn encode_string(bytes: &[u8], dst: &mut Vec<u8>) {
+    let huffman_len = huffman::encoded_len(bytes);
+    if huffman_len < bytes.len() {
+        encode_integer(huffman_len, 7, 0x80, dst);
+        huffman::encode(bytes, dst);
+    } else {
+        encode_integer(bytes.len(), 7, 0, dst);
+        dst.extend_from_slice(bytes);
+    }
+}
+
+// returns the string and the number of the bytes which are consumed
+pub(crate) fn decode_string(src: &[u8]) -> Result<(Vec<u8>, usize), H2Error> {
+    let error = H2Error::Connection(ErrorCode::CompressionError);
+    let is_huffman = src.first().ok_or(error)? & 0x80 != 0;
+    let (length, offset) = decode_integer(src, 7)?;
+
+    let end = offset.checked_add(length).ok_or(error)?;
+    let raw = src.get(offset..end).ok_or(error)?;
+    let string = if is_huffman {
+        huffman::decode(raw)?
+    } else {
+        raw.to_vec()
+    };
+
+    Ok((string, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ref <https://www.rfc-editor.org/rfc/rfc7541#appendix-C.1>
+    #[test]
+    fn test_integer() {
+        let mut dst = vec![];
+        encode_integer(10, 5, 0, &mut dst);
+        assert_eq!(dst, [0x0a]);
+
+        let mut dst = vec![];
+        encode_integer(1337, 5, 0xe0, &mut dst);
+        assert_eq!(dst, [0xff, 0x9a, 0x0a]);
+        assert_eq!(decode_integer(&dst, 5).unwrap(), (1337, 3));
+
+        assert!(decode_integer(&[0x1f, 0x9a], 5).is_err());
+        assert!(decode_integer(&[0x1f, 0xff, 0xff, 0xff, 0xff, 0xff, 0x0f], 5).is_err());
+    }
+
+    #[test]
+    fn test_string() {
+        let mut dst = vec![];
+        encode_string(b"custom-key", &mut dst);
+        assert_eq!(decode_string(&dst).unwrap(), (b"custom-key".to_vec(), dst.len()));
+
+        assert!(decode_string(&[0x0a, b'a']).is_err());
+    }
+}
@@ -0,0 +1,11 @@
+pub(crate) use h2_connection::{H2Connection, SWITCHING_TO_H2C};
+pub(crate) use h2_response::H2Response;
+
+mod frame;
+mod h2_connection;
+mod h2_error;
+mod h2_response;
+mod h2_stream;
+mod hpack;
+#[cfg(test)]
+pub(crate) mod test_client;
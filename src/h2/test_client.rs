@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::io::Read;
+
+use crate::h2::frame::{
+    self, FrameHeader, DATA, FLAG_END_HEADERS, FLAG_END_STREAM, FRAME_HEADER_SIZE, HEADERS, PING,
+};
+use crate::h2::h2_connection::PREFACE;
+use crate::h2::hpack::{HpackDecoder, HpackEncoder};
+
+pub(crate) use crate::h2::frame::{GOAWAY, SETTINGS_INITIAL_WINDOW_SIZE};
+
+// a minimal client of HTTP/2 to test the server, the frames are made and read by hand
+pub(crate) struct TestClient {
+    encoder: HpackEncoder,
+    decoder: HpackDecoder,
+    responses: HashMap<u32, TestResponse>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct TestResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    pub is_ended: bool,
+}
+
+impl TestResponse {
+    pub fn get_header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl TestClient {
+    pub fn new() -> Self {
+        TestClient {
+            encoder: HpackEncoder::new(),
+            decoder: HpackDecoder::new(4096),
+            responses: HashMap::new(),
+        }
+    }
+
+    // the connection preface followed by SETTINGS
+    pub fn start(&self, settings: &[(u16, u32)]) -> Vec<u8> {
+        let mut dst = PREFACE.to_vec();
+        frame::write_settings(&mut dst, settings);
+        dst
+    }
+
+    pub fn window_update(&self, stream_id: u32, increment: u32) -> Vec<u8> {
+        let mut dst = vec![];
+        frame::write_window_update(&mut dst, stream_id, increment);
+        dst
+    }
+
+    pub fn ping(&self, payload: [u8; 8]) -> Vec<u8> {
+        let mut dst = vec![];
+        frame::write_frame(&mut dst, PING, 0, 0, &payload);
+        dst
+    }
+
+    // HEADERS and DATA of a request, the stream is ended by the last frame
+    pub fn request(
+        &mut self,
+        stream_id: u32,
+        method: &str,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> Vec<u8> {
+        let fields = [
+            (":method", method),
+            (":scheme", "http"),
+            (":authority", "localhost"),
+            (":path", path),
+        ];
+        let mut block = vec![];
+        self.encoder
+            .encode(fields.iter().chain(headers.iter()).copied(), &mut block);
+
+        let mut dst = vec![];
+        frame::write_headers(&mut dst, stream_id, &block, body.is_empty(), 16_384);
+        if !body.is_empty() {
+            frame::write_frame(&mut dst, DATA, FLAG_END_STREAM, stream_id, body);
+        }
+        dst
+    }
+
+    // the frames which are not for a response, e.g. SETTINGS and GOAWAY, are returned
+    pub fn receive(&mut self, src: &[u8]) -> Vec<(FrameHeader, Vec<u8>)> {
+        let mut others = vec![];
+        for (frame_header, payload) in parse_frames(src) {
+            if !self.handle_frame(&frame_header, &payload) {
+                others.push((frame_header, payload));
+            }
+        }
+        others
+    }
+
+    // read the frames until the response of the stream is ended
+    pub fn read_response<R: Read>(&mut self, reader: &mut R, stream_id: u32) -> TestResponse {
+        while !self.responses.get(&stream_id).is_some_and(|response| response.is_ended) {
+            let (frame_header, payload) = read_frame(reader).expect("the connection is closed");
+            self.handle_frame(&frame_header, &payload);
+        }
+
+        self.responses.remove(&stream_id).unwrap()
+    }
+
+    pub fn take_response(&mut self, stream_id: u32) -> Option<TestResponse> {
+        self.responses.remove(&stream_id)
+    }
+
+    fn handle_frame(&mut self, frame_header: &FrameHeader, payload: &[u8]) -> bool {
+        let response = match frame_header.frame_type {
+            HEADERS => {
+                // the server never splits the small headers of the tests
+                assert!(frame_header.has_flag(FLAG_END_HEADERS));
+                let header_list = self.decoder.decode(payload, usize::MAX).unwrap();
+                let response = self.responses.entry(frame_header.stream_id).or_default();
+                for (name, value) in header_list.fields {
+                    let name = String::from_utf8(name).unwrap();
+                    let value = String::from_utf8(value).unwrap();
+                    if name == ":status" {
+                        response.status = value.parse().unwrap();
+                    } else {
+                        response.headers.push((name, value));
+                    }
+                }
+                response
+            }
+            DATA => {
+                let response = self.responses.entry(frame_header.stream_id).or_default();
+                response.body.extend_from_slice(payload);
+                response
+            }
+            _ => return false,
+        };
+
+        if frame_header.has_flag(FLAG_END_STREAM) {
+            response.is_ended = true;
+        }
+        true
+    }
+}
+
+pub(crate) fn parse_frames(src: &[u8]) -> Vec<(FrameHeader, Vec<u8>)> {
+    let mut frames = vec![];
+    let mut offset = 0;
+    while let Some(frame_header) = FrameHeader::parse(&src[offset..]) {
+        let start = offset + FRAME_HEADER_SIZE;
+        frames.push((frame_header, src[start..start + frame_header.length].to_vec()));
+        offset = start + frame_header.length;
+    }
+    frames
+}
+
+pub(crate) fn read_frame<R: Read>(reader: &mut R) -> Option<(FrameHeader, Vec<u8>)> {
+    let mut header = [0_u8; FRAME_HEADER_SIZE];
+    reader.read_exact(&mut header).ok()?;
+    let frame_header = FrameHeader::parse(&header)?;
+    let mut payload = vec![0_u8; frame_header.length];
+    reader.read_exact(&mut payload).ok()?;
+
+    Some((frame_header, payload))
+}
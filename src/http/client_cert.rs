@@ -0,0 +1,76 @@
+use std::net::IpAddr;
+
+// the certificate presented by the client with mutual TLS, verified against the configured CA bundle
+#[derive(Debug, Clone, Default)]
+pub struct ClientCert {
+    // e.g. 'CN=service-a, O=Example'
+    subject: String,
+    common_name: Option<String>,
+    // from the subject alternative names
+    dns_names: Vec<String>,
+    email_addresses: Vec<String>,
+    uris: Vec<String>,
+    ip_addresses: Vec<IpAddr>,
+    // DER encoding, e.g. to pin a certificate by its hash
+    der: Vec<u8>,
+}
+
+impl ClientCert {
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn new(subject: String, common_name: Option<String>, der: Vec<u8>) -> Self {
+        ClientCert {
+            subject,
+            common_name,
+            der,
+            ..ClientCert::default()
+        }
+    }
+
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn add_dns_name(&mut self, dns_name: String) {
+        self.dns_names.push(dns_name);
+    }
+
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn add_email_address(&mut self, email_address: String) {
+        self.email_addresses.push(email_address);
+    }
+
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn add_uri(&mut self, uri: String) {
+        self.uris.push(uri);
+    }
+
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn add_ip_address(&mut self, ip_address: IpAddr) {
+        self.ip_addresses.push(ip_address);
+    }
+
+    pub fn get_subject(&self) -> &str {
+        &self.subject
+    }
+
+    pub fn get_common_name(&self) -> Option<&str> {
+        self.common_name.as_deref()
+    }
+
+    pub fn get_dns_names(&self) -> &[String] {
+        &self.dns_names
+    }
+
+    pub fn get_email_addresses(&self) -> &[String] {
+        &self.email_addresses
+    }
+
+    pub fn get_uris(&self) -> &[String] {
+        &self.uris
+    }
+
+    pub fn get_ip_addresses(&self) -> &[IpAddr] {
+        &self.ip_addresses
+    }
+
+    pub fn get_der(&self) -> &[u8] {
+        &self.der
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
+use std::fmt;
 
+#[derive(Clone)]
 pub struct ContentType {
     main_type: &'static str,
     sub_type: &'static str,
@@ -11,11 +13,48 @@ pub struct ContentType {
 impl ContentType {
     pub const TEXT_PLAIN: ContentType = ContentType::create_content_type("text", "plain");
     pub const TEXT_HTML: ContentType = ContentType::create_content_type("text", "html");
+    pub const TEXT_EVENT_STREAM: ContentType = ContentType::create_content_type("text", "event-stream");
+    pub const APPLICATION_JSON: ContentType = ContentType::create_content_type("application", "json");
+    pub const APPLICATION_OCTET_STREAM: ContentType =
+        ContentType::create_content_type("application", "octet-stream");
 
     pub fn new(main_type: &'static str, sub_type: &'static str) -> ContentType {
         ContentType::create_content_type(main_type, sub_type)
     }
 
+    // guess the content type from the extension of a file name
+    pub fn from_extension(extension: &str) -> ContentType {
+        let (main_type, sub_type) = match extension.to_ascii_lowercase().as_str() {
+            "html" | "htm" => ("text", "html"),
+            "css" => ("text", "css"),
+            "js" | "mjs" => ("text", "javascript"),
+            "txt" => ("text", "plain"),
+            "csv" => ("text", "csv"),
+            "xml" => ("application", "xml"),
+            "json" => ("application", "json"),
+            "wasm" => ("application", "wasm"),
+            "pdf" => ("application", "pdf"),
+            "png" => ("image", "png"),
+            "jpg" | "jpeg" => ("image", "jpeg"),
+            "gif" => ("image", "gif"),
+            "svg" => ("image", "svg+xml"),
+            "ico" => ("image", "x-icon"),
+            "webp" => ("image", "webp"),
+            "woff" => ("font", "woff"),
+            "woff2" => ("font", "woff2"),
+            "mp4" => ("video", "mp4"),
+            _ => ("application", "octet-stream"),
+        };
+
+        ContentType::create_content_type(main_type, sub_type)
+    }
+
+    // whether compressing the content is worth it, images and fonts are already compressed
+    pub fn is_compressible(&self) -> bool {
+        self.main_type == "text"
+            || matches!(self.sub_type, "json" | "xml" | "javascript" | "svg+xml" | "wasm")
+    }
+
     const fn create_content_type(main_type: &'static str, sub_type: &'static str) -> ContentType {
         ContentType {
             main_type,
@@ -25,9 +64,17 @@ impl ContentType {
     }
 }
 
-impl ToString for ContentType {
-    fn to_string(&self) -> String {
-        format!("{}/{}", self.main_type, self.sub_type)
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.main_type, self.sub_type)?;
+
+        if let Some(optional_fields) = &self.optional_fields {
+            for (key, value) in optional_fields.iter() {
+                write!(f, "; {}={}", key, value)?;
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -48,4 +95,15 @@ mod tests {
         let c_type = create();
         assert_eq!(c_type.to_string().as_str(), "foo/bar");
     }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(ContentType::from_extension("PNG").to_string(), "image/png");
+        assert_eq!(ContentType::from_extension("js").to_string(), "text/javascript");
+        assert_eq!(ContentType::from_extension("unknown").to_string(), "application/octet-stream");
+
+        assert!(ContentType::from_extension("css").is_compressible());
+        assert!(ContentType::from_extension("svg").is_compressible());
+        assert!(!ContentType::from_extension("png").is_compressible());
+    }
 }
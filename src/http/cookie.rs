@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+// model for a 'Set-Cookie' response header
+#[derive(Debug, Clone)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    max_age: Option<Duration>,
+    http_only: bool,
+    secure: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    pub fn new(name: &str, value: &str) -> Self {
+        Cookie {
+            name: name.to_string(),
+            value: value.to_string(),
+            path: None,
+            max_age: None,
+            http_only: false,
+            secure: false,
+            same_site: None,
+        }
+    }
+
+    // make a cookie that tells the client to remove the cookie immediately
+    pub fn removal(name: &str) -> Self {
+        Cookie::new(name, "").max_age(Duration::from_secs(0))
+    }
+
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_string());
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    pub fn same_site(mut self, same_site: SameSite) -> Self {
+        self.same_site = Some(same_site);
+        self
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_value(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+
+        if let Some(path) = &self.path {
+            write!(f, "; Path={}", path)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={}", max_age.as_secs())?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(f, "; SameSite={:?}", same_site)?;
+        }
+
+        Ok(())
+    }
+}
+
+// parse the value of a 'Cookie' request header into name-value pairs
+pub fn parse_cookies(raw: &str) -> HashMap<String, String> {
+    let mut cookies = HashMap::new();
+
+    for pair in raw.split(';') {
+        let mut split = pair.splitn(2, '=');
+        let name = split.next().map(str::trim);
+        let value = split.next().map(str::trim);
+
+        if let (Some(name), Some(value)) = (name, value) {
+            if !name.is_empty() {
+                cookies.insert(name.to_string(), value.trim_matches('"').to_string());
+            }
+        }
+    }
+
+    cookies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cookies() {
+        let cookies = parse_cookies("sid=abc123; theme=\"dark\";empty=; =invalid; flag");
+
+        assert_eq!(cookies.get("sid"), Some(&"abc123".to_string()));
+        assert_eq!(cookies.get("theme"), Some(&"dark".to_string()));
+        assert_eq!(cookies.get("empty"), Some(&"".to_string()));
+        assert_eq!(cookies.len(), 3);
+    }
+
+    #[test]
+    fn test_to_string() {
+        let cookie = Cookie::new("sid", "abc")
+            .path("/")
+            .max_age(Duration::from_secs(60))
+            .http_only(true)
+            .same_site(SameSite::Lax);
+        assert_eq!(
+            cookie.to_string(),
+            "sid=abc; Path=/; Max-Age=60; HttpOnly; SameSite=Lax"
+        );
+
+        assert_eq!(Cookie::removal("sid").to_string(), "sid=; Max-Age=0");
+    }
+}
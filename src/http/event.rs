@@ -0,0 +1,109 @@
+use std::time::Duration;
+
+// an event of Server-Sent Events.
+// ref <https://html.spec.whatwg.org/multipage/server-sent-events.html#event-stream-interpretation>
+#[derive(Debug, Clone, Default)]
+pub struct Event {
+    event: Option<String>,
+    id: Option<String>,
+    data: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Event {
+    // a message event, the lines of the data are sent as 'data' fields
+    pub fn new(data: &str) -> Self {
+        Event {
+            data: Some(data.to_string()),
+            ..Event::default()
+        }
+    }
+
+    // the type of the event, which is 'message' if it is not set
+    pub fn event(mut self, event: &str) -> Self {
+        self.event = Some(event.to_string());
+        self
+    }
+
+    // the client sends it back with Last-Event-ID when it reconnects
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_string());
+        self
+    }
+
+    // how long the client waits before it reconnects
+    pub fn retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    // a line break in a field would start another field, so it is removed
+    pub(crate) fn write_to(&self, dst: &mut Vec<u8>) {
+        if let Some(event) = &self.event {
+            write_field(dst, "event", &event.replace(['\r', '\n'], ""));
+        }
+        if let Some(id) = &self.id {
+            // an id with NUL is ignored by the client
+            write_field(dst, "id", &id.replace(['\r', '\n', '\0'], ""));
+        }
+        if let Some(retry) = self.retry {
+            write_field(dst, "retry", &retry.as_millis().to_string());
+        }
+        if let Some(data) = &self.data {
+            for line in data.split("\r\n").flat_map(|line| line.split(['\r', '\n'])) {
+                write_field(dst, "data", line);
+            }
+        }
+        dst.push(b'\n');
+    }
+}
+
+// a comment is ignored by the client, e.g. to keep the connection alive
+pub(crate) fn write_comment(dst: &mut Vec<u8>, comment: &str) {
+    for line in comment.split("\r\n").flat_map(|line| line.split(['\r', '\n'])) {
+        dst.push(b':');
+        if !line.is_empty() {
+            dst.push(b' ');
+            dst.extend_from_slice(line.as_bytes());
+        }
+        dst.push(b'\n');
+    }
+    dst.push(b'\n');
+}
+
+fn write_field(dst: &mut Vec<u8>, name: &str, value: &str) {
+    dst.extend_from_slice(name.as_bytes());
+    dst.extend_from_slice(b": ");
+    dst.extend_from_slice(value.as_bytes());
+    dst.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_string(event: &Event) -> String {
+        let mut dst = vec![];
+        event.write_to(&mut dst);
+        String::from_utf8(dst).unwrap()
+    }
+
+    #[test]
+    fn test_write_to() {
+        assert_eq!(to_string(&Event::new("hello")), "data: hello\n\n");
+        assert_eq!(
+            to_string(&Event::new("a\nb\r\nc").event("tick").id("7").retry(Duration::from_secs(3))),
+            "event: tick\nid: 7\nretry: 3000\ndata: a\ndata: b\ndata: c\n\n"
+        );
+        assert_eq!(to_string(&Event::new("").event("a\nb").id("1\r\0")), "event: ab\nid: 1\ndata: \n\n");
+        assert_eq!(to_string(&Event::default().retry(Duration::from_millis(500))), "retry: 500\n\n");
+    }
+
+    #[test]
+    fn test_write_comment() {
+        let mut dst = vec![];
+        write_comment(&mut dst, "keep-alive");
+        write_comment(&mut dst, "a\n");
+        assert_eq!(dst, b": keep-alive\n\n: a\n:\n\n");
+    }
+}
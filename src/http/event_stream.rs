@@ -0,0 +1,110 @@
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use crate::http::{Event, UpgradedStream};
+use crate::http::event::write_comment;
+
+const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+// how often 'forward' checks the client and the shutdown while no event comes
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// the connection of Server-Sent Events, which is given to the producer of
+// 'HttpResponseBuilder::event_stream'. the connection is closed when it is dropped.
+pub struct EventStream {
+    stream: UpgradedStream,
+    keep_alive_interval: Duration,
+    last_written: Instant,
+    is_disconnected: bool,
+}
+
+impl EventStream {
+    pub(crate) fn new(stream: UpgradedStream) -> Self {
+        EventStream {
+            stream,
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            last_written: Instant::now(),
+            is_disconnected: false,
+        }
+    }
+
+    // a comment is sent when no event has been sent for the interval, e.g. for the proxies
+    // which close an idle connection
+    pub fn set_keep_alive_interval(&mut self, keep_alive_interval: Duration) {
+        self.keep_alive_interval = keep_alive_interval;
+    }
+
+    // a producer should stop when the server is shutting down
+    pub fn is_shutdown(&self) -> bool {
+        self.stream.is_shutdown()
+    }
+
+    pub fn send(&mut self, event: &Event) -> io::Result<()> {
+        let mut output = vec![];
+        event.write_to(&mut output);
+        self.write_output(&output)
+    }
+
+    pub fn send_comment(&mut self, comment: &str) -> io::Result<()> {
+        let mut output = vec![];
+        write_comment(&mut output, comment);
+        self.write_output(&output)
+    }
+
+    // send a comment if the connection has been idle for the interval.
+    // it fails when the client has disconnected, so a producer can call it periodically to stop.
+    pub fn keep_alive(&mut self) -> io::Result<()> {
+        if self.is_disconnected() {
+            return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "the client has disconnected"));
+        }
+        if self.last_written.elapsed() >= self.keep_alive_interval {
+            self.send_comment("keep-alive")?;
+        }
+
+        Ok(())
+    }
+
+    // the client sends nothing after the request, so a readable connection has been closed
+    pub fn is_disconnected(&mut self) -> bool {
+        if self.is_disconnected || self.stream.set_nonblocking(true).is_err() {
+            return true;
+        }
+
+        let mut buffer = [0_u8; 1024];
+        self.is_disconnected = loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => break true,
+                Ok(_) => {}
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break false,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+                Err(_) => break true,
+            }
+        };
+
+        self.stream.set_nonblocking(false).is_err() || self.is_disconnected
+    }
+
+    // send the events of the channel until all the senders are dropped, the client disconnects or
+    // the server shuts down. the channel is dropped when it returns, so the producer fails to send.
+    pub fn forward(&mut self, receiver: Receiver<Event>) -> io::Result<()> {
+        while !self.is_shutdown() {
+            match receiver.recv_timeout(POLL_INTERVAL.min(self.keep_alive_interval)) {
+                Ok(event) => self.send(&event)?,
+                Err(RecvTimeoutError::Timeout) => self.keep_alive()?,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_output(&mut self, output: &[u8]) -> io::Result<()> {
+        let result = self.stream.write_all(output).and_then(|_| self.stream.flush());
+        if result.is_err() {
+            self.is_disconnected = true;
+        }
+        self.last_written = Instant::now();
+
+        result
+    }
+}
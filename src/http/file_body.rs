@@ -0,0 +1,169 @@
+use std::fs::File;
+use std::io::{self, ErrorKind, Write};
+
+use crate::util::sendfile::{FileSocket, read_at, send_file};
+
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+// a part of a file which is sent as a response body.
+// it is written with sendfile(2) to a socket on unix, and copied through a buffer otherwise.
+#[derive(Debug)]
+pub struct FileBody {
+    file: File,
+    offset: u64,
+    length: u64,
+}
+
+impl FileBody {
+    pub fn new(file: File, offset: u64, length: u64) -> Self {
+        FileBody {
+            file,
+            offset,
+            length,
+        }
+    }
+
+    pub fn get_length(&self) -> u64 {
+        self.length
+    }
+
+    // copy through a buffer, for the writers which are not sockets
+    #[cfg(unix)]
+    pub fn copy_to<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        let mut buffer = vec![0_u8; COPY_BUFFER_SIZE];
+        let mut copied = 0_u64;
+
+        while copied < self.length {
+            let size = (self.length - copied).min(COPY_BUFFER_SIZE as u64) as usize;
+            let nbytes = read_at(&self.file, &mut buffer[..size], self.offset + copied)?;
+            if nbytes == 0 {
+                return Err(io::Error::new(ErrorKind::UnexpectedEof, "the file has shrunk"));
+            }
+
+            write.write_all(&buffer[..nbytes])?;
+            copied += nbytes as u64;
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn copy_to<W: Write>(&self, write: &mut W) -> io::Result<()> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(self.offset))?;
+        if io::copy(&mut file.take(self.length), write)? < self.length {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "the file has shrunk"));
+        }
+
+        Ok(())
+    }
+
+    // read the next chunk after 'sent' bytes, e.g. to encrypt it
+    pub fn read_chunk(&self, sent: u64, buffer: &mut [u8]) -> io::Result<usize> {
+        let size = (self.length.saturating_sub(sent)).min(buffer.len() as u64) as usize;
+        let nbytes = read_at(&self.file, &mut buffer[..size], self.offset + sent)?;
+        if nbytes == 0 && size > 0 {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "the file has shrunk"));
+        }
+
+        Ok(nbytes)
+    }
+
+    // send the rest after 'sent' bytes, returns the number of bytes sent by this call.
+    // a non-blocking socket fails with 'WouldBlock' when it is full.
+    pub fn send_to<S: FileSocket>(&self, socket: &mut S, sent: u64) -> io::Result<usize> {
+        if sent >= self.length {
+            return Ok(0);
+        }
+
+        let nbytes = send_file(
+            socket,
+            &self.file,
+            self.offset + sent,
+            (self.length - sent) as usize,
+        )?;
+        if nbytes == 0 {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "the file has shrunk"));
+        }
+
+        Ok(nbytes)
+    }
+
+    // send the whole body to a blocking socket
+    #[cfg(unix)]
+    pub fn send_all<S: FileSocket>(&self, socket: &mut S) -> io::Result<()> {
+        let mut sent = 0_u64;
+
+        while sent < self.length {
+            match self.send_to(socket, sent) {
+                Ok(nbytes) => sent += nbytes as u64,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn send_all<S: FileSocket>(&self, socket: &mut S) -> io::Result<()> {
+        self.copy_to(socket)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::io::Read;
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    use crate::util::random::random_hex;
+
+    use super::*;
+
+    fn temp_file(content: &[u8]) -> (std::path::PathBuf, File) {
+        let path = env::temp_dir().join(format!("lite-ws-file-body-{}", random_hex(8)));
+        fs::write(&path, content).unwrap();
+        let file = File::open(&path).unwrap();
+
+        (path, file)
+    }
+
+    #[test]
+    fn test_copy_to() {
+        let (path, file) = temp_file(b"0123456789");
+        let file_body = FileBody::new(file, 2, 5);
+
+        let mut written = vec![];
+        file_body.copy_to(&mut written).unwrap();
+        assert_eq!(written, b"23456");
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_send_all() {
+        let content: Vec<u8> = (0..300_000).map(|i| (i % 251) as u8).collect();
+        let (path, file) = temp_file(&content);
+        let file_body = FileBody::new(file, 1_000, 250_000);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let reader = thread::spawn(move || {
+            let mut received = vec![];
+            TcpStream::connect(local_addr).unwrap().read_to_end(&mut received).unwrap();
+            received
+        });
+
+        let (mut socket, _) = listener.accept().unwrap();
+        file_body.send_all(&mut socket).unwrap();
+        drop(socket);
+
+        assert_eq!(reader.join().unwrap(), &content[1_000..251_000]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,8 +1,57 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::http::HttpStatus;
+
 #[derive(Debug)]
 pub enum HttpError {
     ReadStreamError,
-    ExceedCapacity,
+    HeaderTooLarge,
+    UriTooLong,
+    PayloadTooLarge,
     HeaderParseError,
     BodyReadError,
+    Timeout,
     ResponseBuildError,
+    // the request expects something other than '100-continue'
+    ExpectationFailed,
+    // e.g. a body with Transfer-Encoding
+    NotImplemented,
 }
+
+impl HttpError {
+    // the status which is answered when a request fails with the error
+    pub fn get_status(&self) -> HttpStatus {
+        match self {
+            HttpError::Timeout => HttpStatus::REQUEST_TIMEOUT,
+            HttpError::HeaderTooLarge => HttpStatus::REQUEST_HEADER_FIELDS_TOO_LARGE,
+            HttpError::UriTooLong => HttpStatus::URI_TOO_LONG,
+            HttpError::PayloadTooLarge => HttpStatus::PAYLOAD_TOO_LARGE,
+            HttpError::ResponseBuildError => HttpStatus::INTERNAL_SERVER_ERROR,
+            HttpError::ExpectationFailed => HttpStatus::EXPECTATION_FAILED,
+            HttpError::NotImplemented => HttpStatus::NOT_IMPLEMENTED,
+            _ => HttpStatus::BAD_REQUEST,
+        }
+    }
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let desc = match self {
+            HttpError::ReadStreamError => "fail to read the stream",
+            HttpError::HeaderTooLarge => "the request header is too large",
+            HttpError::UriTooLong => "the request uri is too long",
+            HttpError::PayloadTooLarge => "the request body is too large",
+            HttpError::HeaderParseError => "fail to parse the request header",
+            HttpError::BodyReadError => "fail to read the request body",
+            HttpError::Timeout => "the client is too slow to send the request",
+            HttpError::ResponseBuildError => "fail to build the response",
+            HttpError::ExpectationFailed => "the expectation of the request is not supported",
+            HttpError::NotImplemented => "the request needs a feature which is not supported",
+        };
+
+        f.write_str(desc)
+    }
+}
+
+impl Error for HttpError {}
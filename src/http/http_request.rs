@@ -1,7 +1,14 @@
 use std::convert::TryInto;
+use std::sync::{Arc, Mutex};
 
-use crate::http::{HttpError, HttpRequestBody, HttpRequestHeader};
+use crate::http::{
+    ClientCert, HttpError, HttpRequestBody, HttpRequestHeader, ReadHeaderAs, TlsInfo,
+};
+use crate::http::cookie::parse_cookies;
 use crate::http::method::HttpMethod;
+use crate::route::PathParams;
+use crate::server::AppState;
+use crate::session::Session;
 use crate::url::url_path::UrlPath;
 
 #[derive(Debug)]
@@ -9,8 +16,20 @@ pub struct HttpRequest {
     req_path: UrlPath,
     header: HttpRequestHeader,
     body: Option<HttpRequestBody>,
+    // filled by the Route which has matched with the path
+    path_params: PathParams,
+    // filled by the SessionMiddleware when it is mounted
+    session: Option<Session>,
+    app_state: Option<Arc<AppState>>,
+    // filled when the request has arrived over TLS
+    tls_info: Option<TlsInfo>,
+    // the route keeps the other end while an async handler owns the request
+    session_slot: Option<SessionSlot>,
 }
 
+// the session of a request which is given back when the request is dropped
+pub(crate) type SessionSlot = Arc<Mutex<Option<Session>>>;
+
 impl HttpRequest {
     pub fn new(
         header: HttpRequestHeader,
@@ -26,9 +45,28 @@ impl HttpRequest {
             req_path,
             header,
             body,
+            path_params: PathParams::new(),
+            session: None,
+            app_state: None,
+            tls_info: None,
+            session_slot: None,
         })
     }
 
+    // a copy without the body, e.g. for a handler which outlives the response
+    pub(crate) fn snapshot(&self) -> HttpRequest {
+        HttpRequest {
+            req_path: self.req_path.clone(),
+            header: self.header.clone(),
+            body: None,
+            path_params: self.path_params.clone(),
+            session: self.session.clone(),
+            app_state: self.app_state.clone(),
+            tls_info: self.tls_info.clone(),
+            session_slot: None,
+        }
+    }
+
     pub fn get_req_path(&self) -> &UrlPath {
         &self.req_path
     }
@@ -40,4 +78,111 @@ impl HttpRequest {
             HttpMethod::POST
         }
     }
+
+    pub fn get_request_header(&self) -> &HttpRequestHeader {
+        &self.header
+    }
+
+    pub fn get_body(&self) -> Option<&HttpRequestBody> {
+        self.body.as_ref()
+    }
+
+    // value of a ':name' segment of the matched route path
+    pub fn get_path_param(&self, name: &str) -> Option<&str> {
+        self.path_params.get(name).map(String::as_str)
+    }
+
+    pub fn get_path_params(&self) -> &PathParams {
+        &self.path_params
+    }
+
+    pub(crate) fn set_path_params(&mut self, path_params: PathParams) {
+        self.path_params = path_params;
+    }
+
+    // find a cookie value from the 'Cookie' header
+    pub fn get_cookie(&self, name: &str) -> Option<String> {
+        let raw: String = self.get_header("cookie")?;
+        parse_cookies(&raw).remove(name)
+    }
+
+    pub fn get_session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    pub fn get_session_mut(&mut self) -> Option<&mut Session> {
+        self.session.as_mut()
+    }
+
+    pub(crate) fn set_session(&mut self, session: Session) {
+        self.session = Some(session);
+    }
+
+    pub(crate) fn take_session(&mut self) -> Option<Session> {
+        self.session.take()
+    }
+
+    // the session is put into the slot when this request is dropped
+    pub(crate) fn set_session_slot(&mut self, session_slot: SessionSlot) {
+        self.session_slot = Some(session_slot);
+    }
+
+    // find the application state registered by 'Server::manage_state'
+    pub fn get_state<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.app_state.as_ref().and_then(|app_state| app_state.get::<T>())
+    }
+
+    pub(crate) fn set_app_state(&mut self, app_state: Arc<AppState>) {
+        self.app_state = Some(app_state);
+    }
+
+    // none if the request has arrived over plain TCP
+    pub fn get_tls_info(&self) -> Option<&TlsInfo> {
+        self.tls_info.as_ref()
+    }
+
+    // the verified certificate of the client, for authorization decisions
+    pub fn get_client_cert(&self) -> Option<&ClientCert> {
+        self.tls_info.as_ref().and_then(|tls_info| tls_info.get_client_cert())
+    }
+
+    pub(crate) fn set_tls_info(&mut self, tls_info: Option<TlsInfo>) {
+        self.tls_info = tls_info;
+    }
+}
+
+impl Drop for HttpRequest {
+    fn drop(&mut self) {
+        if let Some(session_slot) = self.session_slot.take() {
+            if let Ok(mut slot) = session_slot.lock() {
+                *slot = self.session.take();
+            }
+        }
+    }
+}
+
+impl<T> ReadHeaderAs<T> for HttpRequest
+    where
+        HttpRequestHeader: ReadHeaderAs<T>,
+{
+    fn get_header(&self, key: &str) -> Option<T> {
+        self.header.get_header(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_cookie() {
+        let header: HttpRequestHeader = b"GET / HTTP/1.1\r\nCookie: a=1; sid=xyz\r\n"
+            .to_vec()
+            .try_into()
+            .unwrap();
+        let request = HttpRequest::new(header, None).unwrap();
+
+        assert_eq!(request.get_cookie("sid"), Some("xyz".to_string()));
+        assert_eq!(request.get_cookie("b"), None);
+    }
 }
@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HttpRequestBody {
     raw: Vec<u8>
 }
@@ -10,4 +10,8 @@ impl HttpRequestBody {
             raw
         }
     }
+
+    pub fn get_raw(&self) -> &[u8] {
+        &self.raw
+    }
 }
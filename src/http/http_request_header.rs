@@ -7,7 +7,7 @@ use crate::http::version::HttpVersion;
 use crate::util::lines::Lines;
 
 /// model for http request header
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HttpRequestHeader {
     method: HttpMethod,
     req_url: String,
@@ -16,6 +16,21 @@ pub struct HttpRequestHeader {
 }
 
 impl HttpRequestHeader {
+    // e.g. for the requests of HTTP/2, which are not parsed from the text
+    pub(crate) fn new(
+        method: HttpMethod,
+        req_url: String,
+        version: HttpVersion,
+        headers: HashMap<String, String>,
+    ) -> Self {
+        HttpRequestHeader {
+            method,
+            req_url,
+            version,
+            headers,
+        }
+    }
+
     pub fn is_get(&self) -> bool {
         self.method == HttpMethod::GET
     }
@@ -24,13 +39,43 @@ impl HttpRequestHeader {
         self.method == HttpMethod::POST
     }
 
+    pub fn get_method(&self) -> HttpMethod {
+        self.method
+    }
+
     pub fn get_req_url(&self) -> &str {
         &self.req_url
     }
 
+    pub fn get_version(&self) -> &HttpVersion {
+        &self.version
+    }
+
     pub fn get_content_length(&self) -> Option<usize> {
         self.get_header("content-length")
     }
+
+    // parse the header of a HTTP/1 request, ref <https://www.rfc-editor.org/rfc/rfc9112#section-2.2>.
+    // the lenient mode accepts what old clients send when it is not ambiguous: bare LF, obs-fold,
+    // whitespace before the colon, extra spaces in the request line and the lines without a colon.
+    // the length of the body is checked in both modes, a request which can be read in two ways
+    // would let a proxy and the server disagree on where the next request starts.
+    pub(crate) fn parse(raw: Vec<u8>, is_lenient: bool) -> Result<Self, HttpError> {
+        if !is_lenient {
+            check_line_endings(&raw)?;
+        }
+
+        let mut header = parse_lines(raw, is_lenient)?;
+        check_message_length(&mut header.headers, &header.version)?;
+
+        Ok(header)
+    }
+
+    // the lenient mode without the checks of the body length, for the tests of a broken header
+    #[cfg(test)]
+    pub(crate) fn parse_unchecked(raw: Vec<u8>) -> Result<Self, HttpError> {
+        parse_lines(raw, true)
+    }
 }
 
 // this Trait is to read a header value with a specific type
@@ -41,11 +86,7 @@ pub trait ReadHeaderAs<T> {
 impl ReadHeaderAs<usize> for HttpRequestHeader {
     fn get_header(&self, key: &str) -> Option<usize> {
         if let Some(value) = self.headers.get(key) {
-            if let Ok(value_as) = value.parse::<usize>() {
-                Some(value_as)
-            } else {
-                None
-            }
+            value.parse::<usize>().ok()
         } else {
             None
         }
@@ -54,36 +95,16 @@ impl ReadHeaderAs<usize> for HttpRequestHeader {
 
 impl ReadHeaderAs<String> for HttpRequestHeader {
     fn get_header(&self, key: &str) -> Option<String> {
-        if let Some(value) = self.headers.get(key) {
-            Some(value.clone())
-        } else {
-            None
-        }
+        self.headers.get(key).cloned()
     }
 }
 
-// to make a request header from a Vec<u8> type.
+// to make a request header from a Vec<u8> type, in the strict mode
 impl TryFrom<Vec<u8>> for HttpRequestHeader {
     type Error = HttpError;
 
     fn try_from(raw: Vec<u8>) -> Result<Self, Self::Error> {
-        let mut lines = Lines::new(raw);
-        let (method, req_url, version) = if let Some(start_line) = lines.next() {
-            parse_start_line(start_line)?
-        } else {
-            return Err(HttpError::HeaderParseError);
-        };
-
-        let headers = parse_header(&mut lines)?;
-        let method: HttpMethod = method.try_into()?;
-        let version: HttpVersion = version.try_into()?;
-
-        Ok(HttpRequestHeader {
-            method,
-            req_url,
-            version,
-            headers,
-        })
+        HttpRequestHeader::parse(raw, false)
     }
 }
 
@@ -97,40 +118,194 @@ type Method = String;
 type Url = String;
 type Version = String;
 
-fn parse_start_line(first_line: String) -> Result<(Method, Url, Version), HttpError> {
-    let mut split = first_line.split(' ');
-    let method = split.next();
-    let req_url = split.next();
-    let version = split.next();
+fn parse_lines(raw: Vec<u8>, is_lenient: bool) -> Result<HttpRequestHeader, HttpError> {
+    let mut lines = Lines::new(raw);
+    let (method, req_url, version) = if let Some(start_line) = lines.next() {
+        parse_start_line(&start_line, is_lenient)?
+    } else {
+        return Err(HttpError::HeaderParseError);
+    };
+
+    let headers = parse_header(&mut lines, is_lenient)?;
+    let method: HttpMethod = method.try_into()?;
+    let version: HttpVersion = version.try_into()?;
+
+    Ok(HttpRequestHeader {
+        method,
+        req_url,
+        version,
+        headers,
+    })
+}
 
-    if let (Some(method), Some(req_url), Some(version)) = (method, req_url, version) {
-        Ok((method.to_string(), req_url.to_string(), version.to_string()))
+// every line ends with CRLF, a bare CR or LF may be read as a line break by someone else
+fn check_line_endings(raw: &[u8]) -> Result<(), HttpError> {
+    for (index, byte) in raw.iter().enumerate() {
+        let is_bare = match byte {
+            b'\r' => raw.get(index + 1) != Some(&b'\n'),
+            b'\n' => index == 0 || raw[index - 1] != b'\r',
+            _ => false,
+        };
+        if is_bare {
+            return Err(HttpError::HeaderParseError);
+        }
+    }
+
+    Ok(())
+}
+
+// method SP request-target SP HTTP-version
+fn parse_start_line(first_line: &str, is_lenient: bool) -> Result<(Method, Url, Version), HttpError> {
+    let split: Vec<&str> = if is_lenient {
+        first_line.split_whitespace().collect()
+    } else {
+        first_line.split(' ').collect()
+    };
+
+    let (method, req_url, version) = if let [method, req_url, version] = split[..] {
+        (method, req_url, version)
     } else {
-        Err(HttpError::HeaderParseError)
+        return Err(HttpError::HeaderParseError);
+    };
+
+    let is_valid = is_token(method)
+        && !req_url.is_empty()
+        && req_url.bytes().all(|byte| byte.is_ascii_graphic())
+        && is_version(version);
+    if !is_lenient && !is_valid {
+        return Err(HttpError::HeaderParseError);
     }
+
+    Ok((method.to_string(), req_url.to_string(), version.to_string()))
 }
 
-fn parse_header(raw: &mut Lines) -> Result<HashMap<String, String>, HttpError> {
+// the names are in lowercase, and the values of the same name are combined into a list
+fn parse_header(raw: &mut Lines, is_lenient: bool) -> Result<HashMap<String, String>, HttpError> {
     let mut header_map = HashMap::<String, String>::new();
+    let mut last_key: Option<String> = None;
 
     for line in raw {
-        let mut split = line.split(':');
-        let key = split.next();
-        let value = split.next();
-
-        if let (Some(key), Some(value)) = (key, value) {
-            header_map.insert(
-                key.trim().to_lowercase().to_string(),
-                value.trim().to_string(),
-            );
-        } else {
+        // obs-fold continues the value of the line before it
+        if line.starts_with([' ', '\t']) {
+            let value = match (is_lenient, &last_key) {
+                (true, Some(last_key)) => header_map.get_mut(last_key),
+                _ => None,
+            };
+            if let Some(value) = value {
+                let folded = parse_value(&line)?;
+                if !folded.is_empty() {
+                    value.push(' ');
+                    value.push_str(folded);
+                }
+                continue;
+            }
+            return Err(HttpError::HeaderParseError);
+        }
+
+        let (key, value) = if let Some((key, value)) = line.split_once(':') {
+            (key, value)
+        } else if is_lenient {
             continue;
+        } else {
+            return Err(HttpError::HeaderParseError);
+        };
+
+        // whitespace between the name and the colon is not allowed
+        let key = if is_lenient { key.trim() } else { key };
+        if !is_token(key) {
+            if is_lenient {
+                continue;
+            }
+            return Err(HttpError::HeaderParseError);
+        }
+
+        let key = key.to_lowercase();
+        let value = parse_value(value)?;
+        if let Some(combined) = header_map.get_mut(&key) {
+            // a request to two hosts could be routed by one and handled by the other
+            if key == "host" {
+                return Err(HttpError::HeaderParseError);
+            }
+            combined.push_str(if key == "cookie" { "; " } else { ", " });
+            combined.push_str(value);
+        } else {
+            header_map.insert(key.clone(), value.to_string());
         }
+        last_key = Some(key);
     }
 
     Ok(header_map)
 }
 
+// the whitespace around the value is not a part of it, and no control character is allowed in it
+fn parse_value(value: &str) -> Result<&str, HttpError> {
+    let value = value.trim_matches([' ', '\t']);
+    if value.bytes().any(|byte| byte != b'\t' && byte.is_ascii_control()) {
+        return Err(HttpError::HeaderParseError);
+    }
+
+    Ok(value)
+}
+
+// the body of a request is framed by a single Content-Length.
+// Transfer-Encoding is not supported, and it must never be used together with Content-Length.
+// ref <https://www.rfc-editor.org/rfc/rfc9112#section-6.3>
+fn check_message_length(
+    headers: &mut HashMap<String, String>,
+    version: &HttpVersion,
+) -> Result<(), HttpError> {
+    if headers.contains_key("transfer-encoding") {
+        if headers.contains_key("content-length")
+            || (version.get_major(), version.get_minor()) < (1, 1)
+        {
+            return Err(HttpError::HeaderParseError);
+        }
+        return Err(HttpError::NotImplemented);
+    }
+
+    let content_length = if let Some(content_length) = headers.get_mut("content-length") {
+        content_length
+    } else {
+        return Ok(());
+    };
+
+    // the same length can be repeated, e.g. 'Content-Length: 42, 42'
+    let mut lengths = content_length.split(',').map(|length| length.trim_matches([' ', '\t']));
+    let first = lengths.next().unwrap_or_default().to_string();
+    if first.is_empty()
+        || !first.bytes().all(|byte| byte.is_ascii_digit())
+        || lengths.any(|length| length != first)
+    {
+        return Err(HttpError::HeaderParseError);
+    }
+    // a length which does not fit would be read as no body
+    if first.parse::<usize>().is_err() {
+        return Err(HttpError::PayloadTooLarge);
+    }
+
+    *content_length = first;
+    Ok(())
+}
+
+// tchar, ref <https://www.rfc-editor.org/rfc/rfc9110#section-5.6.2>
+fn is_token(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte))
+}
+
+// HTTP-name "/" DIGIT "." DIGIT
+fn is_version(value: &str) -> bool {
+    let value = value.as_bytes();
+
+    value.len() == 8
+        && value.starts_with(b"HTTP/")
+        && value[5].is_ascii_digit()
+        && value[6] == b'.'
+        && value[7].is_ascii_digit()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -139,7 +314,7 @@ mod tests {
     fn test_parse_start_line() -> Result<(), HttpError> {
         let raw = Vec::from("GET /hello HTTP/1.1\r\nHost: 127.0.0.1:8888".as_bytes());
         let mut lines = Lines::new(raw);
-        let (method, path, version) = parse_start_line(lines.next().unwrap())?;
+        let (method, path, version) = parse_start_line(&lines.next().unwrap(), false)?;
 
         assert_eq!(method, "GET");
         assert_eq!(path, "/hello");
@@ -161,8 +336,9 @@ User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_5) AppleWebKit/537.36 (
 
         lines.next().unwrap();
 
-        let headers = parse_header(&mut lines).unwrap();
+        let headers = parse_header(&mut lines, false).unwrap();
         assert_eq!(headers.get("connection"), Some(&"keep-alive".to_string()));
+        assert_eq!(headers.get("host"), Some(&"127.0.0.1:8888".to_string()));
     }
 
     #[test]
@@ -175,7 +351,9 @@ Upgrade-Insecure-Requests: 1
 User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/80.0.3987.163 Whale/2.7.99.22 Safari/537.36";
         let raw = Vec::from(raw.as_bytes());
 
-        let request_header: HttpRequestHeader = raw.try_into().unwrap();
+        // the lines end with a bare LF, which only the lenient mode accepts
+        assert!(HttpRequestHeader::try_from(raw.clone()).is_err());
+        let request_header = HttpRequestHeader::parse(raw, true).unwrap();
         assert_eq!(
             request_header.get_header("upgrade-insecure-requests"),
             Some(1)
@@ -185,4 +363,78 @@ User-Agent: Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_5) AppleWebKit/537.36 (
             Some("keep-alive".to_string())
         );
     }
+
+    fn parse(raw: &str, is_lenient: bool) -> Result<HttpRequestHeader, HttpError> {
+        HttpRequestHeader::parse(raw.as_bytes().to_vec(), is_lenient)
+    }
+
+    #[test]
+    fn test_strict_parse() {
+        let raw = "GET / HTTP/1.1\r\nHost: a:8080\r\nAccept: a\r\nAccept:  b\t\r\n\
+            Cookie: c=1\r\nCookie: d=2\r\n\r\n";
+        let header = parse(raw, false).unwrap();
+        assert_eq!(header.get_header("host"), Some("a:8080".to_string()));
+        assert_eq!(header.get_header("accept"), Some("a, b".to_string()));
+        assert_eq!(header.get_header("cookie"), Some("c=1; d=2".to_string()));
+
+        for raw in [
+            // bare LF and CR
+            "GET / HTTP/1.1\nHost: a\r\n\r\n",
+            "GET / HTTP/1.1\r\nHost: a\rX: b\r\n\r\n",
+            // obs-fold
+            "GET / HTTP/1.1\r\nX-A: a\r\n b\r\n\r\n",
+            // whitespace before the colon
+            "GET / HTTP/1.1\r\nHost : a\r\n\r\n",
+            // invalid tokens
+            "GET / HTTP/1.1\r\nX(A): a\r\n\r\n",
+            "GET / HTTP/1.1\r\nX-A\r\n\r\n",
+            "GET  / HTTP/1.1\r\n\r\n",
+            "GET / HTTPS/1.1\r\n\r\n",
+            // a control character in the value
+            "GET / HTTP/1.1\r\nX-A: a\0b\r\n\r\n",
+            "GET / HTTP/1.1\r\nHost: a\r\nHost: b\r\n\r\n",
+        ] {
+            assert!(matches!(parse(raw, false), Err(HttpError::HeaderParseError)), "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn test_lenient_parse() {
+        let header = parse("GET  /  HTTP/1.1\nHost : a:8080\nX-A: a\n\tb\nbroken\n\n", true).unwrap();
+        assert_eq!(header.get_req_url(), "/");
+        assert_eq!(header.get_header("host"), Some("a:8080".to_string()));
+        assert_eq!(header.get_header("x-a"), Some("a b".to_string()));
+
+        // a body of two lengths is never accepted
+        let raw = "POST / HTTP/1.1\nContent-Length: 3\nTransfer-Encoding: chunked\n\n";
+        assert!(matches!(parse(raw, true), Err(HttpError::HeaderParseError)));
+    }
+
+    #[test]
+    fn test_message_length() {
+        let length = |raw: &str| parse(raw, false).map(|header| header.get_content_length());
+
+        assert_eq!(length("POST / HTTP/1.1\r\nContent-Length: 42\r\n\r\n").unwrap(), Some(42));
+        assert_eq!(
+            length("POST / HTTP/1.1\r\nContent-Length: 42\r\nContent-Length: 42, 42\r\n\r\n").unwrap(),
+            Some(42)
+        );
+        for raw in [
+            "POST / HTTP/1.1\r\nContent-Length: 42\r\nContent-Length: 43\r\n\r\n",
+            "POST / HTTP/1.1\r\nContent-Length: +42\r\n\r\n",
+            "POST / HTTP/1.1\r\nContent-Length: \r\n\r\n",
+            "POST / HTTP/1.1\r\nContent-Length: 3\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n",
+        ] {
+            assert!(matches!(length(raw), Err(HttpError::HeaderParseError)), "{:?}", raw);
+        }
+        assert!(matches!(
+            length("POST / HTTP/1.1\r\nContent-Length: 99999999999999999999999\r\n\r\n"),
+            Err(HttpError::PayloadTooLarge)
+        ));
+        assert!(matches!(
+            length("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            Err(HttpError::NotImplemented)
+        ));
+    }
 }
@@ -1,34 +1,82 @@
-use std::collections::HashMap;
-use std::convert::TryFrom;
 use std::fs::File;
 use std::io;
 use std::io::{Cursor, Read, Write};
 
-use crate::http::{HttpError, HttpStatus};
+use crate::http::{
+    Cookie, EventStream, FileBody, FileSocket, HttpError, HttpStatus, UpgradedStream,
+};
 use crate::http::content_type::ContentType;
+use crate::http::upgraded_stream::Upgrade;
 use crate::http::version::{HttpVersion, Protocol};
 
+// the headers, the body in the memory, and the file body of a response
+pub(crate) type ResponseParts = (Vec<(String, String)>, Vec<u8>, Option<FileBody>);
+
 pub struct HttpResponse {
     version: HttpVersion,
     status: HttpStatus,
     content_type: Option<ContentType>,
-    headers: Option<HashMap<String, String>>,
+    headers: Vec<(String, String)>,
     body_length: usize,
-    body: Option<Box<dyn Read>>,
+    body: Option<Box<dyn Read + Send>>,
+    // a file is not read into the memory, it is sent after the header
+    file_body: Option<FileBody>,
+    // whether the connection is kept for the next request
+    keep_alive: bool,
+    // the connection is taken over after the response, e.g. by WebSocket
+    upgrade: Option<Upgrade>,
 }
 
 impl HttpResponse {
     // to make it simple to make a HttpResponse with status
     pub fn new_with(status: HttpStatus) -> Self {
         HttpResponse {
-            version: HttpVersion::new(Protocol::HTTP, 1, 0),
+            version: HttpVersion::new(Protocol::HTTP, 1, 1),
             status,
             content_type: None,
-            headers: None,
+            headers: vec![],
             body_length: 0,
             body: None,
+            file_body: None,
+            keep_alive: false,
+            upgrade: None,
         }
     }
+
+    pub fn get_status(&self) -> &HttpStatus {
+        &self.status
+    }
+
+    pub fn set_status(&mut self, status: HttpStatus) {
+        self.status = status;
+    }
+
+    // append a header, the same key can be appended several times (e.g. Set-Cookie)
+    pub fn append_header(&mut self, key: &str, value: &str) {
+        self.headers.push((key.to_string(), value.to_string()));
+    }
+
+    pub fn set_cookie(&mut self, cookie: &Cookie) {
+        self.append_header("Set-Cookie", &cookie.to_string());
+    }
+
+    pub fn is_keep_alive(&self) -> bool {
+        self.keep_alive
+    }
+
+    pub(crate) fn set_keep_alive(&mut self, keep_alive: bool) {
+        self.keep_alive = keep_alive;
+    }
+
+    pub(crate) fn set_upgrade(&mut self, upgrade: Upgrade) {
+        self.upgrade = Some(upgrade);
+    }
+
+    // taken after the response has been written
+    pub(crate) fn take_upgrade(&mut self) -> Option<Upgrade> {
+        self.upgrade.take()
+    }
+
     // is it better to move the respond function to the Request struct?
     pub fn respond<W: Write>(&mut self, write: &mut W) {
         if let Err(error) = self.try_respond(write) {
@@ -36,55 +84,110 @@ impl HttpResponse {
         }
     }
 
+    // respond to a socket, the file body is sent with sendfile(2) where it is available
+    pub fn respond_to_socket<S: FileSocket>(&mut self, socket: &mut S) {
+        if let Err(error) = self.try_respond_to_socket(socket) {
+            eprintln!("[error] error while respond: {:?}", error);
+        }
+    }
+
     fn try_respond<W: Write>(&mut self, write: &mut W) -> io::Result<()> {
-        write.write_all(
-            format!(
-                "{} {}\r\n",
-                self.version.to_string(),
-                self.status.to_string()
-            )
-                .as_bytes(),
-        )?;
-        write.write_all(b"Connection: close\r\n")?;
+        let (output, file_body) = self.take_output()?;
+        write.write_all(output.as_slice())?;
+        if let Some(file_body) = file_body {
+            file_body.copy_to(write)?;
+        }
+
+        write.flush()?;
+        Ok(())
+    }
+
+    fn try_respond_to_socket<S: FileSocket>(&mut self, socket: &mut S) -> io::Result<()> {
+        let (output, file_body) = self.take_output()?;
+        socket.write_all(output.as_slice())?;
+        if let Some(file_body) = file_body {
+            file_body.send_all(socket)?;
+        }
+
+        socket.flush()?;
+        Ok(())
+    }
 
+    // the header and the body in the memory, and the file body which should be sent after them
+    pub(crate) fn take_output(&mut self) -> io::Result<(Vec<u8>, Option<FileBody>)> {
+        let (headers, mut content, file_body) = self.take_parts()?;
+
+        let mut output: Vec<u8> = Vec::with_capacity(content.len() + 256);
+        output.extend_from_slice(format!("{} {}\r\n", self.version, self.status).as_bytes());
+        if self.upgrade.is_some() && self.status == HttpStatus::SWITCHING_PROTOCOL {
+            output.extend_from_slice(b"Connection: Upgrade\r\n");
+        } else if self.keep_alive && self.upgrade.is_none() {
+            output.extend_from_slice(b"Connection: keep-alive\r\n");
+        } else {
+            output.extend_from_slice(b"Connection: close\r\n");
+        }
+
+        for (key, value) in headers.iter() {
+            output.extend_from_slice(format!("{}: {}\r\n", key, value).as_bytes());
+        }
+        output.extend_from_slice(b"\r\n");
+        if file_body.is_none() {
+            output.append(&mut content);
+        }
+
+        Ok((output, file_body))
+    }
+
+    // the headers including Content-Type and Content-Length, the body in the memory, and the file
+    // body. HTTP/2 frames them by itself.
+    pub(crate) fn take_parts(&mut self) -> io::Result<ResponseParts> {
+        // TODO: support chunked
+        // 204 and 304 never have a body, ref <https://www.rfc-editor.org/rfc/rfc9110#section-8.6>
+        let has_no_body = self.status.code == 204 || self.status.code == 304;
+        let mut content: Vec<u8> = Vec::with_capacity(self.body_length);
         if let Some(body) = &mut self.body {
-            // TODO: support chunked
-            let mut content: Vec<u8> = Vec::with_capacity(self.body_length);
             body.read_to_end(&mut content)?;
+        }
+        let mut file_body = self.file_body.take();
+        if has_no_body {
+            content.clear();
+            file_body = None;
+        }
+        let content_length = match &file_body {
+            Some(file_body) => file_body.get_length(),
+            None => content.len() as u64,
+        };
 
-            if let Some(content_type) = &self.content_type {
-                write.write_all(
-                    format!("Content-Type: {}\r\n", content_type.to_string()).as_bytes(),
-                )?;
-                write.write_all(format!("Content-Length: {}\r\n", content.len()).as_bytes())?;
-            }
-            write.write_all(b"\r\n")?;
-            write.write_all(content.as_slice())?;
+        let mut headers = std::mem::take(&mut self.headers);
+        if let Some(content_type) = &self.content_type {
+            headers.push(("Content-Type".to_string(), content_type.to_string()));
+        }
+        // an informational response has no body, and the body of a stream ends with the connection.
+        // the length of 304 would be the length of the file which is not sent.
+        if self.status.code >= 200 && self.upgrade.is_none() && !has_no_body {
+            headers.push(("Content-Length".to_string(), content_length.to_string()));
         }
 
-        write.flush()?;
-        Ok(())
+        Ok((headers, content, file_body))
     }
 }
 
 // to gather values for building a http response instance.
+#[derive(Default)]
 pub struct HttpResponseBuilder {
     status: Option<HttpStatus>,
     content_type: Option<ContentType>,
-    headers: Option<HashMap<String, String>>,
-    body: (Option<Box<dyn Read>>, usize),
+    headers: Vec<(String, String)>,
+    body: (Option<Box<dyn Read + Send>>, usize),
     file: Option<File>,
+    // offset and length of the part of the file, the whole file if it is none
+    file_range: Option<(u64, u64)>,
+    upgrade: Option<Upgrade>,
 }
 
 impl HttpResponseBuilder {
     pub fn new() -> Self {
-        HttpResponseBuilder {
-            status: None,
-            content_type: None,
-            headers: None,
-            body: (None, 0),
-            file: None,
-        }
+        HttpResponseBuilder::default()
     }
 
     pub fn set_status(mut self, status: HttpStatus) -> Self {
@@ -92,6 +195,16 @@ impl HttpResponseBuilder {
         self
     }
 
+    // append a response header
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn cookie(self, cookie: &Cookie) -> Self {
+        self.header("Set-Cookie", &cookie.to_string())
+    }
+
     // set the content type of the body as html
     pub fn html(mut self, html: String) -> Self {
         let html_len = html.len();
@@ -112,34 +225,119 @@ impl HttpResponseBuilder {
     pub fn file(mut self, content_type: ContentType, file: File) -> Self {
         self.content_type = Some(content_type);
         self.file = Some(file);
+        self.file_range = None;
+
+        self
+    }
+
+    // send only a part of the file, e.g. for a range request
+    pub fn file_range(
+        mut self,
+        content_type: ContentType,
+        file: File,
+        offset: u64,
+        length: u64,
+    ) -> Self {
+        self.content_type = Some(content_type);
+        self.file = Some(file);
+        self.file_range = Some((offset, length));
 
         self
     }
 
-    pub fn build(mut self) -> Result<HttpResponse, HttpError> {
+    // switch to another protocol with '101 Switching Protocols'. the callback owns the connection on
+    // a worker thread after the response is sent, instead of the next request.
+    pub fn upgrade<F>(mut self, protocol: &str, callback: F) -> Self
+        where
+            F: FnOnce(UpgradedStream) + Send + 'static,
+    {
+        self.status = Some(HttpStatus::SWITCHING_PROTOCOL);
+        self.headers.push(("Upgrade".to_string(), protocol.to_string()));
+        self.upgrade = Some(Box::new(callback));
+
+        self
+    }
+
+    // Server-Sent Events, the producer owns the connection on a worker thread after the header
+    // is sent. the connection is not kept for the next request.
+    pub fn event_stream<F>(mut self, producer: F) -> Self
+        where
+            F: FnOnce(EventStream) + Send + 'static,
+    {
+        self.content_type = Some(ContentType::TEXT_EVENT_STREAM);
+        self.headers.push(("Cache-Control".to_string(), "no-cache".to_string()));
+        self.upgrade = Some(Box::new(move |stream| producer(EventStream::new(stream))));
+
+        self
+    }
+
+    pub fn build(self) -> Result<HttpResponse, HttpError> {
         let status = if let Some(status) = self.status {
             status
         } else {
             HttpStatus::OK
         };
 
-        if let Some(file) = self.file {
-            if let Ok(metadata) = file.metadata() {
-                let file_len = metadata.len() as usize;
-                self.body = (Some(Box::new(file)), file_len);
+        let file_body = if let Some(file) = self.file {
+            let file_len = if let Ok(metadata) = file.metadata() {
+                metadata.len()
             } else {
                 return Err(HttpError::ResponseBuildError);
+            };
+
+            let (offset, length) = self.file_range.unwrap_or((0, file_len));
+            if offset.checked_add(length).is_none_or(|end| end > file_len) {
+                return Err(HttpError::ResponseBuildError);
             }
-        }
 
-        // TODO: this code is for the test.
+            Some(FileBody::new(file, offset, length))
+        } else {
+            None
+        };
+
         Ok(HttpResponse {
-            version: HttpVersion::try_from(String::from("HTTP/1.0")).unwrap(),
+            version: HttpVersion::new(Protocol::HTTP, 1, 1),
             status,
             content_type: self.content_type,
             headers: self.headers,
             body_length: self.body.1,
             body: self.body.0,
+            file_body,
+            keep_alive: false,
+            upgrade: self.upgrade,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn respond(http_response: &mut HttpResponse) -> String {
+        let mut written = vec![];
+        http_response.respond(&mut written);
+        String::from_utf8(written).unwrap()
+    }
+
+    #[test]
+    fn test_content_length() {
+        let mut http_response = HttpResponseBuilder::new()
+            .body(ContentType::TEXT_PLAIN, b"hello".to_vec())
+            .build()
+            .unwrap();
+        assert!(respond(&mut http_response).contains("Content-Length: 5\r\n"));
+
+        for status in [HttpStatus::NO_CONTENT, HttpStatus::NOT_MODIFIED] {
+            let mut http_response = HttpResponseBuilder::new()
+                .set_status(status)
+                .header("ETag", "\"1\"")
+                .body(ContentType::TEXT_PLAIN, b"hello".to_vec())
+                .build()
+                .unwrap();
+            let response = respond(&mut http_response);
+            assert!(!response.contains("Content-Length"), "{}", response);
+            assert!(response.contains("ETag: \"1\"\r\n"), "{}", response);
+            assert!(response.ends_with("\r\n\r\n"), "{}", response);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::fmt;
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct HttpStatus {
     pub code: u16,
@@ -19,9 +21,9 @@ impl HttpStatus {
     pub const CREATED: HttpStatus = create_http_status(201, "Created");
     pub const ACCEPTED: HttpStatus = create_http_status(202, "Accepted");
     // 203
-    // 204
+    pub const NO_CONTENT: HttpStatus = create_http_status(204, "No Content");
     // 205
-    // 206
+    pub const PARTIAL_CONTENT: HttpStatus = create_http_status(206, "Partial Content");
     // 207
     // 208
     // 226
@@ -31,7 +33,7 @@ impl HttpStatus {
     pub const MOVED_PERMANENTLY: HttpStatus = create_http_status(301, "Moved Permanently");
     pub const FOUND: HttpStatus = create_http_status(302, "Found");
     // 303
-    // 304
+    pub const NOT_MODIFIED: HttpStatus = create_http_status(304, "Not Modified");
     // 305
     // 306
     // 307
@@ -47,34 +49,35 @@ impl HttpStatus {
     // 405
     // 406
     // 407
-    // 408
+    pub const REQUEST_TIMEOUT: HttpStatus = create_http_status(408, "Request Timeout");
     // 409
     // 410
     // 411
     // 412
-    // 413
-    // 414
-    // 415
-    // 416
-    // 417
+    pub const PAYLOAD_TOO_LARGE: HttpStatus = create_http_status(413, "Payload Too Large");
+    pub const URI_TOO_LONG: HttpStatus = create_http_status(414, "URI Too Long");
+    pub const UNSUPPORTED_MEDIA_TYPE: HttpStatus = create_http_status(415, "Unsupported Media Type");
+    pub const RANGE_NOT_SATISFIABLE: HttpStatus = create_http_status(416, "Range Not Satisfiable");
+    pub const EXPECTATION_FAILED: HttpStatus = create_http_status(417, "Expectation Failed");
     // 418
     // 421
     // 422
     // 423
     // 424
-    // 426
+    pub const UPGRADE_REQUIRED: HttpStatus = create_http_status(426, "Upgrade Required");
     // 428
     // 429
-    // 431
+    pub const REQUEST_HEADER_FIELDS_TOO_LARGE: HttpStatus =
+        create_http_status(431, "Request Header Fields Too Large");
     // 451
 
     // Server Error
     pub const INTERNAL_SERVER_ERROR: HttpStatus = create_http_status(500, "Internal Server Error");
     pub const NOT_IMPLEMENTED: HttpStatus = create_http_status(501, "Not Implemented");
     // 502
-    // 503
+    pub const SERVICE_UNAVAILABLE: HttpStatus = create_http_status(503, "Service Unavailable");
     // 504
-    // 505
+    pub const HTTP_VERSION_NOT_SUPPORTED: HttpStatus = create_http_status(505, "HTTP Version Not Supported");
     // 506
     // 507
     // 508
@@ -87,9 +90,9 @@ impl HttpStatus {
     }
 }
 
-impl ToString for HttpStatus {
-    fn to_string(&self) -> String {
-        format!("{} {}", self.code, self.desc)
+impl fmt::Display for HttpStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.code, self.desc)
     }
 }
 
@@ -0,0 +1,103 @@
+use std::error::Error;
+
+use crate::http::{ContentType, HttpResponse, HttpResponseBuilder, HttpStatus};
+
+// error returned by a handler, the Router turns it into a response with the registered error handler
+pub type HandlerError = Box<dyn Error + Send + Sync>;
+
+// a value which a handler can return instead of building a HttpResponse by itself
+pub trait IntoResponse {
+    fn into_response(self) -> Result<HttpResponse, HandlerError>;
+}
+
+impl IntoResponse for HttpResponse {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        Ok(self)
+    }
+}
+
+// empty response with the status
+impl IntoResponse for HttpStatus {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        Ok(HttpResponse::new_with(self))
+    }
+}
+
+impl IntoResponse for String {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        build(ContentType::TEXT_PLAIN, self.into_bytes())
+    }
+}
+
+impl IntoResponse for &'static str {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        build(ContentType::TEXT_PLAIN, self.as_bytes().to_vec())
+    }
+}
+
+impl IntoResponse for Vec<u8> {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        build(ContentType::APPLICATION_OCTET_STREAM, self)
+    }
+}
+
+// replace the status of the response
+impl<T: IntoResponse> IntoResponse for (HttpStatus, T) {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        let (status, response) = self;
+        let mut response = response.into_response()?;
+        response.set_status(status);
+
+        Ok(response)
+    }
+}
+
+impl<T: IntoResponse, E: Into<HandlerError>> IntoResponse for Result<T, E> {
+    fn into_response(self) -> Result<HttpResponse, HandlerError> {
+        self.map_err(Into::into)?.into_response()
+    }
+}
+
+fn build(content_type: ContentType, body: Vec<u8>) -> Result<HttpResponse, HandlerError> {
+    Ok(HttpResponseBuilder::new().body(content_type, body).build()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+
+    fn respond<T: IntoResponse>(value: T) -> String {
+        let mut written = vec![];
+        value.into_response().unwrap().respond(&mut written);
+
+        String::from_utf8(written).unwrap()
+    }
+
+    #[test]
+    fn test_into_response() {
+        let response = respond("hello");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Content-Type: text/plain\r\n"));
+        assert!(response.ends_with("\r\n\r\nhello"));
+
+        let response = respond((HttpStatus::CREATED, vec![1_u8, 2]));
+        assert!(response.starts_with("HTTP/1.1 201 Created"));
+        assert!(response.contains("Content-Type: application/octet-stream\r\n"));
+
+        let response = respond(HttpStatus::NOT_FOUND);
+        assert!(response.starts_with("HTTP/1.1 404 Not Found"));
+
+        let ok: Result<String, io::Error> = Ok("fine".to_string());
+        assert!(respond(ok).ends_with("fine"));
+    }
+
+    #[test]
+    fn test_error() {
+        let failed: Result<String, io::Error> = Err(io::Error::other("broken"));
+        let error = failed.into_response().err().unwrap();
+
+        assert_eq!(error.to_string(), "broken");
+    }
+}
@@ -2,7 +2,7 @@ use std::convert::TryFrom;
 
 use crate::http::HttpError;
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum HttpMethod {
     GET,
     POST,
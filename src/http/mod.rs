@@ -1,12 +1,27 @@
+pub use client_cert::ClientCert;
 pub use content_type::ContentType;
+pub use cookie::{Cookie, SameSite};
+pub use event::Event;
+pub use event_stream::EventStream;
+pub use file_body::FileBody;
 pub use http_error::HttpError;
 pub use http_request::HttpRequest;
 pub use http_request_body::HttpRequestBody;
-pub use http_request_header::HttpRequestHeader;
+pub use http_request_header::{HttpRequestHeader, ReadHeaderAs};
 pub use http_response::{HttpResponse, HttpResponseBuilder};
 pub use http_status::HttpStatus;
+pub use tls_info::TlsInfo;
+pub use upgraded_stream::UpgradedStream;
+pub use into_response::{HandlerError, IntoResponse};
+pub use version::{HttpVersion, Protocol};
+pub use crate::util::sendfile::FileSocket;
 
+mod client_cert;
 mod content_type;
+mod cookie;
+mod event;
+mod event_stream;
+mod file_body;
 
 pub mod method;
 
@@ -16,4 +31,7 @@ mod http_request_body;
 mod http_request_header;
 mod http_response;
 mod http_status;
+mod into_response;
+mod tls_info;
+pub(crate) mod upgraded_stream;
 mod version;
@@ -0,0 +1,54 @@
+use crate::http::ClientCert;
+
+// the parameters negotiated by the TLS handshake of the connection which carried a request
+#[derive(Debug, Clone, Default)]
+pub struct TlsInfo {
+    // the host name sent by the client with SNI
+    server_name: Option<String>,
+    alpn_protocol: Option<String>,
+    // e.g. 'TLSv1_3'
+    protocol_version: Option<String>,
+    // e.g. 'TLS13_AES_256_GCM_SHA384'
+    cipher_suite: Option<String>,
+    // none unless the client has presented a certificate
+    client_cert: Option<ClientCert>,
+}
+
+impl TlsInfo {
+    #[cfg_attr(not(feature = "tls"), allow(dead_code))]
+    pub(crate) fn new(
+        server_name: Option<String>,
+        alpn_protocol: Option<String>,
+        protocol_version: Option<String>,
+        cipher_suite: Option<String>,
+        client_cert: Option<ClientCert>,
+    ) -> Self {
+        TlsInfo {
+            server_name,
+            alpn_protocol,
+            protocol_version,
+            cipher_suite,
+            client_cert,
+        }
+    }
+
+    pub fn get_server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    pub fn get_alpn_protocol(&self) -> Option<&str> {
+        self.alpn_protocol.as_deref()
+    }
+
+    pub fn get_protocol_version(&self) -> Option<&str> {
+        self.protocol_version.as_deref()
+    }
+
+    pub fn get_cipher_suite(&self) -> Option<&str> {
+        self.cipher_suite.as_deref()
+    }
+
+    pub fn get_client_cert(&self) -> Option<&ClientCert> {
+        self.client_cert.as_ref()
+    }
+}
@@ -0,0 +1,130 @@
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use crate::server::ShutdownHandle;
+use crate::worker::ConnectionStream;
+
+// takes over the connection after the response has been sent, e.g. '101 Switching Protocols'
+pub(crate) type Upgrade = Box<dyn FnOnce(UpgradedStream) + Send>;
+
+// the connection which is taken over from HTTP, e.g. by WebSocket.
+// the bytes which have arrived with the request are read first.
+pub struct UpgradedStream {
+    stream: ConnectionStream,
+    buffered: Vec<u8>,
+    offset: usize,
+    shutdown_handle: ShutdownHandle,
+}
+
+impl UpgradedStream {
+    pub(crate) fn new(
+        stream: ConnectionStream,
+        buffered: Vec<u8>,
+        shutdown_handle: ShutdownHandle,
+    ) -> Self {
+        UpgradedStream {
+            stream,
+            buffered,
+            offset: 0,
+            shutdown_handle,
+        }
+    }
+
+    // a long-lived connection should be closed when the server is shutting down
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown_handle.is_shutdown()
+    }
+
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.get_socket().set_read_timeout(timeout)
+    }
+
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.get_socket().set_write_timeout(timeout)
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.get_socket().peer_addr()
+    }
+
+    // the bytes which have arrived after the request and are not read yet,
+    // then the stream reads the connection directly
+    pub fn take_buffered(&mut self) -> Vec<u8> {
+        let mut buffered = std::mem::take(&mut self.buffered);
+        let rest = buffered.split_off(self.offset.min(buffered.len()));
+        self.offset = 0;
+
+        rest
+    }
+
+    pub(crate) fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.stream.get_socket().set_nonblocking(nonblocking)
+    }
+
+    // the same as dropping it
+    pub fn close(self) {}
+}
+
+impl Drop for UpgradedStream {
+    // the client gets the rest of the output before the connection is closed
+    fn drop(&mut self) {
+        self.stream.flush().ok();
+        self.stream.close_gracefully();
+    }
+}
+
+impl Read for UpgradedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.offset < self.buffered.len() {
+            let nbytes = buf.len().min(self.buffered.len() - self.offset);
+            buf[..nbytes].copy_from_slice(&self.buffered[self.offset..self.offset + nbytes]);
+            self.offset += nbytes;
+            return Ok(nbytes);
+        }
+
+        self.stream.read(buf)
+    }
+}
+
+impl Write for UpgradedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    #[test]
+    fn test_read_buffered() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (socket, _) = listener.accept().unwrap();
+        client.write_all(b"ghi").unwrap();
+
+        let stream = ConnectionStream::Plain(socket);
+        let mut upgraded = UpgradedStream::new(stream, b"abcdef".to_vec(), ShutdownHandle::new());
+        let mut buffer = [0_u8; 3];
+        upgraded.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"abc");
+        assert_eq!(upgraded.take_buffered(), b"def");
+        assert!(upgraded.take_buffered().is_empty());
+        upgraded.read_exact(&mut buffer).unwrap();
+        assert_eq!(&buffer, b"ghi");
+
+        // the connection is closed when it is dropped
+        upgraded.write_all(b"bye").unwrap();
+        drop(upgraded);
+        let mut rest = vec![];
+        client.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"bye");
+    }
+}
@@ -1,7 +1,9 @@
 use std::convert::{TryFrom, TryInto};
+use std::fmt;
 
 use crate::http::HttpError;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone)]
 pub enum Protocol {
     HTTP,
@@ -24,11 +26,23 @@ impl HttpVersion {
             minor,
         }
     }
+
+    pub fn get_protocol(&self) -> &Protocol {
+        &self.protocol
+    }
+
+    pub fn get_major(&self) -> u8 {
+        self.major
+    }
+
+    pub fn get_minor(&self) -> u8 {
+        self.minor
+    }
 }
 
-impl ToString for HttpVersion {
-    fn to_string(&self) -> String {
-        format!("{:?}/{}.{}", self.protocol, self.major, self.minor)
+impl fmt::Display for HttpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}/{}.{}", self.protocol, self.major, self.minor)
     }
 }
 
@@ -95,6 +109,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(unused_variables, clippy::assertions_on_constants)]
     fn test_parse_version() {
         let src = "1.0";
 
@@ -1,9 +1,21 @@
+// the generated code of 'embed_dir' refers to this crate by its name
+extern crate self as lite_ws;
+
 pub mod server;
+pub mod extract;
 pub mod route;
 pub mod http;
 pub mod pipe;
+pub mod session;
+pub mod rt;
+pub mod embed;
+pub mod websocket;
 
 mod util;
 
 pub(crate) mod worker;
+pub(crate) mod h2;
 pub(crate) mod url;
+
+#[cfg(test)]
+pub(crate) mod test_support;
@@ -1,19 +1,32 @@
-use crate::http::{HttpRequest, HttpResponseBuilder};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use crate::http::{HttpRequest, HttpResponse, HttpResponseBuilder};
 use crate::http::method::HttpMethod;
-use crate::route::{RouteError, RoutePath};
-use crate::route::route::{ExecutionResult, Route};
+use crate::route::{Handler, RouteError, RoutePath};
+use crate::route::route::{ExecutionResult, HandlerResult, ResponseFuture, Route, RouteExecution};
+use crate::route::router::default_error_handler;
+use crate::rt::block_on;
+
+type BoxedHandler = Box<dyn Fn(HttpRequest) -> (HttpRequest, HandlerResult) + Send + Sync>;
+type BoxedAsyncHandler = Box<dyn Fn(HttpRequest) -> ResponseFuture + Send + Sync>;
+type ContinueCheck = Box<dyn Fn(&HttpRequest) -> Option<HttpResponse> + Send + Sync>;
+
+enum ActionHandler {
+    Sync(BoxedHandler),
+    Async(BoxedAsyncHandler),
+}
 
 pub struct ActionRoute {
     method: HttpMethod,
     route_path: RoutePath,
-    handler: Box<dyn Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult + Send + Sync>,
+    handler: ActionHandler,
+    max_body_size: Option<usize>,
+    continue_check: Option<ContinueCheck>,
 }
 
 impl ActionRoute {
-    fn new<F: Send + Sync + 'static>(method: HttpMethod, path: &str, handler: F) -> Result<ActionRoute, RouteError>
-        where
-            F: Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult,
-    {
+    fn new(method: HttpMethod, path: &str, handler: ActionHandler) -> Result<ActionRoute, RouteError> {
         let route_path = if let Ok(route_path) = path.parse::<RoutePath>() {
             route_path
         } else {
@@ -23,24 +36,126 @@ impl ActionRoute {
         Ok(ActionRoute {
             method,
             route_path,
-            handler: Box::new(handler),
+            handler,
+            max_body_size: None,
+            continue_check: None,
         })
     }
 
+    fn new_with_builder<F>(method: HttpMethod, path: &str, handler: F) -> Result<ActionRoute, RouteError>
+        where
+            F: Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult + Send + Sync + 'static,
+    {
+        ActionRoute::new(
+            method,
+            path,
+            ActionHandler::Sync(Box::new(move |http_request| {
+                let (http_request, http_response) = handler(http_request, HttpResponseBuilder::new());
+                (http_request, Ok(http_response))
+            })),
+        )
+    }
+
+    fn new_with_extractors<H, Args>(method: HttpMethod, path: &str, handler: H) -> Result<ActionRoute, RouteError>
+        where
+            H: Handler<Args>,
+    {
+        ActionRoute::new(
+            method,
+            path,
+            ActionHandler::Sync(Box::new(move |http_request| {
+                let result = handler.call(&http_request);
+                (http_request, result)
+            })),
+        )
+    }
+
+    fn new_with_future<F, Fut>(method: HttpMethod, path: &str, handler: F) -> Result<ActionRoute, RouteError>
+        where
+            F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = HttpResponse> + Send + 'static,
+    {
+        ActionRoute::new(
+            method,
+            path,
+            ActionHandler::Async(Box::new(move |http_request| Box::pin(handler(http_request)))),
+        )
+    }
+
     // make a new Route instance for get method
-    pub fn new_get<F: Send + Sync + 'static>(path: &str, handler: F) -> Result<ActionRoute, RouteError>
+    pub fn new_get<F>(path: &str, handler: F) -> Result<ActionRoute, RouteError>
         where
-            F: Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult,
+            F: Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult + Send + Sync + 'static,
     {
-        ActionRoute::new(HttpMethod::GET, path, handler)
+        ActionRoute::new_with_builder(HttpMethod::GET, path, handler)
     }
 
     // make a new Route instance for post method
-    pub fn new_post<F: Send + Sync + 'static>(path: &str, handler: F) -> Result<ActionRoute, RouteError>
+    pub fn new_post<F>(path: &str, handler: F) -> Result<ActionRoute, RouteError>
+        where
+            F: Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult + Send + Sync + 'static,
+    {
+        ActionRoute::new_with_builder(HttpMethod::POST, path, handler)
+    }
+
+    // make a new Route instance for get method, whose handler takes extractors as arguments
+    pub fn get<H, Args>(path: &str, handler: H) -> Result<ActionRoute, RouteError>
+        where
+            H: Handler<Args>,
+    {
+        ActionRoute::new_with_extractors(HttpMethod::GET, path, handler)
+    }
+
+    // make a new Route instance for post method, whose handler takes extractors as arguments
+    pub fn post<H, Args>(path: &str, handler: H) -> Result<ActionRoute, RouteError>
+        where
+            H: Handler<Args>,
+    {
+        ActionRoute::new_with_extractors(HttpMethod::POST, path, handler)
+    }
+
+    // make a new Route instance for get method, whose handler waits without blocking a thread.
+    // the session is given back to the middlewares when the handler drops the request.
+    // only the primitives of 'rt' (sleep, TcpStream, channel) wait without a thread, any other
+    // blocking call holds an executor thread. without the reactors, a worker blocks on the handler.
+    pub fn get_async<F, Fut>(path: &str, handler: F) -> Result<ActionRoute, RouteError>
+        where
+            F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = HttpResponse> + Send + 'static,
+    {
+        ActionRoute::new_with_future(HttpMethod::GET, path, handler)
+    }
+
+    // make a new Route instance for post method, whose handler waits without blocking a thread
+    pub fn post_async<F, Fut>(path: &str, handler: F) -> Result<ActionRoute, RouteError>
+        where
+            F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+            Fut: Future<Output = HttpResponse> + Send + 'static,
+    {
+        ActionRoute::new_with_future(HttpMethod::POST, path, handler)
+    }
+
+    fn set_path_params(&self, http_request: &mut HttpRequest) {
+        let pathname = http_request.get_req_path().get_pathname();
+        if let Some(path_params) = self.route_path.captures(pathname) {
+            http_request.set_path_params(path_params);
+        }
+    }
+
+    // limit the request body of this route, e.g. larger for uploads
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = Some(max_body_size);
+        self
+    }
+
+    // veto an upload with 'Expect: 100-continue' by its header, e.g. without a valid token.
+    // it should be quick, the reactor mode calls it on the reactor thread.
+    pub fn check_continue<F>(mut self, check: F) -> Self
         where
-            F: Fn(HttpRequest, HttpResponseBuilder) -> ExecutionResult,
+            F: Fn(&HttpRequest) -> Option<HttpResponse> + Send + Sync + 'static,
     {
-        ActionRoute::new(HttpMethod::POST, path, handler)
+        self.continue_check = Some(Box::new(check));
+        self
     }
 }
 
@@ -50,18 +165,161 @@ impl Route for ActionRoute {
         method == self.method && self.route_path.is_match(pathname)
     }
 
-    // execute request handler
+    // execute request handler, an error is answered by the default error handler
     fn execute(&self, http_request: HttpRequest) -> ExecutionResult {
-        self.handler.as_ref()(http_request, HttpResponseBuilder::new())
+        let (http_request, result) = self.try_execute(http_request);
+        let http_response = match result {
+            Ok(http_response) => http_response,
+            Err(error) => default_error_handler(&http_request, error),
+        };
+
+        (http_request, http_response)
+    }
+
+    // an async handler blocks the calling thread here, e.g. a worker of the thread-per-connection mode
+    fn try_execute(&self, http_request: HttpRequest) -> (HttpRequest, HandlerResult) {
+        match self.start(http_request) {
+            RouteExecution::Ready(http_request, result) => (http_request, result),
+            RouteExecution::Pending(future) => {
+                let (http_request, http_response) = block_on(future);
+                (http_request, Ok(http_response))
+            }
+        }
+    }
+
+    fn start(&self, mut http_request: HttpRequest) -> RouteExecution {
+        self.set_path_params(&mut http_request);
+
+        match &self.handler {
+            ActionHandler::Sync(handler) => {
+                let (http_request, result) = handler(http_request);
+                RouteExecution::Ready(http_request, result)
+            }
+            ActionHandler::Async(handler) => {
+                // the middlewares get a copy of the request, whose session is put back by the
+                // request of the handler when it is dropped
+                let mut kept = http_request.snapshot();
+                let session_slot = Arc::new(Mutex::new(kept.take_session()));
+                http_request.set_session_slot(session_slot.clone());
+                let future = handler(http_request);
+
+                RouteExecution::Pending(Box::pin(async move {
+                    let http_response = future.await;
+                    if let Some(session) = session_slot.lock().ok().and_then(|mut slot| slot.take()) {
+                        kept.set_session(session);
+                    }
+                    (kept, http_response)
+                }))
+            }
+        }
+    }
+
+    fn check_continue(&self, http_request: &HttpRequest) -> Option<HttpResponse> {
+        let check = self.continue_check.as_ref()?;
+        let mut http_request = http_request.snapshot();
+        self.set_path_params(&mut http_request);
+
+        check(&http_request)
+    }
+
+    fn get_max_body_size(&self) -> Option<usize> {
+        self.max_body_size
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::http::HttpStatus;
+    use serde::Deserialize;
+
+    use crate::extract::{Json, Path, Query};
+    use crate::test_support::make_request;
+    use crate::http::{ContentType, HttpResponse, HttpStatus};
 
     use super::*;
 
+    #[derive(Deserialize)]
+    struct UserPath {
+        id: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct Paging {
+        page: u32,
+    }
+
+    #[derive(Deserialize)]
+    struct NewUser {
+        name: String,
+    }
+
+    fn execute(route: &ActionRoute, http_request: HttpRequest) -> String {
+        let (_, mut http_response) = route.execute(http_request);
+        let mut written = vec![];
+        http_response.respond(&mut written);
+
+        String::from_utf8(written).unwrap()
+    }
+
+    fn show_user(
+        Path(user): Path<UserPath>,
+        Query(paging): Query<Paging>,
+        builder: HttpResponseBuilder,
+    ) -> HttpResponse {
+        let body = format!("user {} page {}", user.id, paging.page);
+        builder.body(ContentType::TEXT_PLAIN, body.into_bytes()).build().unwrap()
+    }
+
+    #[test]
+    fn test_extractor_handler() {
+        let route = ActionRoute::get("/users/:id", show_user).unwrap();
+        assert!(route.is_path_matching(HttpMethod::GET, "/users/3"));
+
+        let response = execute(&route, make_request("GET /users/3?page=2 HTTP/1.1\r\n", None));
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("user 3 page 2"));
+
+        let response = execute(&route, make_request("GET /users/three?page=2 HTTP/1.1\r\n", None));
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_extractor_closure() {
+        let route = ActionRoute::post("/users", |Json(user): Json<NewUser>| {
+            HttpResponseBuilder::new()
+                .set_status(HttpStatus::CREATED)
+                .body(ContentType::TEXT_PLAIN, user.name.into_bytes())
+                .build()
+                .unwrap()
+        })
+            .unwrap();
+
+        let header = "POST /users HTTP/1.1\r\nContent-Type: application/json\r\n";
+        let response = execute(&route, make_request(header, Some(br#"{"name": "foo"}"#)));
+        assert!(response.starts_with("HTTP/1.1 201 Created"));
+        assert!(response.ends_with("foo"));
+
+        let header = "POST /users HTTP/1.1\r\nContent-Type: text/plain\r\n";
+        let response = execute(&route, make_request(header, Some(b"foo")));
+        assert!(response.starts_with("HTTP/1.1 415 Unsupported Media Type"));
+    }
+
+    #[test]
+    fn test_async_handler() {
+        let route = ActionRoute::get_async("/users/:id", |req: HttpRequest| async move {
+            crate::rt::sleep(std::time::Duration::from_millis(10)).await;
+            let body = format!("user {}", req.get_path_param("id").unwrap_or_default());
+            HttpResponseBuilder::new()
+                .body(ContentType::TEXT_PLAIN, body.into_bytes())
+                .build()
+                .unwrap()
+        })
+            .unwrap();
+
+        let response = execute(&route, make_request("GET /users/3 HTTP/1.1\r\n", None));
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.ends_with("user 3"));
+    }
+
     #[test]
     fn test_is_path_matching() {
         let route = ActionRoute::new_get("/test", |req, builder| {
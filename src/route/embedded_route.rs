@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::embed::EmbeddedFile;
+use crate::http::{HttpRequest, HttpResponse, HttpStatus};
+use crate::http::method::HttpMethod;
+use crate::route::route::Route;
+use crate::route::static_route::{get_content_type, respond_content};
+
+// route for static resources bundled into the binary, it behaves like a cached 'StaticRoute'.
+//
+//     static STATICS: &[EmbeddedFile] = lite_ws::include_assets!("statics.rs");
+//     server.mount_route(EmbeddedRoute::new("/st", STATICS));
+pub struct EmbeddedRoute {
+    // url path -> file, e.g. '/st/tayo.png'
+    files: HashMap<String, &'static EmbeddedFile>,
+}
+
+impl EmbeddedRoute {
+    pub fn new(path_root: &str, files: &'static [EmbeddedFile]) -> Self {
+        let path_root = path_root.trim_end_matches('/');
+        let files = files
+            .iter()
+            .map(|file| (format!("{}{}", path_root, file.path), file))
+            .collect();
+
+        EmbeddedRoute { files }
+    }
+}
+
+impl Route for EmbeddedRoute {
+    fn is_path_matching(&self, method: HttpMethod, pathname: &str) -> bool {
+        method == HttpMethod::GET && self.files.contains_key(pathname)
+    }
+
+    fn execute(&self, http_request: HttpRequest) -> (HttpRequest, HttpResponse) {
+        let file = if let Some(file) = self.files.get(http_request.get_req_path().get_pathname()) {
+            *file
+        } else {
+            return (
+                http_request,
+                HttpResponse::new_with(HttpStatus::NOT_FOUND),
+            );
+        };
+
+        let http_response = respond_content(
+            &http_request,
+            file.content,
+            file.gzipped,
+            file.etag,
+            get_content_type(Path::new(file.path)),
+        );
+
+        (
+            http_request,
+            http_response.unwrap_or(HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    // the files which 'embed_dir' would bundle from the statics of the demo server
+    static STATICS: &[EmbeddedFile] = &[
+        EmbeddedFile {
+            path: "/nested/n_tayo.png",
+            content: include_bytes!("../../statics/nested/n_tayo.png"),
+            gzipped: None,
+            etag: "\"1\"",
+        },
+        EmbeddedFile {
+            path: "/tayo.png",
+            content: include_bytes!("../../statics/tayo.png"),
+            gzipped: None,
+            etag: "\"2\"",
+        },
+    ];
+
+    static TEXTS: &[EmbeddedFile] = &[EmbeddedFile {
+        path: "/app.js",
+        content: b"console.log('hello');",
+        gzipped: Some(b"compressed"),
+        etag: "\"1\"",
+    }];
+
+    fn respond(route: &EmbeddedRoute, raw: &str) -> Vec<u8> {
+        let (_, mut http_response) = route.execute(make_request(raw, None));
+        let mut written = vec![];
+        http_response.respond(&mut written);
+
+        written
+    }
+
+    #[test]
+    fn test_embedded_statics() {
+        let route = EmbeddedRoute::new("/st/", STATICS);
+        assert!(route.is_path_matching(HttpMethod::GET, "/st/tayo.png"));
+        assert!(route.is_path_matching(HttpMethod::GET, "/st/nested/n_tayo.png"));
+        assert!(!route.is_path_matching(HttpMethod::GET, "/st/unknown.png"));
+        assert!(!route.is_path_matching(HttpMethod::POST, "/st/tayo.png"));
+
+        let response = respond(&route, "GET /st/tayo.png HTTP/1.1\r\n");
+        let content = std::fs::read("statics/tayo.png").unwrap();
+        assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+        assert!(response.ends_with(&content));
+        assert!(String::from_utf8_lossy(&response).contains("Content-Type: image/png"));
+    }
+
+    #[test]
+    fn test_precompressed_variant() {
+        let route = EmbeddedRoute::new("/", TEXTS);
+
+        let response = respond(&route, "GET /app.js HTTP/1.1\r\nAccept-Encoding: gzip\r\n");
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.contains("Content-Type: text/javascript"));
+        assert!(response.contains("Content-Encoding: gzip"));
+        assert!(response.ends_with("compressed"));
+
+        let response = String::from_utf8(respond(&route, "GET /app.js HTTP/1.1\r\n")).unwrap();
+        assert!(response.ends_with("console.log('hello');"));
+
+        let response = respond(&route, "GET /app.js HTTP/1.1\r\nIf-None-Match: \"1\"\r\n");
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::NOT_MODIFIED)));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::extract::FromRequest;
+use crate::http::{HttpRequest, IntoResponse};
+use crate::route::route::HandlerResult;
+
+// a function whose arguments are extractors (e.g. 'fn(Path<P>, Json<T>) -> Result<String, E>').
+// 'Args' is the tuple of the argument types, it only exists to tell the implementations apart.
+pub trait Handler<Args>: Send + Sync + 'static {
+    fn call(&self, http_request: &HttpRequest) -> HandlerResult;
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> Handler<($($arg,)*)> for F
+            where
+                F: Fn($($arg),*) -> R + Send + Sync + 'static,
+                R: IntoResponse,
+                $($arg: FromRequest,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&self, http_request: &HttpRequest) -> HandlerResult {
+                $(
+                    let $arg = match $arg::from_request(http_request) {
+                        Ok(extracted) => extracted,
+                        Err(error) => return Ok(error.to_response()),
+                    };
+                )*
+
+                self($($arg),*).into_response()
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(A);
+impl_handler!(A, B);
+impl_handler!(A, B, C);
+impl_handler!(A, B, C, D);
+impl_handler!(A, B, C, D, E);
+impl_handler!(A, B, C, D, E, G);
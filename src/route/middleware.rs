@@ -0,0 +1,12 @@
+use crate::http::{HttpRequest, HttpResponse};
+
+// hook that runs around every route execution.
+// the Router calls 'before' in mount order and 'after' in reverse order.
+pub trait Middleware: Send + Sync {
+    // return a response to skip the route (e.g. to reject the request)
+    fn before(&self, _http_request: &mut HttpRequest) -> Option<HttpResponse> {
+        None
+    }
+
+    fn after(&self, _http_request: &mut HttpRequest, _http_response: &mut HttpResponse) {}
+}
@@ -1,15 +1,23 @@
 pub use action_route::ActionRoute;
+pub use embedded_route::EmbeddedRoute;
+pub use handler::Handler;
+pub use middleware::Middleware;
 pub use route_error::RouteError;
-pub use route_path::RoutePath;
-pub use router::Router;
+pub use route_path::{PathParams, RoutePath};
+pub use router::{ErrorHandler, Router};
 pub use router_builder::RouterBuilder;
 pub use static_route::StaticRoute;
 
+#[allow(clippy::module_inception)]
 pub mod route;
 
 mod action_route;
-mod router;
+mod embedded_route;
+mod handler;
+mod middleware;
+pub(crate) mod router;
 mod route_path;
 mod router_builder;
 mod route_error;
+mod static_cache;
 mod static_route;
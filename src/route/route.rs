@@ -1,10 +1,46 @@
-use crate::http::{HttpRequest, HttpResponse};
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::http::{HandlerError, HttpRequest, HttpResponse};
 use crate::http::method::HttpMethod;
 
 pub type ExecutionResult = (HttpRequest, HttpResponse);
+pub type HandlerResult = Result<HttpResponse, HandlerError>;
+pub type ResponseFuture = Pin<Box<dyn Future<Output = HttpResponse> + Send>>;
+pub type ExecutionFuture = Pin<Box<dyn Future<Output = ExecutionResult> + Send>>;
+
+// how a route has started to handle a request, it is matched right after it is made
+#[allow(clippy::large_enum_variant)]
+pub enum RouteExecution {
+    Ready(HttpRequest, HandlerResult),
+    // the handler owns the request until it gives it back with the response
+    Pending(ExecutionFuture),
+}
 
 pub trait Route: Send + Sync {
     fn is_path_matching(&self, method: HttpMethod, pathname: &str) -> bool;
     fn execute(&self, http_request: HttpRequest) -> ExecutionResult;
-}
 
+    // execute the handler but keep its error, the Router maps the error with the error handler
+    fn try_execute(&self, http_request: HttpRequest) -> (HttpRequest, HandlerResult) {
+        let (http_request, http_response) = self.execute(http_request);
+        (http_request, Ok(http_response))
+    }
+
+    // an async route returns its future instead of blocking the calling thread
+    fn start(&self, http_request: HttpRequest) -> RouteExecution {
+        let (http_request, result) = self.try_execute(http_request);
+        RouteExecution::Ready(http_request, result)
+    }
+
+    // decides on 'Expect: 100-continue' before the body is read, the request has only the header.
+    // a response rejects the request, e.g. '401 Unauthorized', and the body is never sent.
+    fn check_continue(&self, _http_request: &HttpRequest) -> Option<HttpResponse> {
+        None
+    }
+
+    // overrides 'ServerConfig::max_body_size' for the requests of this route
+    fn get_max_body_size(&self) -> Option<usize> {
+        None
+    }
+}
@@ -1,17 +1,57 @@
+use std::collections::HashMap;
 use std::convert::TryInto;
 use std::str::FromStr;
 
 use crate::route::RouteError;
 use crate::url::url_path::UrlPath;
+use crate::util::percent::percent_decode;
+
+pub type PathParams = HashMap<String, String>;
+
+#[derive(Debug)]
+enum Segment {
+    Literal(String),
+    // ':name' segment matches any single segment
+    Param(String),
+}
 
 #[derive(Debug)]
 pub struct RoutePath {
     path: String,
+    segments: Vec<Segment>,
 }
 
 impl RoutePath {
     pub fn is_match(&self, pathname: &str) -> bool {
-        *pathname == self.path
+        if self.segments.iter().all(|segment| matches!(segment, Segment::Literal(_))) {
+            return *pathname == self.path;
+        }
+
+        self.captures(pathname).is_some()
+    }
+
+    // extract path parameters, returns None if the pathname does not match
+    pub fn captures(&self, pathname: &str) -> Option<PathParams> {
+        let mut params = PathParams::new();
+        let mut pieces = pathname.split('/');
+
+        for segment in self.segments.iter() {
+            let piece = pieces.next()?;
+
+            match segment {
+                Segment::Literal(literal) if literal == piece => {}
+                Segment::Param(name) if !piece.is_empty() => {
+                    params.insert(name.clone(), percent_decode(piece)?);
+                }
+                _ => return None,
+            }
+        }
+
+        if pieces.next().is_some() {
+            return None;
+        }
+
+        Some(params)
     }
 }
 
@@ -25,8 +65,43 @@ impl FromStr for RoutePath {
             return Err(RouteError::RoutePathParseError);
         };
 
-        Ok(RoutePath {
-            path: url_path.get_pathname().to_string(),
-        })
+        let path = url_path.get_pathname().to_string();
+        let segments = path
+            .split('/')
+            .map(|piece| match piece.strip_prefix(':') {
+                Some(name) => Segment::Param(name.to_string()),
+                None => Segment::Literal(piece.to_string()),
+            })
+            .collect();
+
+        Ok(RoutePath { path, segments })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_literal_path() {
+        let route_path: RoutePath = "/hello/world".parse().unwrap();
+
+        assert!(route_path.is_match("/hello/world"));
+        assert!(!route_path.is_match("/hello"));
+        assert_eq!(route_path.captures("/hello/world"), Some(PathParams::new()));
+    }
+
+    #[test]
+    fn test_path_params() {
+        let route_path: RoutePath = "/users/:id/posts/:post_id".parse().unwrap();
+
+        let params = route_path.captures("/users/7/posts/hello%20world").unwrap();
+        assert_eq!(params.get("id"), Some(&"7".to_string()));
+        assert_eq!(params.get("post_id"), Some(&"hello world".to_string()));
+
+        assert!(!route_path.is_match("/users/7/posts"));
+        assert!(!route_path.is_match("/users//posts/1"));
+        assert!(!route_path.is_match("/users/7/posts/1/extra"));
+        assert!(!route_path.is_match("/members/7/posts/1"));
     }
 }
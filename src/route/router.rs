@@ -1,14 +1,57 @@
-use crate::http::{HttpRequest, HttpResponse, HttpResponseBuilder, HttpStatus};
-use crate::route::route::Route;
+use std::sync::Arc;
+
+use crate::http::{HandlerError, HttpRequest, HttpResponse, HttpStatus};
+use crate::http::method::HttpMethod;
+use crate::route::Middleware;
+use crate::route::route::{HandlerResult, ResponseFuture, Route, RouteExecution};
+use crate::server::AppState;
+
+pub type ErrorHandler = Box<dyn Fn(&HttpRequest, HandlerError) -> HttpResponse + Send + Sync>;
 
 // find a route and execute route's handler.
 pub struct Router {
     routes: Vec<Box<dyn Route>>,
+    middlewares: Vec<Box<dyn Middleware>>,
+    app_state: Arc<AppState>,
+    error_handler: ErrorHandler,
 }
 
 impl Router {
-    pub fn new(routes: Vec<Box<dyn Route>>) -> Self {
-        Router { routes }
+    pub fn new(routes: Vec<Box<dyn Route>>, middlewares: Vec<Box<dyn Middleware>>) -> Self {
+        Router {
+            routes,
+            middlewares,
+            app_state: Arc::new(AppState::new()),
+            error_handler: Box::new(default_error_handler),
+        }
+    }
+
+    pub fn with_app_state(mut self, app_state: AppState) -> Self {
+        self.app_state = Arc::new(app_state);
+        self
+    }
+
+    // to turn errors returned by handlers into responses
+    pub fn with_error_handler(mut self, error_handler: ErrorHandler) -> Self {
+        self.error_handler = error_handler;
+        self
+    }
+
+    // the body limit of the route which will handle the request, the body is not read yet
+    pub fn find_max_body_size(&self, method: HttpMethod, pathname: &str) -> Option<usize> {
+        self.routes
+            .iter()
+            .find(|route| route.is_path_matching(method, pathname))
+            .and_then(|route| route.get_max_body_size())
+    }
+
+    // decide on 'Expect: 100-continue' by the header, none lets the client send the body.
+    // the middlewares see the request only once, when its body has arrived.
+    pub(crate) fn check_continue(&self, http_request: &HttpRequest) -> Option<HttpResponse> {
+        match self.find_route(http_request) {
+            Some(route) => route.check_continue(http_request),
+            None => Some(HttpResponse::new_with(HttpStatus::NOT_FOUND)),
+        }
     }
 
     // TODO list:
@@ -17,28 +60,193 @@ impl Router {
     //  - post route
     // - [wip] static route
     // - [wip] error response
-    // - support path parameter
-    pub fn execute_route(&self, http_request: HttpRequest) -> (HttpRequest, HttpResponse) {
-        let req_path = http_request.get_req_path();
+    pub fn execute_route(&self, mut http_request: HttpRequest) -> (HttpRequest, HttpResponse) {
+        if let Some(http_response) = self.run_before_middlewares(&mut http_request) {
+            return (http_request, http_response);
+        }
+
+        let (mut http_request, mut http_response) = self.dispatch(http_request);
+        self.run_after_middlewares(&mut http_request, &mut http_response);
+
+        (http_request, http_response)
+    }
 
-        for route in self.routes.iter() {
-            if route.is_path_matching(http_request.get_method(), req_path.get_pathname()) {
-                return route.execute(http_request);
+    // like 'execute_route', but an async route is given back as a future instead of blocking.
+    // the middlewares run after the future has completed.
+    pub(crate) fn start_route(self: &Arc<Self>, mut http_request: HttpRequest) -> RouterExecution {
+        if let Some(http_response) = self.run_before_middlewares(&mut http_request) {
+            return RouterExecution::Ready(http_response);
+        }
+
+        let route = if let Some(route) = self.find_route(&http_request) {
+            route
+        } else {
+            let mut http_response = HttpResponse::new_with(HttpStatus::NOT_FOUND);
+            self.run_after_middlewares(&mut http_request, &mut http_response);
+            return RouterExecution::Ready(http_response);
+        };
+
+        match route.start(http_request) {
+            RouteExecution::Ready(mut http_request, result) => {
+                let mut http_response = self.handle_result(&http_request, result);
+                self.run_after_middlewares(&mut http_request, &mut http_response);
+                RouterExecution::Ready(http_response)
+            }
+            RouteExecution::Pending(future) => {
+                let router = Arc::clone(self);
+                RouterExecution::Pending(Box::pin(async move {
+                    let (mut http_request, mut http_response) = future.await;
+                    router.run_after_middlewares(&mut http_request, &mut http_response);
+                    http_response
+                }))
+            }
+        }
+    }
+
+    // returns a response when a middleware has answered without the route
+    fn run_before_middlewares(&self, http_request: &mut HttpRequest) -> Option<HttpResponse> {
+        http_request.set_app_state(self.app_state.clone());
+
+        for (index, middleware) in self.middlewares.iter().enumerate() {
+            if let Some(mut http_response) = middleware.before(http_request) {
+                // only the middlewares which have seen the request are notified
+                for middleware in self.middlewares[..=index].iter().rev() {
+                    middleware.after(http_request, &mut http_response);
+                }
+                return Some(http_response);
             }
         }
 
-        (
-            http_request,
-            HttpResponseBuilder::new()
-                .set_status(HttpStatus::NOT_FOUND)
-                .build()
-                .unwrap(),
-        )
+        None
+    }
+
+    fn run_after_middlewares(&self, http_request: &mut HttpRequest, http_response: &mut HttpResponse) {
+        for middleware in self.middlewares.iter().rev() {
+            middleware.after(http_request, http_response);
+        }
+    }
+
+    fn find_route(&self, http_request: &HttpRequest) -> Option<&dyn Route> {
+        let pathname = http_request.get_req_path().get_pathname();
+
+        self.routes
+            .iter()
+            .find(|route| route.is_path_matching(http_request.get_method(), pathname))
+            .map(|route| route.as_ref())
+    }
+
+    fn handle_result(&self, http_request: &HttpRequest, result: HandlerResult) -> HttpResponse {
+        match result {
+            Ok(http_response) => http_response,
+            Err(error) => (self.error_handler)(http_request, error),
+        }
+    }
+
+    fn dispatch(&self, http_request: HttpRequest) -> (HttpRequest, HttpResponse) {
+        if let Some(route) = self.find_route(&http_request) {
+            let (http_request, result) = route.try_execute(http_request);
+            let http_response = self.handle_result(&http_request, result);
+            return (http_request, http_response);
+        }
+
+        (http_request, HttpResponse::new_with(HttpStatus::NOT_FOUND))
     }
 }
 
+// the response of 'Router::start_route'
+pub(crate) enum RouterExecution {
+    Ready(HttpResponse),
+    Pending(ResponseFuture),
+}
+
 impl Default for Router {
     fn default() -> Self {
-        Router { routes: vec![] }
+        Router::new(vec![], vec![])
+    }
+}
+
+// log the error, and hide the detail from the client
+pub(crate) fn default_error_handler(http_request: &HttpRequest, error: HandlerError) -> HttpResponse {
+    eprintln!(
+        "[error] handler of {} failed: {}",
+        http_request.get_req_path().get_pathname(),
+        error
+    );
+
+    HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::test_support::make_request;
+    use crate::http::ContentType;
+    use crate::route::{ActionRoute, RouterBuilder};
+
+    use super::*;
+
+    struct HitCounter(AtomicUsize);
+
+    #[derive(Debug)]
+    struct NotAllowed;
+
+    impl fmt::Display for NotAllowed {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("not allowed")
+        }
+    }
+
+    impl std::error::Error for NotAllowed {}
+
+    #[test]
+    fn test_app_state() {
+        let route = ActionRoute::new_get("/hit", |req, builder| {
+            let counter = req.get_state::<HitCounter>().unwrap();
+            let hits = counter.0.fetch_add(1, Ordering::SeqCst) + 1;
+            let res = builder.body(ContentType::TEXT_PLAIN, hits.to_string().into_bytes());
+
+            (req, res.build().unwrap())
+        })
+            .unwrap();
+
+        let router = RouterBuilder::new()
+            .append_route(Box::new(route))
+            .insert_state(HitCounter(AtomicUsize::new(0)))
+            .build();
+
+        router.execute_route(make_request("GET /hit HTTP/1.1\r\n", None));
+        let (req, _) = router.execute_route(make_request("GET /hit HTTP/1.1\r\n", None));
+
+        let counter = req.get_state::<HitCounter>().unwrap();
+        assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+        assert!(req.get_state::<String>().is_none());
+    }
+
+    #[test]
+    fn test_error_handler() {
+        fn denied() -> Result<String, NotAllowed> {
+            Err(NotAllowed)
+        }
+
+        let router = RouterBuilder::new()
+            .append_route(Box::new(ActionRoute::get("/denied", denied).unwrap()))
+            .build();
+        let (_, res) = router.execute_route(make_request("GET /denied HTTP/1.1\r\n", None));
+        assert_eq!(res.get_status(), &HttpStatus::INTERNAL_SERVER_ERROR);
+
+        let router = RouterBuilder::new()
+            .append_route(Box::new(ActionRoute::get("/denied", denied).unwrap()))
+            .set_error_handler(Box::new(|_, error| {
+                if error.downcast_ref::<NotAllowed>().is_some() {
+                    HttpResponse::new_with(HttpStatus::UNAUTHORIZED)
+                } else {
+                    HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR)
+                }
+            }))
+            .build();
+        let (_, res) = router.execute_route(make_request("GET /denied HTTP/1.1\r\n", None));
+        assert_eq!(res.get_status(), &HttpStatus::UNAUTHORIZED);
     }
 }
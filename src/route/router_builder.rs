@@ -1,18 +1,21 @@
-use crate::route::Router;
+use crate::route::{ErrorHandler, Middleware, Router};
 use crate::route::route::Route;
+use crate::server::AppState;
 
 // role of this struct is to gather Routes and to create Router with Routes.
 // you could think this struct is useless.
 // but i thought i need some temporary place to store routes and to take ownership of that.
+#[derive(Default)]
 pub struct RouterBuilder {
     routes: Option<Vec<Box<dyn Route>>>,
+    middlewares: Vec<Box<dyn Middleware>>,
+    app_state: AppState,
+    error_handler: Option<ErrorHandler>,
 }
 
 impl RouterBuilder {
     pub fn new() -> Self {
-        RouterBuilder {
-            routes: None,
-        }
+        RouterBuilder::default()
     }
 
     // append new Route
@@ -27,11 +30,38 @@ impl RouterBuilder {
         self
     }
 
+    // append new Middleware, it wraps every Route
+    pub fn append_middleware(&mut self, middleware: Box<dyn Middleware>) -> &mut Self {
+        self.middlewares.push(middleware);
+        self
+    }
+
+    // register a shared state which handlers can find by its type
+    pub fn insert_state<T: Send + Sync + 'static>(&mut self, state: T) -> &mut Self {
+        self.app_state.insert(state);
+        self
+    }
+
+    // replace the default error handler, which answers '500 Internal Server Error'
+    pub fn set_error_handler(&mut self, error_handler: ErrorHandler) -> &mut Self {
+        self.error_handler = Some(error_handler);
+        self
+    }
+
     // move Routes to the Router
     pub fn build(&mut self) -> Router {
-        match self.routes.take() {
-            Some(routes) => Router::new(routes),
-            None => Router::default(),
+        let middlewares = std::mem::take(&mut self.middlewares);
+        let app_state = std::mem::take(&mut self.app_state);
+
+        let router = match self.routes.take() {
+            Some(routes) => Router::new(routes, middlewares),
+            None => Router::new(vec![], middlewares),
+        }
+            .with_app_state(app_state);
+
+        match self.error_handler.take() {
+            Some(error_handler) => router.with_error_handler(error_handler),
+            None => router,
         }
     }
 }
@@ -0,0 +1,245 @@
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+
+use crate::http::ContentType;
+
+// a smaller file is not worth compressing
+const MIN_COMPRESS_SIZE: usize = 256;
+
+// the content of a static file and what is derived from it, made once when the file is cached
+pub(crate) struct CachedFile {
+    content: Vec<u8>,
+    gzipped: Option<Vec<u8>>,
+    etag: String,
+    content_type: ContentType,
+    modified: Option<SystemTime>,
+}
+
+impl CachedFile {
+    pub fn new(content: Vec<u8>, content_type: ContentType, metadata: &Metadata) -> Self {
+        let gzipped = if content_type.is_compressible() && content.len() >= MIN_COMPRESS_SIZE {
+            gzip(&content).filter(|gzipped| gzipped.len() < content.len())
+        } else {
+            None
+        };
+
+        CachedFile {
+            content,
+            gzipped,
+            etag: make_etag(metadata),
+            content_type,
+            modified: metadata.modified().ok(),
+        }
+    }
+
+    pub fn get_content(&self) -> &[u8] {
+        &self.content
+    }
+
+    pub fn get_gzipped(&self) -> Option<&[u8]> {
+        self.gzipped.as_deref()
+    }
+
+    pub fn get_etag(&self) -> &str {
+        &self.etag
+    }
+
+    pub fn get_content_type(&self) -> &ContentType {
+        &self.content_type
+    }
+
+    // the cached bytes, which are counted against the budget
+    fn get_size(&self) -> usize {
+        self.content.len() + self.gzipped.as_ref().map_or(0, Vec::len)
+    }
+
+    // the file has been changed since it was cached
+    fn is_stale(&self, metadata: &Metadata) -> bool {
+        self.content.len() as u64 != metadata.len() || self.modified != metadata.modified().ok()
+    }
+}
+
+struct CacheEntry {
+    file: Arc<CachedFile>,
+    last_used: u64,
+}
+
+// keeps recently used static files in the memory within a byte budget.
+// an entry is checked against the modified time of the file, so a changed file is read again.
+pub(crate) struct StaticCache {
+    capacity: usize,
+    size: usize,
+    entries: HashMap<PathBuf, CacheEntry>,
+    // increases on each access, the entry with the smallest one is evicted first
+    clock: u64,
+}
+
+impl StaticCache {
+    pub fn new(capacity: usize) -> Self {
+        StaticCache {
+            capacity,
+            size: 0,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    // a single file may take at most a quarter of the budget, larger files are sent with sendfile(2)
+    pub fn can_cache(&self, file_len: u64) -> bool {
+        file_len <= (self.capacity / 4) as u64
+    }
+
+    pub fn get(&mut self, path: &Path, metadata: &Metadata) -> Option<Arc<CachedFile>> {
+        let is_stale = self.entries.get(path)?.file.is_stale(metadata);
+        if is_stale {
+            self.remove(path);
+            return None;
+        }
+
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        entry.last_used = self.clock;
+
+        Some(Arc::clone(&entry.file))
+    }
+
+    pub fn insert(&mut self, path: PathBuf, file: Arc<CachedFile>) {
+        let file_size = file.get_size();
+        if file_size > self.capacity {
+            return;
+        }
+
+        self.remove(&path);
+        while self.size + file_size > self.capacity {
+            if !self.evict_least_recently_used() {
+                break;
+            }
+        }
+
+        self.clock += 1;
+        self.size += file_size;
+        self.entries.insert(
+            path,
+            CacheEntry {
+                file,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(entry) = self.entries.remove(path) {
+            self.size -= entry.file.get_size();
+        }
+    }
+
+    fn evict_least_recently_used(&mut self) -> bool {
+        let path = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(path, _)| path.clone());
+
+        if let Some(path) = path {
+            self.remove(&path);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// made from the size and the modified time, so it is known without reading the file
+pub(crate) fn make_etag(metadata: &Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+
+    format!("\"{:x}-{:x}\"", metadata.len(), modified)
+}
+
+fn gzip(content: &[u8]) -> Option<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(content).ok()?;
+
+    encoder.finish().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use crate::util::random::random_hex;
+
+    use super::*;
+
+    fn temp_file(content: &[u8]) -> (PathBuf, Metadata) {
+        let path = env::temp_dir().join(format!("lite-ws-static-cache-{}", random_hex(8)));
+        fs::write(&path, content).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+
+        (path, metadata)
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = StaticCache::new(100);
+        let files: Vec<(PathBuf, Metadata)> = (0..3).map(|_| temp_file(&[0_u8; 40])).collect();
+
+        for (path, metadata) in files[..2].iter() {
+            let file = CachedFile::new(vec![0_u8; 40], ContentType::from_extension("png"), metadata);
+            cache.insert(path.clone(), Arc::new(file));
+        }
+        // the first file is used recently, so the second one is evicted
+        assert!(cache.get(&files[0].0, &files[0].1).is_some());
+
+        let file = CachedFile::new(vec![0_u8; 40], ContentType::from_extension("png"), &files[2].1);
+        cache.insert(files[2].0.clone(), Arc::new(file));
+        assert!(cache.get(&files[0].0, &files[0].1).is_some());
+        assert!(cache.get(&files[1].0, &files[1].1).is_none());
+        assert!(cache.get(&files[2].0, &files[2].1).is_some());
+        assert_eq!(cache.size, 80);
+
+        for (path, _) in files {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stale_entry() {
+        let (path, metadata) = temp_file(b"old");
+        let mut cache = StaticCache::new(100);
+        let file = CachedFile::new(b"old".to_vec(), ContentType::TEXT_PLAIN, &metadata);
+        cache.insert(path.clone(), Arc::new(file));
+
+        fs::write(&path, b"changed").unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        assert!(cache.get(&path, &metadata).is_none());
+        assert_eq!(cache.size, 0);
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_gzip_variant() {
+        let content = "hello world ".repeat(100).into_bytes();
+        let (path, metadata) = temp_file(&content);
+
+        let file = CachedFile::new(content.clone(), ContentType::from_extension("css"), &metadata);
+        assert!(file.get_gzipped().unwrap().len() < content.len());
+        let file = CachedFile::new(content, ContentType::from_extension("png"), &metadata);
+        assert!(file.get_gzipped().is_none());
+
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,8 +1,15 @@
-use std::fs::File;
+use std::fs::{self, File, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
-use crate::http::{ContentType, HttpRequest, HttpResponse, HttpResponseBuilder, HttpStatus};
+use crate::http::{
+    ContentType, HttpError, HttpRequest, HttpResponse, HttpResponseBuilder, HttpStatus,
+    ReadHeaderAs,
+};
 use crate::http::method::HttpMethod;
 use crate::route::route::Route;
+use crate::route::static_cache::{CachedFile, make_etag, StaticCache};
+use crate::util::percent::percent_decode;
 
 // route for static resources.
 pub struct StaticRoute {
@@ -11,6 +18,11 @@ pub struct StaticRoute {
     path_root: String,
     // url path root to access static resources
     extension_rules: Option<Vec<String>>,   // allowed extensions for static resources
+    cache: Option<Mutex<StaticCache>>,
+    // a document in 'static_root' which is served for the unknown paths, e.g. 'index.html'
+    fallback: Option<String>,
+    // url paths which never fall back, e.g. '/app/api'
+    excluded_prefixes: Vec<String>,
 }
 
 impl StaticRoute {
@@ -29,8 +41,120 @@ impl StaticRoute {
             static_root,
             path_root,
             extension_rules,
+            cache: None,
+            fallback: None,
+            excluded_prefixes: vec![],
         }
     }
+
+    // serve the fallback document for a path which has no file, so the client-side routing of
+    // a single page application works. paths with an extension are still answered with 404.
+    pub fn spa_fallback(mut self, fallback: &str) -> Self {
+        self.fallback = Some(fallback.trim_start_matches('/').to_string());
+        self
+    }
+
+    // a path under the prefix is not answered with the fallback, e.g. an api path
+    pub fn exclude_prefix(mut self, prefix: &str) -> Self {
+        self.excluded_prefixes.push(prefix.to_string());
+        self
+    }
+
+    // whether the fallback document is served for the path when it has no file
+    fn can_fall_back(&self, pathname: &str) -> bool {
+        if self.fallback.is_none() {
+            return false;
+        }
+
+        if self
+            .excluded_prefixes
+            .iter()
+            .any(|prefix| strip_path_prefix(pathname, prefix).is_some())
+        {
+            return false;
+        }
+
+        // e.g. '/app/main.js' is a missing file, not a client-side route
+        let last_segment = pathname.rsplit('/').next().unwrap_or("");
+        !last_segment.contains('.')
+    }
+
+    fn respond_static_file(
+        &self,
+        http_request: &HttpRequest,
+        file_path: &Path,
+        metadata: &Metadata,
+    ) -> Result<HttpResponse, HttpError> {
+        if let Some(cached_file) = self.get_cached_file(file_path, metadata) {
+            respond_cached_file(http_request, &cached_file)
+        } else {
+            respond_file(http_request, file_path, metadata)
+        }
+    }
+
+    // the fallback document must not be cached as the response of the path
+    fn respond_fallback(&self, http_request: &HttpRequest) -> Result<HttpResponse, HttpError> {
+        let fallback = self.fallback.as_deref().unwrap_or("");
+        let file_path = PathBuf::from(&self.static_root[1..]).join(fallback);
+        let (file_path, metadata) = if let Some(resolved) = self.resolve_file(&file_path) {
+            resolved
+        } else {
+            return Ok(HttpResponse::new_with(HttpStatus::NOT_FOUND));
+        };
+
+        let mut http_response = self.respond_static_file(http_request, &file_path, &metadata)?;
+        http_response.append_header("Cache-Control", "no-cache");
+
+        Ok(http_response)
+    }
+
+    // the file with the symbolic links resolved, only if it is in 'static_root'
+    fn resolve_file(&self, file_path: &Path) -> Option<(PathBuf, Metadata)> {
+        let static_root = fs::canonicalize(&self.static_root[1..]).ok()?;
+        let file_path = fs::canonicalize(file_path).ok()?;
+        if !file_path.starts_with(&static_root) {
+            return None;
+        }
+
+        let metadata = fs::metadata(&file_path).ok()?;
+        if metadata.is_file() {
+            Some((file_path, metadata))
+        } else {
+            None
+        }
+    }
+
+    // keep recently used files in the memory, up to 'capacity' bytes
+    pub fn cache(mut self, capacity: usize) -> Self {
+        self.cache = Some(Mutex::new(StaticCache::new(capacity)));
+        self
+    }
+
+    // a cached file, it is read and cached if it is not cached yet
+    fn get_cached_file(&self, path: &Path, metadata: &Metadata) -> Option<Arc<CachedFile>> {
+        let cache = self.cache.as_ref()?;
+        {
+            let mut cache = cache.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            if !cache.can_cache(metadata.len()) {
+                return None;
+            }
+            if let Some(cached_file) = cache.get(path, metadata) {
+                return Some(cached_file);
+            }
+        }
+
+        // the file is read without holding the lock
+        let content = fs::read(path).ok()?;
+        // the metadata is taken before reading, so a file changed while reading looks stale
+        // and is read again on the next request
+        let cached_file = Arc::new(CachedFile::new(content, get_content_type(path), metadata));
+        cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(path.to_path_buf(), Arc::clone(&cached_file));
+
+        Some(cached_file)
+    }
 }
 
 impl Route for StaticRoute {
@@ -39,10 +163,14 @@ impl Route for StaticRoute {
             return false
         }
 
-        if !pathname.starts_with(&self.path_root) {
+        if strip_path_prefix(pathname, &self.path_root).is_none() || has_dot_segment(pathname) {
             return false
         }
 
+        if self.can_fall_back(pathname) {
+            return true;
+        }
+
         if let Some(extension_rules) = &self.extension_rules {
             for rule in extension_rules.iter() {
                 if pathname.ends_with(rule) {
@@ -55,28 +183,447 @@ impl Route for StaticRoute {
     }
 
     fn execute(&self, http_request: HttpRequest) -> (HttpRequest, HttpResponse) {
-        let req_path = http_request.get_req_path();
-        let file_path = req_path
-            .get_pathname()
-            .replace(&self.path_root, &self.static_root);
+        let pathname = http_request.get_req_path().get_pathname();
+        let relative_path = match strip_path_prefix(pathname, &self.path_root) {
+            Some(relative_path) if !has_dot_segment(pathname) => relative_path,
+            _ => return (http_request, HttpResponse::new_with(HttpStatus::NOT_FOUND)),
+        };
+        let file_path = PathBuf::from(format!("{}{}", &self.static_root[1..], relative_path));
 
-        let file = if let Ok(file) = File::open(&file_path[1..]) {
-            file
-        } else {
-            return (
-                http_request,
-                HttpResponse::new_with(HttpStatus::NOT_FOUND),
-            );
+        let http_response = match self.resolve_file(&file_path) {
+            Some((file_path, metadata)) => {
+                self.respond_static_file(&http_request, &file_path, &metadata)
+            }
+            None if self.can_fall_back(pathname) => self.respond_fallback(&http_request),
+            None => {
+                return (
+                    http_request,
+                    HttpResponse::new_with(HttpStatus::NOT_FOUND),
+                );
+            }
         };
 
         (
             http_request,
-            HttpResponseBuilder::new()
+            http_response.unwrap_or(HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR)),
+        )
+    }
+}
+
+// the rest of the path after the prefix, only when the prefix ends at a segment boundary,
+// e.g. '/app' is a prefix of '/app' and '/app/users', but not of '/application'
+fn strip_path_prefix<'a>(pathname: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = pathname.strip_prefix(prefix)?;
+    if rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/') {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+// '.' and '..' segments, also when they are percent-encoded, could reach out of 'static_root'
+fn has_dot_segment(pathname: &str) -> bool {
+    pathname.split('/').any(|segment| {
+        let segment = percent_decode(segment).unwrap_or_else(|| segment.to_string());
+        segment == "." || segment == ".."
+    })
+}
+
+pub(crate) fn get_content_type(path: &Path) -> ContentType {
+    let extension = path.extension().and_then(|extension| extension.to_str());
+    ContentType::from_extension(extension.unwrap_or(""))
+}
+
+// the file is sent with sendfile(2)
+fn respond_file(
+    http_request: &HttpRequest,
+    file_path: &Path,
+    metadata: &Metadata,
+) -> Result<HttpResponse, HttpError> {
+    let etag = make_etag(metadata);
+    if is_not_modified(http_request, &etag) {
+        return respond_not_modified(&etag);
+    }
+
+    let file = File::open(file_path).map_err(|_| HttpError::ResponseBuildError)?;
+    let file_len = metadata.len();
+    let content_type = get_content_type(file_path);
+    let range: Option<String> = http_request.get_header("range");
+
+    match parse_range(range.as_deref(), file_len) {
+        ByteRange::Full => HttpResponseBuilder::new()
+            .set_status(HttpStatus::OK)
+            .header("Accept-Ranges", "bytes")
+            .header("ETag", &etag)
+            .file(content_type, file)
+            .build(),
+        ByteRange::Partial(offset, length) => HttpResponseBuilder::new()
+            .set_status(HttpStatus::PARTIAL_CONTENT)
+            .header("Accept-Ranges", "bytes")
+            .header("ETag", &etag)
+            .header("Content-Range", &make_content_range(offset, length, file_len))
+            .file_range(content_type, file, offset, length)
+            .build(),
+        ByteRange::Unsatisfiable => respond_range_not_satisfiable(file_len),
+    }
+}
+
+fn respond_cached_file(
+    http_request: &HttpRequest,
+    cached_file: &CachedFile,
+) -> Result<HttpResponse, HttpError> {
+    respond_content(
+        http_request,
+        cached_file.get_content(),
+        cached_file.get_gzipped(),
+        cached_file.get_etag(),
+        cached_file.get_content_type().clone(),
+    )
+}
+
+// the content is sent from the memory, compressed if the client accepts it
+pub(crate) fn respond_content(
+    http_request: &HttpRequest,
+    content: &[u8],
+    gzipped: Option<&[u8]>,
+    etag: &str,
+    content_type: ContentType,
+) -> Result<HttpResponse, HttpError> {
+    if is_not_modified(http_request, etag) {
+        return respond_not_modified(etag);
+    }
+
+    let file_len = content.len() as u64;
+    let range: Option<String> = http_request.get_header("range");
+
+    match parse_range(range.as_deref(), file_len) {
+        ByteRange::Full => {
+            let http_response = HttpResponseBuilder::new()
                 .set_status(HttpStatus::OK)
-                // TODO: content type mapping by extension
-                .file(ContentType::new("image", "png"), file)
-                .build()
-                .unwrap_or(HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR)),
+                .header("Accept-Ranges", "bytes")
+                .header("ETag", etag);
+
+            match gzipped {
+                Some(gzipped) if is_gzip_accepted(http_request) => http_response
+                    .header("Content-Encoding", "gzip")
+                    .header("Vary", "Accept-Encoding")
+                    .body(content_type, gzipped.to_vec())
+                    .build(),
+                Some(_) => http_response
+                    .header("Vary", "Accept-Encoding")
+                    .body(content_type, content.to_vec())
+                    .build(),
+                None => http_response.body(content_type, content.to_vec()).build(),
+            }
+        }
+        // a range is a part of the uncompressed content
+        ByteRange::Partial(offset, length) => HttpResponseBuilder::new()
+            .set_status(HttpStatus::PARTIAL_CONTENT)
+            .header("Accept-Ranges", "bytes")
+            .header("ETag", etag)
+            .header("Content-Range", &make_content_range(offset, length, file_len))
+            .body(
+                content_type,
+                content[offset as usize..(offset + length) as usize].to_vec(),
+            )
+            .build(),
+        ByteRange::Unsatisfiable => respond_range_not_satisfiable(file_len),
+    }
+}
+
+fn respond_not_modified(etag: &str) -> Result<HttpResponse, HttpError> {
+    HttpResponseBuilder::new()
+        .set_status(HttpStatus::NOT_MODIFIED)
+        .header("ETag", etag)
+        .build()
+}
+
+fn respond_range_not_satisfiable(file_len: u64) -> Result<HttpResponse, HttpError> {
+    HttpResponseBuilder::new()
+        .set_status(HttpStatus::RANGE_NOT_SATISFIABLE)
+        .header("Content-Range", &format!("bytes */{}", file_len))
+        .build()
+}
+
+fn make_content_range(offset: u64, length: u64, file_len: u64) -> String {
+    format!("bytes {}-{}/{}", offset, offset + length - 1, file_len)
+}
+
+// the client has the same version of the file
+fn is_not_modified(http_request: &HttpRequest, etag: &str) -> bool {
+    let if_none_match: Option<String> = http_request.get_header("if-none-match");
+    if let Some(if_none_match) = if_none_match {
+        if_none_match.split(',').map(str::trim).any(|tag| {
+            tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag
+        })
+    } else {
+        false
+    }
+}
+
+// e.g. 'gzip, deflate, br' or 'br;q=1.0, gzip;q=0.8'
+fn is_gzip_accepted(http_request: &HttpRequest) -> bool {
+    let accept_encoding: Option<String> = http_request.get_header("accept-encoding");
+    let accept_encoding = accept_encoding.unwrap_or_default();
+
+    accept_encoding.split(',').any(|coding| {
+        let mut params = coding.split(';').map(str::trim);
+        let is_gzip = params.next().is_some_and(|name| name.eq_ignore_ascii_case("gzip"));
+        let is_refused = params.any(|param| {
+            param
+                .strip_prefix("q=")
+                .and_then(|q| q.parse::<f32>().ok())
+                .is_some_and(|q| q == 0.0)
+        });
+
+        is_gzip && !is_refused
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    Full,
+    // offset and length
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+// only a single range is served, the whole file is sent for the other ranges.
+// e.g. 'bytes=0-499', 'bytes=500-', 'bytes=-500'
+fn parse_range(range: Option<&str>, file_len: u64) -> ByteRange {
+    let spec = if let Some(spec) = range.and_then(|range| range.trim().strip_prefix("bytes=")) {
+        spec.trim()
+    } else {
+        return ByteRange::Full;
+    };
+
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+
+    let (start, end) = if let Some(range) = spec.split_once('-') {
+        (range.0.trim(), range.1.trim())
+    } else {
+        return ByteRange::Full;
+    };
+
+    // the last bytes of the file
+    if start.is_empty() {
+        return match end.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if file_len == 0 => ByteRange::Unsatisfiable,
+            Ok(suffix) => {
+                let length = suffix.min(file_len);
+                ByteRange::Partial(file_len - length, length)
+            }
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let start = if let Ok(start) = start.parse::<u64>() {
+        start
+    } else {
+        return ByteRange::Full;
+    };
+    let end = if end.is_empty() {
+        None
+    } else if let Ok(end) = end.parse::<u64>() {
+        Some(end)
+    } else {
+        return ByteRange::Full;
+    };
+
+    if end.is_some_and(|end| end < start) {
+        return ByteRange::Full;
+    }
+    if start >= file_len {
+        return ByteRange::Unsatisfiable;
+    }
+
+    let end = end.map_or(file_len - 1, |end| end.min(file_len - 1));
+    ByteRange::Partial(start, end - start + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use crate::test_support::make_request;
+    use crate::util::random::random_hex;
+
+    use super::*;
+
+    fn respond(route: &StaticRoute, raw: &str) -> String {
+        let (_, mut http_response) = route.execute(make_request(raw, None));
+        let mut written = vec![];
+        http_response.respond(&mut written);
+
+        String::from_utf8_lossy(&written).to_string()
+    }
+
+    // 'static_root' is resolved without its leading '/', so an absolute directory gets one more
+    fn make_static_root(name: &str) -> (String, PathBuf) {
+        let directory = env::temp_dir().join(format!("{}-{}", name, random_hex(4)));
+        (format!("/{}", directory.display()), directory)
+    }
+
+    fn get_header(response: &str, key: &str) -> Option<String> {
+        response
+            .lines()
+            .find_map(|line| line.strip_prefix(&format!("{}: ", key)))
+            .map(str::to_string)
+    }
+
+    #[test]
+    fn test_cache() {
+        let (static_root, directory) = make_static_root("lite-ws-static");
+        fs::create_dir_all(&directory).unwrap();
+        let css_path = directory.join("style.css");
+        fs::write(&css_path, "body { color: red; }\n".repeat(50)).unwrap();
+
+        let route = StaticRoute::new(
+            static_root.clone(),
+            Some("/static".to_string()),
+            Some(vec![".css".to_string()]),
+        )
+            .cache(1024 * 1024);
+
+        let response = respond(&route, "GET /static/style.css HTTP/1.1\r\nAccept-Encoding: gzip, br\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(get_header(&response, "Content-Type").unwrap(), "text/css");
+        assert_eq!(get_header(&response, "Content-Encoding").unwrap(), "gzip");
+        let etag = get_header(&response, "ETag").unwrap();
+
+        let response = respond(&route, "GET /static/style.css HTTP/1.1\r\nAccept-Encoding: gzip;q=0\r\n");
+        assert!(get_header(&response, "Content-Encoding").is_none());
+        assert!(response.ends_with("body { color: red; }\n"));
+
+        let response = respond(
+            &route,
+            &format!("GET /static/style.css HTTP/1.1\r\nIf-None-Match: {}\r\n", etag),
+        );
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::NOT_MODIFIED)));
+
+        let response = respond(&route, "GET /static/style.css HTTP/1.1\r\nRange: bytes=0-3\r\n");
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::PARTIAL_CONTENT)));
+        assert!(response.ends_with("\r\n\r\nbody"));
+
+        // the changed file is read again
+        fs::write(&css_path, "p {}").unwrap();
+        let response = respond(&route, "GET /static/style.css HTTP/1.1\r\n");
+        assert!(response.ends_with("\r\n\r\np {}"));
+        assert_ne!(get_header(&response, "ETag").unwrap(), etag);
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_spa_fallback() {
+        let (static_root, directory) = make_static_root("lite-ws-spa");
+        fs::create_dir_all(directory.join("assets")).unwrap();
+        fs::write(directory.join("index.html"), "<html>app</html>").unwrap();
+        fs::write(directory.join("assets/main.js"), "main()").unwrap();
+
+        let route = StaticRoute::new(
+            static_root.clone(),
+            Some("/app".to_string()),
+            Some(vec![".js".to_string()]),
         )
+            .spa_fallback("index.html")
+            .exclude_prefix("/app/api");
+
+        assert!(route.is_path_matching(HttpMethod::GET, "/app"));
+        assert!(route.is_path_matching(HttpMethod::GET, "/app/users/1"));
+        assert!(route.is_path_matching(HttpMethod::GET, "/app/assets/main.js"));
+        assert!(!route.is_path_matching(HttpMethod::GET, "/app/api/users"));
+        assert!(route.is_path_matching(HttpMethod::GET, "/app/apidocs"));
+        assert!(!route.is_path_matching(HttpMethod::GET, "/application"));
+        assert!(!route.is_path_matching(HttpMethod::GET, "/apple/main.js"));
+        assert!(!route.is_path_matching(HttpMethod::GET, "/app/logo.png"));
+        assert!(!route.is_path_matching(HttpMethod::POST, "/app/users/1"));
+
+        let response = respond(&route, "GET /app/users/1 HTTP/1.1\r\n");
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(get_header(&response, "Cache-Control").unwrap(), "no-cache");
+        assert_eq!(get_header(&response, "Content-Type").unwrap(), "text/html");
+        assert!(response.ends_with("<html>app</html>"));
+
+        let response = respond(&route, "GET /app/assets/main.js HTTP/1.1\r\n");
+        assert!(get_header(&response, "Cache-Control").is_none());
+        assert!(response.ends_with("main()"));
+
+        // a missing file is not a client-side route
+        let response = respond(&route, "GET /app/assets/missing.js HTTP/1.1\r\n");
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::NOT_FOUND)));
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_path_traversal() {
+        let (static_root, directory) = make_static_root("lite-ws-traversal");
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("index.html"), "<html>app</html>").unwrap();
+        fs::write(directory.with_extension("secret"), "secret").unwrap();
+
+        let route = StaticRoute::new(
+            static_root.clone(),
+            Some("/app".to_string()),
+            Some(vec![".secret".to_string()]),
+        )
+            .spa_fallback("index.html");
+
+        for pathname in [
+            "/app/../../../../../etc/passwd",
+            "/app/./index.html",
+            "/app/%2e%2e/%2E%2E/etc/passwd",
+            "/app/.%2e/secret",
+        ] {
+            assert!(!route.is_path_matching(HttpMethod::GET, pathname), "{}", pathname);
+            let response = respond(&route, &format!("GET {} HTTP/1.1\r\n", pathname));
+            let not_found = format!("HTTP/1.1 {}", HttpStatus::NOT_FOUND);
+            assert!(response.starts_with(&not_found), "{}", pathname);
+        }
+
+        // a link in 'static_root' can not reach out of it
+        #[cfg(unix)]
+        {
+            let secret = fs::canonicalize(directory.with_extension("secret")).unwrap();
+            std::os::unix::fs::symlink(secret, directory.join("link.secret")).unwrap();
+            assert!(route.is_path_matching(HttpMethod::GET, "/app/link.secret"));
+            let response = respond(&route, "GET /app/link.secret HTTP/1.1\r\n");
+            assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::NOT_FOUND)));
+        }
+
+        fs::remove_dir_all(&directory).unwrap();
+        fs::remove_file(directory.with_extension("secret")).unwrap();
+    }
+
+    #[test]
+    fn test_strip_path_prefix() {
+        assert_eq!(strip_path_prefix("/app", "/app"), Some(""));
+        assert_eq!(strip_path_prefix("/app/users", "/app"), Some("/users"));
+        assert_eq!(strip_path_prefix("/app/users", "/app/"), Some("users"));
+        assert_eq!(strip_path_prefix("/application", "/app"), None);
+        assert_eq!(strip_path_prefix("/", "/app"), None);
+        assert_eq!(strip_path_prefix("/style.css", "/"), Some("style.css"));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), ByteRange::Partial(0, 10));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), ByteRange::Partial(90, 10));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), ByteRange::Partial(90, 10));
+        assert_eq!(parse_range(Some("bytes=-200"), 100), ByteRange::Partial(0, 100));
+        assert_eq!(parse_range(Some("bytes=50-200"), 100), ByteRange::Partial(50, 50));
+
+        assert_eq!(parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=-0"), 100), ByteRange::Unsatisfiable);
+
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=9-0"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("items=0-9"), 100), ByteRange::Full);
+        assert_eq!(parse_range(Some("bytes=a-9"), 100), ByteRange::Full);
     }
 }
@@ -0,0 +1,50 @@
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+// run a future on the current thread until it completes.
+// async routes are executed with it when the server does not run the reactors.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            // a spurious unpark only costs another poll
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::rt::sleep;
+
+    use super::*;
+
+    #[test]
+    fn test_block_on() {
+        let started = Instant::now();
+        let output = block_on(async {
+            sleep(Duration::from_millis(50)).await;
+            1
+        });
+
+        assert_eq!(output, 1);
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Poll, Waker};
+
+struct ChannelState<T> {
+    queue: VecDeque<T>,
+    // the receiver waiting for the next value
+    waker: Option<Waker>,
+    sender_count: usize,
+    is_receiver_dropped: bool,
+}
+
+struct Shared<T> {
+    state: Mutex<ChannelState<T>>,
+}
+
+impl<T> Shared<T> {
+    fn lock(&self) -> MutexGuard<'_, ChannelState<T>> {
+        match self.state.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+// sends to a Receiver from any thread, e.g. a blocking job which hands its result to an async handler
+pub struct Sender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// receives without blocking the executor
+pub struct Receiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+// an unbounded queue between threads and futures
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(ChannelState {
+            queue: VecDeque::new(),
+            waker: None,
+            sender_count: 1,
+            is_receiver_dropped: false,
+        }),
+    });
+
+    (
+        Sender {
+            shared: Arc::clone(&shared),
+        },
+        Receiver { shared },
+    )
+}
+
+impl<T> Sender<T> {
+    // the value is given back when the receiver has been dropped
+    pub fn send(&self, value: T) -> Result<(), T> {
+        let mut state = self.shared.lock();
+        if state.is_receiver_dropped {
+            return Err(value);
+        }
+
+        state.queue.push_back(value);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.lock().sender_count += 1;
+        Sender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.sender_count -= 1;
+        if state.sender_count == 0 {
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    // none once every sender has been dropped and the queue is empty
+    pub async fn recv(&mut self) -> Option<T> {
+        poll_fn(|context| {
+            let mut state = self.shared.lock();
+            if let Some(value) = state.queue.pop_front() {
+                return Poll::Ready(Some(value));
+            }
+            if state.sender_count == 0 {
+                return Poll::Ready(None);
+            }
+
+            state.waker = Some(context.waker().clone());
+            Poll::Pending
+        })
+            .await
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut state = self.shared.lock();
+        state.is_receiver_dropped = true;
+        state.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::rt::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_channel() {
+        let (sender, mut receiver) = channel();
+        let other_sender = sender.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            sender.send(1).unwrap();
+            other_sender.send(2).unwrap();
+        });
+
+        let received = block_on(async move {
+            let mut received = vec![];
+            while let Some(value) = receiver.recv().await {
+                received.push(value);
+            }
+            received
+        });
+
+        assert_eq!(received, vec![1, 2]);
+        handle.join().unwrap();
+
+        let (sender, receiver) = channel();
+        drop(receiver);
+        assert_eq!(sender.send(3), Err(3));
+    }
+}
@@ -0,0 +1,159 @@
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, mpsc, Mutex};
+use std::task::{Context, Wake, Waker};
+use std::thread;
+
+type BoxedFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+enum Message {
+    Poll(Arc<AsyncTask>),
+    Terminate,
+}
+
+// a spawned future, which is sent to the run queue again whenever it is woken
+struct AsyncTask {
+    future: Mutex<Option<BoxedFuture>>,
+    sender: mpsc::Sender<Message>,
+}
+
+impl Wake for AsyncTask {
+    fn wake(self: Arc<Self>) {
+        let sender = self.sender.clone();
+        // the executor has stopped, the future is dropped with the task
+        sender.send(Message::Poll(self)).ok();
+    }
+}
+
+// polls futures on a few threads, so a waiting handler costs no thread
+pub(crate) struct Executor {
+    sender: mpsc::Sender<Message>,
+    threads: Vec<thread::JoinHandle<()>>,
+}
+
+impl Executor {
+    pub fn new(thread_count: u16) -> Executor {
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let threads = (0..thread_count.max(1))
+            .map(|id| {
+                let receiver = Arc::clone(&receiver);
+                thread::spawn(move || run(id, receiver))
+            })
+            .collect();
+
+        Executor { sender, threads }
+    }
+
+    pub fn spawn<F: Future<Output = ()> + Send + 'static>(&self, future: F) {
+        let task = Arc::new(AsyncTask {
+            future: Mutex::new(Some(Box::pin(future))),
+            sender: self.sender.clone(),
+        });
+
+        if self.sender.send(Message::Poll(task)).is_err() {
+            eprintln!("[error] the executor has stopped");
+        }
+    }
+
+    // the futures which are still pending are dropped
+    pub fn shutdown(&mut self) {
+        for _ in self.threads.iter() {
+            self.sender.send(Message::Terminate).ok();
+        }
+
+        for thread in self.threads.drain(..) {
+            if thread.join().is_err() {
+                eprintln!("[error] an executor thread has died with a panic");
+            }
+        }
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn run(id: u16, receiver: Arc<Mutex<mpsc::Receiver<Message>>>) {
+    loop {
+        let message = match receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => break,
+        };
+
+        let task = match message {
+            Ok(Message::Poll(task)) => task,
+            Ok(Message::Terminate) | Err(_) => break,
+        };
+
+        let mut slot = match task.future.lock() {
+            Ok(slot) => slot,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // woken again after it has completed
+        let future = if let Some(future) = slot.as_mut() {
+            future
+        } else {
+            continue;
+        };
+
+        let waker = Waker::from(Arc::clone(&task));
+        let mut context = Context::from_waker(&waker);
+        // a panic of a future must not kill the executor thread
+        match panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(&mut context))) {
+            Ok(poll) if poll.is_pending() => {}
+            Ok(_) => *slot = None,
+            Err(_) => {
+                eprintln!("[executor {}] future panicked, it is dropped", id);
+                *slot = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::rt::sleep;
+
+    use super::*;
+
+    #[test]
+    fn test_many_sleeping_futures() {
+        let executor = Executor::new(1);
+        let (sender, receiver) = mpsc::channel();
+        let started = Instant::now();
+
+        // they sleep at the same time on a single thread
+        for i in 0..1000 {
+            let sender = sender.clone();
+            executor.spawn(async move {
+                sleep(Duration::from_millis(100)).await;
+                sender.send(i).unwrap();
+            });
+        }
+
+        let mut received: Vec<i32> = (0..1000)
+            .map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap())
+            .collect();
+        received.sort();
+        assert_eq!(received, (0..1000).collect::<Vec<i32>>());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_panic_in_future() {
+        let executor = Executor::new(1);
+        let (sender, receiver) = mpsc::channel();
+
+        executor.spawn(async { panic!("panic in a future") });
+        executor.spawn(async move { sender.send(1).unwrap() });
+
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+    }
+}
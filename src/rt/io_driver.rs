@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use lazy_static::lazy_static;
+use mio::{Events, Interest, Registry, Token};
+use mio::event::Source;
+
+// which readiness an operation waits for
+#[derive(Clone, Copy)]
+pub(crate) enum Direction {
+    Read,
+    Write,
+}
+
+// the readiness of a source, shared by the driver and the futures which use the source
+#[derive(Default)]
+struct IoState {
+    // counted on every event, so an event between a try and storing the waker is not lost
+    read_ticks: u64,
+    write_ticks: u64,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+struct Driver {
+    registry: Registry,
+    sources: Mutex<HashMap<Token, Arc<Mutex<IoState>>>>,
+    next_token: AtomicUsize,
+}
+
+lazy_static! {
+    // a single thread waits for the readiness of every async socket, like the timer of 'Sleep'
+    static ref DRIVER: io::Result<&'static Driver> = {
+        let mut poll = mio::Poll::new()?;
+        let driver: &'static Driver = Box::leak(Box::new(Driver {
+            registry: poll.registry().try_clone()?,
+            sources: Mutex::new(HashMap::new()),
+            next_token: AtomicUsize::new(0),
+        }));
+        thread::spawn(move || driver.run(&mut poll));
+
+        Ok(driver)
+    };
+}
+
+fn get_driver() -> io::Result<&'static Driver> {
+    match &*DRIVER {
+        Ok(driver) => Ok(driver),
+        Err(error) => Err(io::Error::new(error.kind(), error.to_string())),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+impl Driver {
+    fn run(&self, poll: &mut mio::Poll) {
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            if let Err(error) = poll.poll(&mut events, None) {
+                if error.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                eprintln!("[error] the I/O driver fails to poll: {}", error);
+                return;
+            }
+
+            let sources = lock(&self.sources);
+            for event in events.iter() {
+                let mut state = if let Some(state) = sources.get(&event.token()) {
+                    lock(state)
+                } else {
+                    continue;
+                };
+
+                // an error or a closed side is found by the next operation
+                if event.is_readable() || event.is_read_closed() || event.is_error() {
+                    state.read_ticks += 1;
+                    if let Some(waker) = state.read_waker.take() {
+                        waker.wake();
+                    }
+                }
+                if event.is_writable() || event.is_write_closed() || event.is_error() {
+                    state.write_ticks += 1;
+                    if let Some(waker) = state.write_waker.take() {
+                        waker.wake();
+                    }
+                }
+            }
+        }
+    }
+}
+
+// a source registered to the I/O driver, its operations wait for the readiness instead of blocking
+pub(crate) struct Registration {
+    token: Token,
+    state: Arc<Mutex<IoState>>,
+}
+
+impl Registration {
+    pub fn new<S: Source>(source: &mut S) -> io::Result<Self> {
+        let driver = get_driver()?;
+        let token = Token(driver.next_token.fetch_add(1, Ordering::Relaxed));
+        let state = Arc::new(Mutex::new(IoState::default()));
+
+        lock(&driver.sources).insert(token, Arc::clone(&state));
+        if let Err(error) = driver
+            .registry
+            .register(source, token, Interest::READABLE | Interest::WRITABLE)
+        {
+            lock(&driver.sources).remove(&token);
+            return Err(error);
+        }
+
+        Ok(Registration { token, state })
+    }
+
+    // run the operation, and wait for the readiness while it would block
+    pub fn poll_io<T, F>(&self, context: &mut Context<'_>, direction: Direction, mut operation: F) -> Poll<io::Result<T>>
+        where
+            F: FnMut() -> io::Result<T>,
+    {
+        loop {
+            let ticks = self.get_ticks(direction);
+            match operation() {
+                Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                result => return Poll::Ready(result),
+            }
+
+            let mut state = lock(&self.state);
+            let (current_ticks, waker) = match direction {
+                Direction::Read => (state.read_ticks, &mut state.read_waker),
+                Direction::Write => (state.write_ticks, &mut state.write_waker),
+            };
+            // the source has become ready while the operation was tried
+            if current_ticks != ticks {
+                continue;
+            }
+            *waker = Some(context.waker().clone());
+
+            return Poll::Pending;
+        }
+    }
+
+    fn get_ticks(&self, direction: Direction) -> u64 {
+        let state = lock(&self.state);
+        match direction {
+            Direction::Read => state.read_ticks,
+            Direction::Write => state.write_ticks,
+        }
+    }
+
+    // must be called before the source is dropped
+    pub fn deregister<S: Source>(&self, source: &mut S) {
+        if let Ok(driver) = get_driver() {
+            driver.registry.deregister(source).ok();
+            lock(&driver.sources).remove(&self.token);
+        }
+    }
+}
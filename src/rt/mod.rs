@@ -0,0 +1,13 @@
+pub use block_on::block_on;
+pub use channel::{channel, Receiver, Sender};
+pub use sleep::{sleep, Sleep};
+pub use tcp_stream::TcpStream;
+
+pub(crate) use executor::Executor;
+
+mod block_on;
+mod channel;
+mod executor;
+mod io_driver;
+mod sleep;
+mod tcp_stream;
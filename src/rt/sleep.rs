@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+
+// the deadline of an entry, and an id which tells apart the same deadlines
+type TimerKey = (Instant, u64);
+
+struct Timer {
+    entries: Mutex<TimerEntries>,
+    condvar: Condvar,
+}
+
+// the wakers in the order of their deadlines
+#[derive(Default)]
+struct TimerEntries {
+    wakers: BTreeMap<TimerKey, Waker>,
+    next_id: u64,
+}
+
+lazy_static! {
+    // a single thread wakes every sleeping future, so sleeping costs no thread
+    static ref TIMER: &'static Timer = {
+        let timer: &'static Timer = Box::leak(Box::new(Timer::new()));
+        thread::spawn(move || timer.run());
+
+        timer
+    };
+}
+
+impl Timer {
+    fn new() -> Self {
+        Timer {
+            entries: Mutex::new(TimerEntries::default()),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TimerEntries> {
+        match self.entries.lock() {
+            Ok(entries) => entries,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+
+    fn register(&self, deadline: Instant, waker: Waker) -> TimerKey {
+        let mut entries = self.lock();
+        let key = (deadline, entries.next_id);
+        entries.next_id += 1;
+
+        // the timer thread waits for the earliest deadline
+        let is_earliest = entries.wakers.keys().next().is_none_or(|first| key < *first);
+        entries.wakers.insert(key, waker);
+        if is_earliest {
+            self.condvar.notify_one();
+        }
+
+        key
+    }
+
+    // the future has been polled by another task
+    fn update(&self, key: TimerKey, waker: Waker) {
+        if let Some(registered) = self.lock().wakers.get_mut(&key) {
+            *registered = waker;
+        }
+    }
+
+    fn cancel(&self, key: TimerKey) {
+        self.lock().wakers.remove(&key);
+    }
+
+    fn run(&self) {
+        let mut entries = self.lock();
+
+        loop {
+            let now = Instant::now();
+            while let Some(entry) = entries.wakers.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                entry.remove().wake();
+            }
+
+            let timeout = entries
+                .wakers
+                .keys()
+                .next()
+                .map(|(deadline, _)| *deadline - now)
+                .unwrap_or(Duration::from_secs(60));
+            entries = match self.condvar.wait_timeout(entries, timeout) {
+                Ok((entries, _)) => entries,
+                Err(poisoned) => poisoned.into_inner().0,
+            };
+        }
+    }
+}
+
+// a future which completes after the duration, without blocking the thread
+pub struct Sleep {
+    deadline: Instant,
+    // the entry of the timer and its waker, once the future has been polled
+    registration: Option<(TimerKey, Waker)>,
+}
+
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now() + duration,
+        registration: None,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let sleep = self.get_mut();
+        if Instant::now() >= sleep.deadline {
+            if let Some((key, _)) = sleep.registration.take() {
+                TIMER.cancel(key);
+            }
+            return Poll::Ready(());
+        }
+
+        match &mut sleep.registration {
+            Some((_, waker)) if waker.will_wake(context.waker()) => {}
+            Some((key, waker)) => {
+                *waker = context.waker().clone();
+                TIMER.update(*key, waker.clone());
+            }
+            None => {
+                let waker = context.waker().clone();
+                let key = TIMER.register(sleep.deadline, waker.clone());
+                sleep.registration = Some((key, waker));
+            }
+        }
+
+        Poll::Pending
+    }
+}
+
+// a dropped future leaves nothing in the timer
+impl Drop for Sleep {
+    fn drop(&mut self) {
+        if let Some((key, _)) = self.registration.take() {
+            TIMER.cancel(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use super::*;
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    #[test]
+    fn test_timer_entries() {
+        let timer = Timer::new();
+        let later = timer.register(Instant::now() + Duration::from_secs(10), Waker::from(Arc::new(NoopWaker)));
+        let sooner = timer.register(Instant::now() + Duration::from_secs(1), Waker::from(Arc::new(NoopWaker)));
+        assert_eq!(timer.lock().wakers.keys().next(), Some(&sooner));
+
+        timer.update(later, Waker::from(Arc::new(NoopWaker)));
+        timer.cancel(sooner);
+        assert_eq!(timer.lock().wakers.keys().collect::<Vec<_>>(), vec![&later]);
+    }
+
+    #[test]
+    fn test_sleep_registration() {
+        let waker = Waker::from(Arc::new(NoopWaker));
+        let mut context = Context::from_waker(&waker);
+        let mut sleep = sleep(Duration::from_secs(10));
+
+        // polled again, the entry is kept instead of adding another one
+        assert!(Pin::new(&mut sleep).poll(&mut context).is_pending());
+        let key = sleep.registration.as_ref().map(|(key, _)| *key).unwrap();
+        assert!(Pin::new(&mut sleep).poll(&mut context).is_pending());
+        assert_eq!(sleep.registration.as_ref().map(|(key, _)| *key), Some(key));
+
+        let other_waker = Waker::from(Arc::new(NoopWaker));
+        assert!(Pin::new(&mut sleep).poll(&mut Context::from_waker(&other_waker)).is_pending());
+        assert!(TIMER.lock().wakers[&key].will_wake(&other_waker));
+
+        drop(sleep);
+        assert!(!TIMER.lock().wakers.contains_key(&key));
+    }
+}
@@ -0,0 +1,132 @@
+use std::future::poll_fn;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr};
+
+use crate::rt::io_driver::{Direction, Registration};
+
+// a TCP socket for async handlers, e.g. to call another service.
+// a read or a write waits for the readiness from the I/O driver instead of blocking the executor.
+pub struct TcpStream {
+    stream: mio::net::TcpStream,
+    registration: Registration,
+}
+
+impl TcpStream {
+    pub async fn connect(addr: SocketAddr) -> io::Result<TcpStream> {
+        let stream = TcpStream::new(mio::net::TcpStream::connect(addr)?)?;
+
+        // the connection is established once the socket becomes writable
+        poll_fn(|context| {
+            stream.registration.poll_io(context, Direction::Write, || {
+                if let Some(error) = stream.stream.take_error()? {
+                    return Err(error);
+                }
+                match stream.stream.peer_addr() {
+                    Ok(_) => Ok(()),
+                    Err(error) if error.kind() == ErrorKind::NotConnected => {
+                        Err(ErrorKind::WouldBlock.into())
+                    }
+                    Err(error) => Err(error),
+                }
+            })
+        })
+            .await?;
+
+        Ok(stream)
+    }
+
+    // take over a connected socket, e.g. accepted by a std listener
+    pub fn from_std(stream: std::net::TcpStream) -> io::Result<TcpStream> {
+        stream.set_nonblocking(true)?;
+        TcpStream::new(mio::net::TcpStream::from_std(stream))
+    }
+
+    fn new(mut stream: mio::net::TcpStream) -> io::Result<TcpStream> {
+        let registration = Registration::new(&mut stream)?;
+        Ok(TcpStream { stream, registration })
+    }
+
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.stream.peer_addr()
+    }
+
+    pub async fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        let TcpStream { stream, registration } = self;
+        poll_fn(|context| registration.poll_io(context, Direction::Read, || stream.read(buffer))).await
+    }
+
+    pub async fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let TcpStream { stream, registration } = self;
+        poll_fn(|context| registration.poll_io(context, Direction::Write, || stream.write(buffer))).await
+    }
+
+    pub async fn write_all(&mut self, mut buffer: &[u8]) -> io::Result<()> {
+        while !buffer.is_empty() {
+            match self.write(buffer).await? {
+                0 => return Err(ErrorKind::WriteZero.into()),
+                nbytes => buffer = &buffer[nbytes..],
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.stream.shutdown(how)
+    }
+}
+
+impl Drop for TcpStream {
+    fn drop(&mut self) {
+        self.registration.deregister(&mut self.stream);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::rt::block_on;
+
+    use super::*;
+
+    #[test]
+    fn test_echo() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let local_addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0_u8; 5];
+            stream.read_exact(&mut buffer).unwrap();
+            // the client waits for the answer without blocking
+            thread::sleep(std::time::Duration::from_millis(50));
+            stream.write_all(&buffer).unwrap();
+        });
+
+        let echoed = block_on(async move {
+            let mut stream = TcpStream::connect(local_addr).await.unwrap();
+            stream.write_all(b"hello").await.unwrap();
+
+            let mut echoed = vec![];
+            let mut buffer = [0_u8; 16];
+            loop {
+                match stream.read(&mut buffer).await.unwrap() {
+                    0 => break,
+                    nbytes => echoed.extend_from_slice(&buffer[..nbytes]),
+                }
+            }
+            echoed
+        });
+
+        assert_eq!(echoed, b"hello");
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_connect_error() {
+        // nothing listens on the port after the listener is dropped
+        let local_addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        assert!(block_on(TcpStream::connect(local_addr)).is_err());
+    }
+}
@@ -0,0 +1,62 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+// shared resources of the application (e.g. database pools, config, caches), looked up by type.
+// it is registered once on the Server, and handlers read it from 'HttpRequest::get_state'.
+#[derive(Default)]
+pub struct AppState {
+    states: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        AppState::default()
+    }
+
+    // register a state, the state which has the same type is replaced
+    pub fn insert<T: Send + Sync + 'static>(&mut self, state: T) {
+        self.states.insert(TypeId::of::<T>(), Arc::new(state));
+    }
+
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        self.states
+            .get(&TypeId::of::<T>())
+            .and_then(|state| state.clone().downcast::<T>().ok())
+    }
+
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.states.contains_key(&TypeId::of::<T>())
+    }
+}
+
+impl fmt::Debug for AppState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AppState")
+            .field("state_count", &self.states.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Config {
+        name: &'static str,
+    }
+
+    #[test]
+    fn test_get_by_type() {
+        let mut app_state = AppState::new();
+        app_state.insert(Config { name: "first" });
+        app_state.insert(Config { name: "second" });
+        app_state.insert(7_u32);
+
+        assert_eq!(app_state.get::<Config>().unwrap().name, "second");
+        assert_eq!(*app_state.get::<u32>().unwrap(), 7);
+        assert!(app_state.get::<String>().is_none());
+        assert!(!app_state.contains::<String>());
+    }
+}
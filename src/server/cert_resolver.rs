@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use rustls::crypto::CryptoProvider;
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+
+use crate::server::tls::load_certified_key;
+use crate::server::TlsConfig;
+
+#[derive(Debug)]
+struct CertSet {
+    default: Arc<CertifiedKey>,
+    // server name (or wildcard) -> certificate
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+}
+
+// selects a certificate by the host name sent with SNI.
+// the certificates can be replaced while the server runs, a handshake in progress keeps the old one.
+#[derive(Debug)]
+pub(crate) struct CertResolver {
+    tls_config: TlsConfig,
+    provider: Arc<CryptoProvider>,
+    cert_set: RwLock<Arc<CertSet>>,
+}
+
+impl CertResolver {
+    pub fn new(tls_config: TlsConfig, provider: Arc<CryptoProvider>) -> io::Result<Self> {
+        let cert_set = load_cert_set(&tls_config, &provider)?;
+
+        Ok(CertResolver {
+            tls_config,
+            provider,
+            cert_set: RwLock::new(Arc::new(cert_set)),
+        })
+    }
+
+    pub fn get_tls_config(&self) -> &TlsConfig {
+        &self.tls_config
+    }
+
+    // the old certificates are kept if any file fails to load
+    pub fn reload(&self) -> io::Result<()> {
+        let cert_set = load_cert_set(&self.tls_config, &self.provider)?;
+        match self.cert_set.write() {
+            Ok(mut guard) => *guard = Arc::new(cert_set),
+            Err(poisoned) => *poisoned.into_inner() = Arc::new(cert_set),
+        }
+
+        Ok(())
+    }
+
+    // the files which are watched for changes
+    pub fn get_file_paths(&self) -> Vec<PathBuf> {
+        let mut file_paths = vec![
+            self.tls_config.get_cert_path().to_path_buf(),
+            self.tls_config.get_key_path().to_path_buf(),
+        ];
+        for (_, cert_path, key_path) in self.tls_config.get_sni_certs() {
+            file_paths.push(cert_path.clone());
+            file_paths.push(key_path.clone());
+        }
+
+        file_paths
+    }
+
+    fn get_cert_set(&self) -> Arc<CertSet> {
+        match self.cert_set.read() {
+            Ok(guard) => Arc::clone(&guard),
+            Err(poisoned) => Arc::clone(&poisoned.into_inner()),
+        }
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let cert_set = self.get_cert_set();
+        let server_name = if let Some(server_name) = client_hello.server_name() {
+            server_name.to_lowercase()
+        } else {
+            return Some(Arc::clone(&cert_set.default));
+        };
+
+        if let Some(certified_key) = cert_set.by_name.get(&server_name) {
+            return Some(Arc::clone(certified_key));
+        }
+
+        // a wildcard matches a single label, e.g. '*.example.com' matches 'www.example.com'
+        let wildcard = server_name
+            .split_once('.')
+            .map(|(_, parent)| format!("*.{}", parent));
+        let certified_key = wildcard.and_then(|wildcard| cert_set.by_name.get(&wildcard));
+
+        Some(Arc::clone(certified_key.unwrap_or(&cert_set.default)))
+    }
+}
+
+fn load_cert_set(tls_config: &TlsConfig, provider: &CryptoProvider) -> io::Result<CertSet> {
+    let default =
+        load_certified_key(tls_config.get_cert_path(), tls_config.get_key_path(), provider)?;
+
+    let mut by_name = HashMap::new();
+    for (server_name, cert_path, key_path) in tls_config.get_sni_certs() {
+        let certified_key = load_certified_key(cert_path, key_path, provider)?;
+        by_name.insert(server_name.clone(), Arc::new(certified_key));
+    }
+
+    Ok(CertSet {
+        default: Arc::new(default),
+        by_name,
+    })
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+#[cfg(unix)]
+use signal_hook::consts::SIGHUP;
+use signal_hook::SigId;
+
+use crate::server::cert_resolver::CertResolver;
+
+// how often the stop and SIGHUP are checked
+const TICK: Duration = Duration::from_millis(100);
+
+// reloads the certificates on SIGHUP, or when the files are changed.
+// the running server keeps its connections, only the new handshakes get the new certificates.
+pub(crate) struct CertWatcher {
+    is_stopped: Arc<AtomicBool>,
+    sig_id: Option<SigId>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl CertWatcher {
+    pub fn start(
+        cert_resolver: Arc<CertResolver>,
+        reload_interval: Option<Duration>,
+        handle_sighup: bool,
+    ) -> io::Result<Self> {
+        let is_hung_up = Arc::new(AtomicBool::new(false));
+        let sig_id = if handle_sighup {
+            register_sighup(&is_hung_up)?
+        } else {
+            None
+        };
+
+        let is_stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let is_stopped = is_stopped.clone();
+            thread::spawn(move || {
+                watch(&cert_resolver, reload_interval, &is_hung_up, &is_stopped);
+            })
+        };
+
+        Ok(CertWatcher {
+            is_stopped,
+            sig_id,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for CertWatcher {
+    fn drop(&mut self) {
+        self.is_stopped.store(true, Ordering::SeqCst);
+        if let Some(sig_id) = self.sig_id.take() {
+            signal_hook::low_level::unregister(sig_id);
+        }
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+#[cfg(unix)]
+fn register_sighup(is_hung_up: &Arc<AtomicBool>) -> io::Result<Option<SigId>> {
+    signal_hook::flag::register(SIGHUP, is_hung_up.clone()).map(Some)
+}
+
+// there is no SIGHUP on the other targets, the certificates are reloaded only by the interval
+#[cfg(not(unix))]
+fn register_sighup(_is_hung_up: &Arc<AtomicBool>) -> io::Result<Option<SigId>> {
+    Ok(None)
+}
+
+fn watch(
+    cert_resolver: &CertResolver,
+    reload_interval: Option<Duration>,
+    is_hung_up: &AtomicBool,
+    is_stopped: &AtomicBool,
+) {
+    let mut modified_times = get_modified_times(cert_resolver);
+    let mut checked_at = Instant::now();
+
+    while !is_stopped.load(Ordering::SeqCst) {
+        thread::sleep(TICK);
+
+        if is_hung_up.swap(false, Ordering::SeqCst) {
+            println!("SIGHUP is received, reloading the certificates");
+            reload(cert_resolver);
+            modified_times = get_modified_times(cert_resolver);
+        }
+
+        let reload_interval = if let Some(reload_interval) = reload_interval {
+            reload_interval
+        } else {
+            continue;
+        };
+        if checked_at.elapsed() < reload_interval {
+            continue;
+        }
+        checked_at = Instant::now();
+
+        let current_times = get_modified_times(cert_resolver);
+        // a file being written may fail to load, it is tried again on the next check
+        if current_times != modified_times && reload(cert_resolver) {
+            modified_times = current_times;
+        }
+    }
+}
+
+fn reload(cert_resolver: &CertResolver) -> bool {
+    match cert_resolver.reload() {
+        Ok(()) => {
+            println!("certificates are reloaded");
+            true
+        }
+        Err(error) => {
+            eprintln!("[error] fail to reload the certificates, keep the old ones: {}", error);
+            false
+        }
+    }
+}
+
+fn get_modified_times(cert_resolver: &CertResolver) -> Vec<Option<SystemTime>> {
+    cert_resolver
+        .get_file_paths()
+        .iter()
+        .map(|path| fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+        .collect()
+}
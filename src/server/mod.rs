@@ -1,5 +1,30 @@
+#[allow(clippy::module_inception)]
 pub mod server;
 pub mod server_builder;
 
+mod app_state;
+#[cfg(feature = "tls")]
+mod cert_resolver;
+#[cfg(feature = "tls")]
+mod cert_watcher;
+mod reactor;
+mod reactor_stream;
+mod shutdown;
+#[cfg(feature = "tls")]
+mod tls;
+
+pub use app_state::AppState;
 pub use server::Server;
-pub use server_builder::{ServerBuilder, ServerConfig};
+pub use server_builder::{IoMode, ServerBuilder, ServerConfig};
+pub use shutdown::ShutdownHandle;
+#[cfg(feature = "tls")]
+pub use tls::TlsConfig;
+pub use crate::worker::QueueMetrics;
+
+#[cfg(feature = "tls")]
+pub(crate) use tls::{get_tls_info, TlsAcceptor};
+
+// TLS is not compiled in, so there is never an acceptor
+#[cfg(not(feature = "tls"))]
+#[derive(Clone)]
+pub(crate) enum TlsAcceptor {}
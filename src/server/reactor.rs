@@ -0,0 +1,1214 @@
+//! ref <https://docs.rs/mio/latest/mio/guide/index.html>
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Error, ErrorKind, Read, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TrySendError};
+use std::time::{Duration, Instant};
+
+use mio::{Events, Interest, Poll, Token, Waker};
+use mio::net::TcpListener;
+
+use crate::h2::{self, H2Connection, H2Response};
+use crate::http::upgraded_stream::Upgrade;
+use crate::http::{FileBody, HttpRequest, HttpResponse, HttpStatus, UpgradedStream};
+use crate::route::Router;
+use crate::route::router::RouterExecution;
+use crate::rt::Executor;
+use crate::server::{ServerConfig, ShutdownHandle, TlsAcceptor};
+use crate::server::reactor_stream::ReactorStream;
+use crate::worker::Task;
+use crate::worker::request_parser::{
+    self, can_pipeline_after, is_keep_alive_requested, RequestParser,
+};
+use crate::worker::worker_manager::{Message, WorkerManager};
+
+const LISTENER: Token = Token(0);
+const WAKER: Token = Token(1);
+const FIRST_CONNECTION: usize = 2;
+// how often the timeouts and the shutdown are checked
+const TICK: Duration = Duration::from_millis(100);
+const READ_BUFFER_SIZE: usize = 8 * 1024;
+// the frames of HTTP/2 left to write, over which the connection is not read any more.
+// e.g. a client which sends PING but does not read the answers can not pile them up.
+const MAX_H2_OUTPUT_SIZE: usize = 256 * 1024;
+// a connection which keeps sending gives the others a turn after these reads
+const MAX_READS_PER_WAKEUP: usize = 16;
+
+// a response made by a worker, which is written by the reactor
+enum Completion {
+    Http1 {
+        token: Token,
+        // the order of the request on the connection
+        sequence: u64,
+        response: Vec<u8>,
+        // sent with sendfile(2) after the response
+        file_body: Option<FileBody>,
+        keep_alive: bool,
+        // the connection is handed to a worker after the response
+        upgrade: Option<Upgrade>,
+    },
+    Http2 {
+        token: Token,
+        stream_id: u32,
+        h2_response: H2Response,
+    },
+}
+
+enum ConnectionState {
+    // waiting for the next request
+    Idle { since: Instant },
+    // a part of the request has arrived
+    Reading { deadline: Instant, is_reading_body: bool },
+    // workers are executing the handlers, the response of the first request is waited for
+    Dispatched,
+    Writing {
+        response: Vec<u8>,
+        offset: usize,
+        file_body: Option<FileBody>,
+        file_sent: u64,
+        keep_alive: bool,
+        upgrade: Option<Upgrade>,
+        deadline: Instant,
+    },
+    // the streams of HTTP/2 are served, each request is dispatched as soon as it arrives
+    Http2 {
+        h2_connection: Box<H2Connection>,
+        output: Vec<u8>,
+        offset: usize,
+        // the write timeout while the output is left
+        deadline: Instant,
+        // the keep-alive timeout while no stream is open
+        idle_since: Instant,
+        // the reading is stopped until the output goes under 'MAX_H2_OUTPUT_SIZE'
+        is_read_paused: bool,
+    },
+    Closed,
+}
+
+struct Connection {
+    stream: ReactorStream,
+    request_parser: RequestParser,
+    state: ConnectionState,
+    // the client has closed its side, no more requests will arrive
+    is_read_closed: bool,
+    // the responses of the dispatched requests in the order of the requests, none until the
+    // handler completes. the front is the next response to write.
+    pipeline: VecDeque<Option<Completion>>,
+    // the sequence of the front of 'pipeline'
+    first_sequence: u64,
+    // the last dispatched request must be answered before the next one is handled, e.g. POST
+    is_pipeline_blocked: bool,
+    // taken when an upgrade is answered, and kept until the upgraded connection ends
+    upgrade_permit: Option<UpgradePermit>,
+}
+
+impl Connection {
+    // a slot for the response of the next request, returns its sequence
+    fn push_pipeline(&mut self) -> u64 {
+        self.pipeline.push_back(None);
+        self.first_sequence + self.pipeline.len() as u64 - 1
+    }
+}
+
+// multiplexes connections on a thread, and hands only complete requests to the workers.
+// a slow client costs a buffer instead of a worker thread, and an async handler costs a future.
+pub(crate) struct Reactor<'a> {
+    id: u16,
+    poll: Poll,
+    listener: Option<TcpListener>,
+    waker: Arc<Waker>,
+    connections: HashMap<Token, Connection>,
+    next_token: usize,
+    router: Arc<Router>,
+    server_config: Arc<ServerConfig>,
+    shutdown_handle: ShutdownHandle,
+    worker_manager: &'a WorkerManager,
+    executor: Arc<Executor>,
+    tls_acceptor: Option<TlsAcceptor>,
+    completion_sender: Sender<Completion>,
+    completion_receiver: Receiver<Completion>,
+    shutdown_since: Option<Instant>,
+    // the upgraded connections of every reactor
+    upgraded_count: Arc<AtomicUsize>,
+    // the connections which have stopped reading with bytes left, the readiness is not notified
+    // again for them, so they are read on the next turn
+    unread: Vec<Token>,
+}
+
+impl<'a> Reactor<'a> {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        id: u16,
+        listener: std::net::TcpListener,
+        router: Arc<Router>,
+        server_config: Arc<ServerConfig>,
+        shutdown_handle: ShutdownHandle,
+        worker_manager: &'a WorkerManager,
+        executor: Arc<Executor>,
+        tls_acceptor: Option<TlsAcceptor>,
+        upgraded_count: Arc<AtomicUsize>,
+    ) -> Result<Reactor<'a>, Error> {
+        let poll = Poll::new()?;
+        let waker = Arc::new(Waker::new(poll.registry(), WAKER)?);
+
+        listener.set_nonblocking(true)?;
+        let mut listener = TcpListener::from_std(listener);
+        poll.registry()
+            .register(&mut listener, LISTENER, Interest::READABLE)?;
+
+        let (completion_sender, completion_receiver) = mpsc::channel();
+
+        Ok(Reactor {
+            id,
+            poll,
+            listener: Some(listener),
+            waker,
+            connections: HashMap::new(),
+            next_token: FIRST_CONNECTION,
+            router,
+            server_config,
+            shutdown_handle,
+            worker_manager,
+            executor,
+            tls_acceptor,
+            completion_sender,
+            completion_receiver,
+            shutdown_since: None,
+            upgraded_count,
+            unread: vec![],
+        })
+    }
+
+    // serve until the shutdown is requested and the in-flight requests are answered
+    pub fn run(mut self) {
+        let mut events = Events::with_capacity(1024);
+
+        loop {
+            if self.shutdown_handle.is_shutdown() && self.shutdown_since.is_none() {
+                self.stop_accepting();
+            }
+
+            if let Some(shutdown_since) = self.shutdown_since {
+                if self.connections.is_empty() {
+                    break;
+                }
+                if shutdown_since.elapsed() >= self.server_config.shutdown_timeout {
+                    eprintln!(
+                        "[error] reactor {} leaves {} connections behind",
+                        self.id,
+                        self.connections.len()
+                    );
+                    break;
+                }
+            }
+
+            let timeout = if self.unread.is_empty() { TICK } else { Duration::ZERO };
+            if let Err(error) = self.poll.poll(&mut events, Some(timeout)) {
+                if error.kind() == ErrorKind::Interrupted {
+                    continue;
+                }
+                eprintln!("[error] reactor {} fails to poll: {}", self.id, error);
+                break;
+            }
+
+            for event in events.iter() {
+                match event.token() {
+                    LISTENER => self.accept_connections(),
+                    // completions are handled below
+                    WAKER => {}
+                    token => {
+                        if event.is_writable() {
+                            self.write_response(token);
+                        }
+                        if event.is_readable() || event.is_read_closed() {
+                            self.read_request(token);
+                        }
+                    }
+                }
+            }
+
+            for token in std::mem::take(&mut self.unread) {
+                self.read_request(token);
+            }
+
+            while let Ok(completion) = self.completion_receiver.try_recv() {
+                self.start_writing(completion);
+            }
+
+            self.check_timeouts();
+            self.connections
+                .retain(|_, connection| !matches!(connection.state, ConnectionState::Closed));
+        }
+    }
+
+    fn stop_accepting(&mut self) {
+        println!("reactor {} stops accepting", self.id);
+        self.shutdown_since = Some(Instant::now());
+
+        if let Some(mut listener) = self.listener.take() {
+            self.poll.registry().deregister(&mut listener).ok();
+        }
+    }
+
+    fn accept_connections(&mut self) {
+        loop {
+            let listener = if let Some(listener) = &self.listener {
+                listener
+            } else {
+                return;
+            };
+
+            if self.shutdown_handle.is_shutdown() {
+                return;
+            }
+
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    eprintln!("[error] reactor {} fails to accept: {}", self.id, error);
+                    return;
+                }
+            };
+
+            let mut stream = match ReactorStream::new(stream, self.tls_acceptor.as_ref()) {
+                Ok(stream) => stream,
+                Err(error) => {
+                    eprintln!("[error] reactor {} fails to start a connection: {}", self.id, error);
+                    continue;
+                }
+            };
+
+            let token = Token(self.next_token);
+            self.next_token += 1;
+
+            if let Err(error) = self.poll.registry().register(
+                stream.get_socket_mut(),
+                token,
+                Interest::READABLE | Interest::WRITABLE,
+            ) {
+                eprintln!("[error] reactor {} fails to register a connection: {}", self.id, error);
+                continue;
+            }
+
+            self.connections.insert(
+                token,
+                Connection {
+                    stream,
+                    request_parser: RequestParser::new(
+                        self.server_config.clone(),
+                        self.router.clone(),
+                    ),
+                    state: ConnectionState::Idle { since: Instant::now() },
+                    is_read_closed: false,
+                    pipeline: VecDeque::new(),
+                    first_sequence: 0,
+                    is_pipeline_blocked: false,
+                    upgrade_permit: None,
+                },
+            );
+        }
+    }
+
+    fn read_request(&mut self, token: Token) {
+        loop {
+            let connection = if let Some(connection) = self.connections.get_mut(&token) {
+                connection
+            } else {
+                return;
+            };
+
+            // the next request waits in the socket until the current one is answered
+            if !matches!(
+                connection.state,
+                ConnectionState::Idle { .. }
+                    | ConnectionState::Reading { .. }
+                    | ConnectionState::Http2 { .. }
+            ) {
+                return;
+            }
+
+            let mut buffer = [0_u8; READ_BUFFER_SIZE];
+            for read_count in 0.. {
+                if read_count == MAX_READS_PER_WAKEUP {
+                    self.unread.push(token);
+                    break;
+                }
+
+                if let ConnectionState::Http2 {
+                    h2_connection,
+                    output,
+                    offset,
+                    is_read_paused,
+                    ..
+                } = &mut connection.state
+                {
+                    if output.len() - *offset + h2_connection.get_output_size() > MAX_H2_OUTPUT_SIZE {
+                        *is_read_paused = true;
+                        break;
+                    }
+                }
+
+                match connection.stream.read(&mut buffer) {
+                    Ok(0) => {
+                        connection.is_read_closed = true;
+                        break;
+                    }
+                    Ok(nbytes) => match &mut connection.state {
+                        ConnectionState::Http2 { h2_connection, .. } => {
+                            h2_connection.push(&buffer[..nbytes])
+                        }
+                        _ => {
+                            connection.request_parser.push(&buffer[..nbytes]);
+                            // the frames after the preface are read under the limit of HTTP/2
+                            if self.server_config.http2
+                                && connection.request_parser.is_http2_preface()
+                            {
+                                break;
+                            }
+                            // the limits are checked by parsing before more bytes are buffered
+                            if connection.request_parser.is_full() {
+                                self.unread.push(token);
+                                break;
+                            }
+                        }
+                    },
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                    Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        connection.state = ConnectionState::Closed;
+                        return;
+                    }
+                }
+            }
+
+            if let ConnectionState::Http2 { .. } = connection.state {
+                self.serve_http2(token);
+                // the output has been written at once, so the rest of the input is read
+                if self.resume_reading(token) {
+                    continue;
+                }
+            } else {
+                self.parse_request(token);
+                // the rest of the input is left in the socket for HTTP/2
+                if let Some(Connection {
+                    state: ConnectionState::Http2 { .. },
+                    ..
+                }) = self.connections.get(&token)
+                {
+                    continue;
+                }
+            }
+            return;
+        }
+    }
+
+    // the reading of HTTP/2 which has been paused can go on, the readiness is not notified again
+    fn resume_reading(&mut self, token: Token) -> bool {
+        if let Some(Connection {
+            state:
+                ConnectionState::Http2 {
+                    h2_connection,
+                    output,
+                    offset,
+                    is_read_paused,
+                    ..
+                },
+            ..
+        }) = self.connections.get_mut(&token)
+        {
+            if *is_read_paused
+                && output.len() - *offset + h2_connection.get_output_size() <= MAX_H2_OUTPUT_SIZE
+            {
+                *is_read_paused = false;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn parse_request(&mut self, token: Token) {
+        let connection = if let Some(connection) = self.connections.get_mut(&token) {
+            connection
+        } else {
+            return;
+        };
+
+        if let ConnectionState::Idle { .. } = connection.state {
+            if !connection.request_parser.has_buffered_data() {
+                if connection.is_read_closed {
+                    connection.state = ConnectionState::Closed;
+                }
+                return;
+            }
+
+            // a client which trickles bytes can not hold the connection longer than the timeout
+            connection.state = ConnectionState::Reading {
+                deadline: Instant::now() + self.server_config.header_read_timeout,
+                is_reading_body: false,
+            };
+        }
+
+        // the client has started HTTP/2 with the prior knowledge
+        if self.server_config.http2 && connection.request_parser.is_http2_preface() {
+            let h2_connection = H2Connection::new(self.server_config.clone(), self.router.clone());
+            self.start_http2(token, h2_connection, vec![]);
+            return;
+        }
+
+        match connection.request_parser.parse() {
+            Ok(Some(http_request)) => {
+                // TLS negotiates HTTP/2 with ALPN instead
+                let h2_connection = if connection.stream.is_tls() {
+                    None
+                } else {
+                    H2Connection::upgrade(
+                        self.server_config.clone(),
+                        self.router.clone(),
+                        &http_request,
+                    )
+                };
+
+                if let Some(mut h2_connection) = h2_connection {
+                    // the request is answered on the first stream
+                    h2_connection.open_upgraded_stream(http_request);
+                    self.start_http2(token, h2_connection, h2::SWITCHING_TO_H2C.to_vec());
+                } else {
+                    self.dispatch(token, None, http_request);
+                    self.dispatch_pipelined(token);
+                }
+            }
+            Ok(None) => {
+                if connection.is_read_closed {
+                    connection.state = ConnectionState::Closed;
+                    return;
+                }
+
+                // the client waits until the request is accepted by its header
+                if let Some(mut http_request) = connection.request_parser.take_expectation() {
+                    http_request.set_tls_info(connection.stream.get_tls_info());
+                    if let Some(http_response) = self.router.check_continue(&http_request) {
+                        // the body is never read, so the connection is closed after the response
+                        let sequence = connection.push_pipeline();
+                        self.start_writing(make_completion(token, None, sequence, http_response, false));
+                        return;
+                    }
+                    write_continue(&mut connection.stream);
+                }
+
+                // the header has arrived, the body has its own deadline
+                if let ConnectionState::Reading { is_reading_body: false, .. } = connection.state {
+                    if connection.request_parser.is_reading_body() {
+                        connection.state = ConnectionState::Reading {
+                            deadline: Instant::now() + self.server_config.body_read_timeout,
+                            is_reading_body: true,
+                        };
+                    }
+                }
+            }
+            Err(error) => {
+                let sequence = connection.push_pipeline();
+                let http_response = HttpResponse::new_with(error.get_status());
+                self.start_writing(make_completion(token, None, sequence, http_response, false));
+            }
+        }
+    }
+
+    // the complete requests which have arrived behind the dispatched one are handled at the same
+    // time, the bytes of the others wait until the connection is idle again
+    fn dispatch_pipelined(&mut self, token: Token) {
+        loop {
+            let connection = if let Some(connection) = self.connections.get_mut(&token) {
+                connection
+            } else {
+                return;
+            };
+
+            if !matches!(
+                connection.state,
+                ConnectionState::Dispatched | ConnectionState::Writing { .. }
+            ) || connection.is_pipeline_blocked
+                || connection.pipeline.len() >= self.server_config.max_pipelined_requests
+                || !connection.request_parser.is_next_request_safe()
+            {
+                return;
+            }
+
+            match connection.request_parser.parse() {
+                Ok(Some(http_request)) => self.dispatch(token, None, http_request),
+                Ok(None) => return,
+                Err(error) => {
+                    // answered after the requests before it, then the connection is closed
+                    connection.is_pipeline_blocked = true;
+                    let sequence = connection.push_pipeline();
+                    let http_response = HttpResponse::new_with(error.get_status());
+                    self.start_writing(make_completion(token, None, sequence, http_response, false));
+                    return;
+                }
+            }
+        }
+    }
+
+    // 'output' is written before the frames, e.g. the response to 'Upgrade: h2c'
+    fn start_http2(&mut self, token: Token, mut h2_connection: H2Connection, output: Vec<u8>) {
+        let connection = if let Some(connection) = self.connections.get_mut(&token) {
+            connection
+        } else {
+            return;
+        };
+
+        h2_connection.push(&connection.request_parser.take_buffer());
+        connection.state = ConnectionState::Http2 {
+            h2_connection: Box::new(h2_connection),
+            output,
+            offset: 0,
+            deadline: Instant::now() + self.server_config.write_timeout,
+            idle_since: Instant::now(),
+            is_read_paused: false,
+        };
+
+        self.serve_http2(token);
+    }
+
+    // dispatch the requests which have arrived, and write the frames
+    fn serve_http2(&mut self, token: Token) {
+        let http_requests = match self.connections.get_mut(&token) {
+            Some(Connection {
+                state: ConnectionState::Http2 { h2_connection, .. },
+                ..
+            }) => h2_connection.take_requests(),
+            _ => return,
+        };
+
+        for (stream_id, http_request) in http_requests {
+            self.dispatch(token, Some(stream_id), http_request);
+        }
+
+        self.write_http2(token);
+    }
+
+    fn write_http2(&mut self, token: Token) {
+        let connection = if let Some(connection) = self.connections.get_mut(&token) {
+            connection
+        } else {
+            return;
+        };
+
+        let is_finished = if let ConnectionState::Http2 {
+            h2_connection,
+            output,
+            offset,
+            deadline,
+            ..
+        } = &mut connection.state
+        {
+            loop {
+                if *offset >= output.len() {
+                    *output = h2_connection.poll_output();
+                    *offset = 0;
+                    *deadline = Instant::now() + self.server_config.write_timeout;
+                    if output.is_empty() {
+                        break;
+                    }
+                }
+
+                match connection.stream.write(&output[*offset..]) {
+                    Ok(0) => {
+                        connection.state = ConnectionState::Closed;
+                        return;
+                    }
+                    Ok(nbytes) => {
+                        *offset += nbytes;
+                        *deadline = Instant::now() + self.server_config.write_timeout;
+                    }
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                    Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        connection.state = ConnectionState::Closed;
+                        return;
+                    }
+                }
+            }
+
+            match connection.stream.flush() {
+                Ok(()) => {}
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                Err(_) => {
+                    connection.state = ConnectionState::Closed;
+                    return;
+                }
+            }
+
+            // the client which has closed its side can not receive the rest of the streams
+            h2_connection.is_finished() || (connection.is_read_closed && h2_connection.is_idle())
+        } else {
+            return;
+        };
+
+        if is_finished {
+            close_gracefully(connection);
+        }
+    }
+
+    // 'stream_id' is some for a request of HTTP/2, whose connection keeps reading other streams
+    fn dispatch(&mut self, token: Token, stream_id: Option<u32>, mut http_request: HttpRequest) {
+        let mut sequence = 0;
+        if let Some(connection) = self.connections.get_mut(&token) {
+            if stream_id.is_none() {
+                sequence = connection.push_pipeline();
+                connection.is_pipeline_blocked = !can_pipeline_after(&http_request);
+                // a pipelined request leaves the state of the first one
+                if let ConnectionState::Idle { .. } | ConnectionState::Reading { .. } = connection.state {
+                    connection.state = ConnectionState::Dispatched;
+                }
+            }
+            http_request.set_tls_info(connection.stream.get_tls_info());
+        }
+
+        let responder = Responder {
+            token,
+            stream_id,
+            sequence,
+            keep_alive: is_keep_alive_requested(&http_request),
+            shutdown_handle: self.shutdown_handle.clone(),
+            completion_sender: self.completion_sender.clone(),
+            waker: self.waker.clone(),
+            is_completed: false,
+        };
+        let reactor_task = ReactorTask {
+            http_request: Some(http_request),
+            responder: Some(responder),
+            router: self.router.clone(),
+            server_config: self.server_config.clone(),
+            executor: self.executor.clone(),
+        };
+
+        match self.worker_manager.request(Box::new(reactor_task)) {
+            Ok(()) => {}
+            // shed the load, the task answers '503 Service Unavailable'
+            Err(TrySendError::Full(Message::Job(mut task))) => {
+                eprintln!("[error] the job queue is full, reject a request");
+                task.on_rejected();
+            }
+            Err(error) => {
+                eprintln!("[error] fail to request a task: {}", error);
+                if let Some(connection) = self.connections.get_mut(&token) {
+                    connection.state = ConnectionState::Closed;
+                }
+            }
+        }
+    }
+
+    fn start_writing(&mut self, completion: Completion) {
+        let completion = self.limit_upgrade(completion);
+        match completion {
+            Completion::Http1 { token, sequence, .. } => {
+                if let Some(connection) = self.connections.get_mut(&token) {
+                    // the response of a request after a closed one may come late
+                    let index = sequence.wrapping_sub(connection.first_sequence) as usize;
+                    if let Some(slot) = connection.pipeline.get_mut(index) {
+                        *slot = Some(completion);
+                    }
+                }
+
+                self.write_next(token);
+            }
+            Completion::Http2 {
+                token,
+                stream_id,
+                h2_response,
+            } => {
+                if let Some(Connection {
+                    state: ConnectionState::Http2 { h2_connection, .. },
+                    ..
+                }) = self.connections.get_mut(&token)
+                {
+                    h2_connection.respond(stream_id, h2_response);
+                }
+
+                self.write_http2(token);
+                if self.resume_reading(token) {
+                    self.read_request(token);
+                }
+            }
+        }
+    }
+
+    // an upgraded connection holds a worker until its callback returns, so the upgrades above
+    // 'max_upgraded_connections' are answered with '503 Service Unavailable'
+    fn limit_upgrade(&mut self, completion: Completion) -> Completion {
+        let (token, sequence) = match &completion {
+            Completion::Http1 {
+                token,
+                sequence,
+                upgrade: Some(_),
+                ..
+            } => (*token, *sequence),
+            _ => return completion,
+        };
+        let connection = if let Some(connection) = self.connections.get_mut(&token) {
+            connection
+        } else {
+            return completion;
+        };
+
+        if connection.upgrade_permit.is_none() {
+            connection.upgrade_permit = UpgradePermit::acquire(
+                &self.upgraded_count,
+                self.server_config.max_upgraded_connections,
+            );
+        }
+        if connection.upgrade_permit.is_some() {
+            return completion;
+        }
+
+        eprintln!("[error] too many upgraded connections, reject an upgrade");
+        make_completion(token, None, sequence, make_unavailable_response(&self.server_config), false)
+    }
+
+    // the responses are written in the order of the requests
+    fn write_next(&mut self, token: Token) {
+        let connection = if let Some(connection) = self.connections.get_mut(&token) {
+            connection
+        } else {
+            return;
+        };
+
+        if matches!(
+            connection.state,
+            ConnectionState::Writing { .. } | ConnectionState::Http2 { .. } | ConnectionState::Closed
+        ) || !matches!(connection.pipeline.front(), Some(Some(_)))
+        {
+            return;
+        }
+
+        if let Some(Some(Completion::Http1 {
+            response,
+            file_body,
+            keep_alive,
+            upgrade,
+            ..
+        })) = connection.pipeline.pop_front()
+        {
+            connection.first_sequence += 1;
+            connection.state = ConnectionState::Writing {
+                response,
+                offset: 0,
+                file_body,
+                file_sent: 0,
+                keep_alive,
+                upgrade,
+                deadline: Instant::now() + self.server_config.write_timeout,
+            };
+        }
+
+        self.write_response(token);
+    }
+
+    fn write_response(&mut self, token: Token) {
+        let connection = if let Some(connection) = self.connections.get_mut(&token) {
+            connection
+        } else {
+            return;
+        };
+
+        if let ConnectionState::Http2 { .. } = connection.state {
+            self.write_http2(token);
+            if self.resume_reading(token) {
+                self.read_request(token);
+            }
+            return;
+        }
+
+        let (keep_alive, upgrade) = if let ConnectionState::Writing {
+            response,
+            offset,
+            file_body,
+            file_sent,
+            keep_alive,
+            upgrade,
+            deadline,
+        } = &mut connection.state
+        {
+            while *offset < response.len() {
+                match connection.stream.write(&response[*offset..]) {
+                    Ok(0) => {
+                        connection.state = ConnectionState::Closed;
+                        return;
+                    }
+                    Ok(nbytes) => {
+                        *offset += nbytes;
+                        // a client which reads slowly is given the timeout for each progress
+                        *deadline = Instant::now() + self.server_config.write_timeout;
+                    }
+                    Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                    Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                    Err(_) => {
+                        connection.state = ConnectionState::Closed;
+                        return;
+                    }
+                }
+            }
+
+            if let Some(file_body) = file_body {
+                while *file_sent < file_body.get_length() {
+                    match connection.stream.send_file(file_body, *file_sent) {
+                        Ok(nbytes) => {
+                            *file_sent += nbytes as u64;
+                            *deadline = Instant::now() + self.server_config.write_timeout;
+                        }
+                        Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                        Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                        Err(_) => {
+                            connection.state = ConnectionState::Closed;
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // the encrypted records may be left
+            match connection.stream.flush() {
+                Ok(()) => {}
+                Err(error) if error.kind() == ErrorKind::WouldBlock => return,
+                Err(_) => {
+                    connection.state = ConnectionState::Closed;
+                    return;
+                }
+            }
+
+            (*keep_alive, upgrade.take())
+        } else {
+            // e.g. the TLS handshake messages which did not fit in the socket
+            if let Err(error) = connection.stream.flush() {
+                if error.kind() != ErrorKind::WouldBlock {
+                    connection.state = ConnectionState::Closed;
+                }
+            }
+            return;
+        };
+
+        if let Some(upgrade) = upgrade {
+            self.upgrade_connection(token, upgrade);
+        } else if !keep_alive {
+            close_gracefully(connection);
+        } else if !connection.pipeline.is_empty() {
+            connection.state = ConnectionState::Dispatched;
+            self.dispatch_pipelined(token);
+            self.write_next(token);
+        } else if connection.is_read_closed && !connection.request_parser.has_buffered_data() {
+            close_gracefully(connection);
+        } else {
+            connection.state = ConnectionState::Idle { since: Instant::now() };
+            // the next request may have arrived while this one was handled
+            self.read_request(token);
+        }
+    }
+
+    // the connection leaves the reactor, and is served by a worker until the callback returns
+    fn upgrade_connection(&mut self, token: Token, upgrade: Upgrade) {
+        let mut connection = if let Some(connection) = self.connections.remove(&token) {
+            connection
+        } else {
+            return;
+        };
+
+        self.poll.registry().deregister(connection.stream.get_socket_mut()).ok();
+        let buffered = connection.request_parser.take_buffer();
+        let stream = match connection.stream.into_connection_stream() {
+            Ok(stream) => stream,
+            Err(error) => {
+                eprintln!("[error] reactor {} fails to take over a connection: {}", self.id, error);
+                return;
+            }
+        };
+        if let Err(error) = stream.get_socket().set_write_timeout(Some(self.server_config.write_timeout)) {
+            eprintln!("[error] reactor {} fails to take over a connection: {}", self.id, error);
+            return;
+        }
+
+        let upgrade_task = UpgradeTask {
+            upgrade: Some(upgrade),
+            _permit: connection.upgrade_permit.take(),
+            stream: Some(UpgradedStream::new(stream, buffered, self.shutdown_handle.clone())),
+        };
+        match self.worker_manager.request(Box::new(upgrade_task)) {
+            Ok(()) => {}
+            Err(TrySendError::Full(Message::Job(mut task))) => {
+                eprintln!("[error] the job queue is full, close an upgraded connection");
+                task.on_rejected();
+            }
+            Err(error) => eprintln!("[error] fail to request a task: {}", error),
+        }
+    }
+
+    fn check_timeouts(&mut self) {
+        let now = Instant::now();
+        let is_shutdown = self.shutdown_since.is_some();
+        let mut timed_out = vec![];
+        let mut going_away = vec![];
+
+        for (token, connection) in self.connections.iter_mut() {
+            match connection.state {
+                ConnectionState::Idle { since }
+                if is_shutdown || now >= since + self.server_config.keep_alive_timeout =>
+                {
+                    connection.state = ConnectionState::Closed;
+                }
+                ConnectionState::Reading { deadline, .. } if now >= deadline => {
+                    timed_out.push(*token);
+                }
+                ConnectionState::Writing { deadline, .. } if now >= deadline => {
+                    connection.state = ConnectionState::Closed;
+                }
+                ConnectionState::Http2 {
+                    ref mut h2_connection,
+                    ref output,
+                    offset,
+                    deadline,
+                    ref mut idle_since,
+                    ..
+                } => {
+                    if offset < output.len() && now >= deadline {
+                        connection.state = ConnectionState::Closed;
+                        continue;
+                    }
+
+                    if !h2_connection.is_idle() {
+                        *idle_since = now;
+                    }
+                    // the streams which have started are still answered
+                    if is_shutdown || now >= *idle_since + self.server_config.keep_alive_timeout {
+                        h2_connection.go_away();
+                        going_away.push(*token);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for token in timed_out {
+            let sequence = match self.connections.get_mut(&token) {
+                Some(connection) => connection.push_pipeline(),
+                None => continue,
+            };
+            let http_response = HttpResponse::new_with(HttpStatus::REQUEST_TIMEOUT);
+            self.start_writing(make_completion(token, None, sequence, http_response, false));
+        }
+        for token in going_away {
+            self.write_http2(token);
+        }
+    }
+}
+
+fn close_gracefully(connection: &mut Connection) {
+    connection.stream.close_gracefully();
+    connection.state = ConnectionState::Closed;
+}
+
+// the interim response is small enough for the socket buffer of an idle connection.
+// if it can not be written, the client sends the body after waiting for a while anyway.
+fn write_continue(stream: &mut ReactorStream) {
+    let mut offset = 0;
+    while offset < request_parser::CONTINUE.len() {
+        match stream.write(&request_parser::CONTINUE[offset..]) {
+            Ok(0) => return,
+            Ok(nbytes) => offset += nbytes,
+            Err(error) if error.kind() == ErrorKind::Interrupted => {}
+            Err(_) => return,
+        }
+    }
+    stream.flush().ok();
+}
+
+fn make_completion(
+    token: Token,
+    stream_id: Option<u32>,
+    sequence: u64,
+    mut http_response: HttpResponse,
+    keep_alive: bool,
+) -> Completion {
+    if let Some(stream_id) = stream_id {
+        let h2_response = H2Response::new(&mut http_response).unwrap_or_else(|error| {
+            eprintln!("[error] error while respond: {:?}", error);
+            H2Response::new_with(HttpStatus::INTERNAL_SERVER_ERROR)
+        });
+        return Completion::Http2 {
+            token,
+            stream_id,
+            h2_response,
+        };
+    }
+
+    http_response.set_keep_alive(keep_alive);
+
+    let (response, file_body) = match http_response.take_output() {
+        Ok(output) => output,
+        Err(error) => {
+            eprintln!("[error] error while respond: {:?}", error);
+            // nothing has been written yet, so the connection is simply closed
+            return Completion::Http1 {
+                token,
+                sequence,
+                response: vec![],
+                file_body: None,
+                keep_alive: false,
+                upgrade: None,
+            };
+        }
+    };
+
+    Completion::Http1 {
+        token,
+        sequence,
+        response,
+        file_body,
+        keep_alive,
+        upgrade: http_response.take_upgrade(),
+    }
+}
+
+// sends the response of a request back to the reactor.
+// '500 Internal Server Error' is sent if it is dropped without a response, e.g. by a panic.
+struct Responder {
+    token: Token,
+    stream_id: Option<u32>,
+    sequence: u64,
+    keep_alive: bool,
+    shutdown_handle: ShutdownHandle,
+    completion_sender: Sender<Completion>,
+    waker: Arc<Waker>,
+    is_completed: bool,
+}
+
+impl Responder {
+    fn respond(mut self, http_response: HttpResponse) {
+        // the server which is shutting down closes connections after the response
+        let keep_alive = self.keep_alive && !self.shutdown_handle.is_shutdown();
+        self.complete(http_response, keep_alive);
+    }
+
+    fn complete(&mut self, http_response: HttpResponse, keep_alive: bool) {
+        self.is_completed = true;
+
+        // the reactor may have stopped already
+        if self
+            .completion_sender
+            .send(make_completion(
+                self.token,
+                self.stream_id,
+                self.sequence,
+                http_response,
+                keep_alive,
+            ))
+            .is_ok()
+        {
+            self.waker.wake().ok();
+        }
+    }
+}
+
+impl Drop for Responder {
+    fn drop(&mut self) {
+        if !self.is_completed {
+            self.complete(HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR), false);
+        }
+    }
+}
+
+// executes the handler on a worker, an async handler is continued on the executor
+struct ReactorTask {
+    http_request: Option<HttpRequest>,
+    responder: Option<Responder>,
+    router: Arc<Router>,
+    server_config: Arc<ServerConfig>,
+    executor: Arc<Executor>,
+}
+
+impl Task for ReactorTask {
+    fn execute(&mut self) {
+        let (http_request, responder) = match (self.http_request.take(), self.responder.take()) {
+            (Some(http_request), Some(responder)) => (http_request, responder),
+            _ => return,
+        };
+
+        match self.router.start_route(http_request) {
+            RouterExecution::Ready(http_response) => responder.respond(http_response),
+            // the worker is free while the handler waits
+            RouterExecution::Pending(future) => self.executor.spawn(async move {
+                responder.respond(future.await);
+            }),
+        }
+    }
+
+    fn on_rejected(&mut self) {
+        let http_response = make_unavailable_response(&self.server_config);
+
+        if let Some(mut responder) = self.responder.take() {
+            responder.complete(http_response, false);
+        }
+    }
+}
+
+fn make_unavailable_response(server_config: &ServerConfig) -> HttpResponse {
+    let mut http_response = HttpResponse::new_with(HttpStatus::SERVICE_UNAVAILABLE);
+    http_response.append_header("Retry-After", &server_config.retry_after.as_secs().to_string());
+
+    http_response
+}
+
+// a place among 'max_upgraded_connections', given back when it is dropped
+struct UpgradePermit {
+    upgraded_count: Arc<AtomicUsize>,
+}
+
+impl UpgradePermit {
+    fn acquire(upgraded_count: &Arc<AtomicUsize>, max_upgraded_connections: usize) -> Option<Self> {
+        upgraded_count
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                if count < max_upgraded_connections {
+                    Some(count + 1)
+                } else {
+                    None
+                }
+            })
+            .ok()?;
+
+        Some(UpgradePermit {
+            upgraded_count: Arc::clone(upgraded_count),
+        })
+    }
+}
+
+impl Drop for UpgradePermit {
+    fn drop(&mut self) {
+        self.upgraded_count.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// runs the callback of an upgraded connection on a worker
+struct UpgradeTask {
+    upgrade: Option<Upgrade>,
+    stream: Option<UpgradedStream>,
+    // dropped with the task, after the callback has returned or the task has been rejected
+    _permit: Option<UpgradePermit>,
+}
+
+impl Task for UpgradeTask {
+    fn execute(&mut self) {
+        if let (Some(upgrade), Some(stream)) = (self.upgrade.take(), self.stream.take()) {
+            upgrade(stream);
+        }
+    }
+
+    // the job queue is full, the connection is just closed
+    fn on_rejected(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            stream.close();
+        }
+    }
+}
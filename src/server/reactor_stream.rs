@@ -0,0 +1,192 @@
+use std::io::{self, Read, Write};
+#[cfg(feature = "tls")]
+use std::io::ErrorKind;
+use std::net::Shutdown;
+
+use mio::net::TcpStream;
+#[cfg(feature = "tls")]
+use rustls::{ServerConnection, StreamOwned};
+
+use crate::http::{FileBody, TlsInfo};
+use crate::server::TlsAcceptor;
+use crate::worker::ConnectionStream;
+#[cfg(feature = "tls")]
+use crate::server::get_tls_info;
+
+// the non-blocking socket of a connection in the reactor, which may be encrypted by TLS.
+// a read or a write fails with 'WouldBlock' until the socket is ready, even in the TLS handshake.
+pub(crate) struct ReactorStream {
+    socket: TcpStream,
+    #[cfg(feature = "tls")]
+    tls: Option<Box<ServerConnection>>,
+}
+
+impl ReactorStream {
+    pub fn new(socket: TcpStream, tls_acceptor: Option<&TlsAcceptor>) -> io::Result<Self> {
+        #[cfg(feature = "tls")]
+        let tls = match tls_acceptor {
+            Some(tls_acceptor) => Some(Box::new(tls_acceptor.accept()?)),
+            None => None,
+        };
+        #[cfg(not(feature = "tls"))]
+        if let Some(tls_acceptor) = tls_acceptor {
+            match *tls_acceptor {}
+        }
+
+        Ok(ReactorStream {
+            socket,
+            #[cfg(feature = "tls")]
+            tls,
+        })
+    }
+
+    // to register the socket to the poll
+    pub fn get_socket_mut(&mut self) -> &mut TcpStream {
+        &mut self.socket
+    }
+
+    pub fn is_tls(&self) -> bool {
+        #[cfg(feature = "tls")]
+        return self.tls.is_some();
+        #[cfg(not(feature = "tls"))]
+        false
+    }
+
+    pub fn get_tls_info(&self) -> Option<TlsInfo> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &self.tls {
+            return get_tls_info(tls);
+        }
+
+        None
+    }
+
+    // returns the number of bytes of the file body which are sent by this call.
+    // the file is sent with sendfile(2) over plain TCP, TLS has to encrypt it.
+    pub fn send_file(&mut self, file_body: &FileBody, sent: u64) -> io::Result<usize> {
+        #[cfg(feature = "tls")]
+        if self.tls.is_some() {
+            let mut buffer = vec![0_u8; 16 * 1024];
+            let nbytes = file_body.read_chunk(sent, &mut buffer)?;
+            return self.write(&buffer[..nbytes]);
+        }
+
+        file_body.send_to(&mut self.socket, sent)
+    }
+
+    // a blocking stream for a worker, e.g. after the connection is upgraded.
+    // the socket must have been deregistered from the poll.
+    pub fn into_connection_stream(self) -> io::Result<ConnectionStream> {
+        let socket = std::net::TcpStream::from(self.socket);
+        socket.set_nonblocking(false)?;
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = self.tls {
+            return Ok(ConnectionStream::Tls(Box::new(StreamOwned::new(*tls, socket))));
+        }
+
+        Ok(ConnectionStream::Plain(socket))
+    }
+
+    // closing with unread data resets the connection, which may discard the response
+    pub fn close_gracefully(&mut self) {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &mut self.tls {
+            tls.send_close_notify();
+            self.flush().ok();
+        }
+
+        self.socket.shutdown(Shutdown::Write).ok();
+
+        let mut buffer = [0_u8; 1024];
+        while let Ok(nbytes) = self.socket.read(&mut buffer) {
+            if nbytes == 0 {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tls")]
+impl ReactorStream {
+    fn read_tls(
+        tls: &mut ServerConnection,
+        socket: &mut TcpStream,
+        buf: &mut [u8],
+    ) -> io::Result<usize> {
+        loop {
+            match tls.reader().read(buf) {
+                Ok(nbytes) => return Ok(nbytes),
+                Err(error) if error.kind() == ErrorKind::WouldBlock => {}
+                Err(error) => return Err(error),
+            }
+
+            // the plaintext is drained, more records are needed
+            if tls.read_tls(socket)? == 0 {
+                return Ok(0);
+            }
+            tls.process_new_packets()
+                .map_err(|error| io::Error::new(ErrorKind::InvalidData, error))?;
+
+            // e.g. the handshake messages, the rest is written when the socket becomes writable
+            match Self::write_tls(tls, socket) {
+                Err(error) if error.kind() != ErrorKind::WouldBlock => return Err(error),
+                _ => {}
+            }
+        }
+    }
+
+    // write the pending records, fails with 'WouldBlock' if some are left
+    fn write_tls(tls: &mut ServerConnection, socket: &mut TcpStream) -> io::Result<()> {
+        while tls.wants_write() {
+            if tls.write_tls(socket)? == 0 {
+                return Err(io::Error::from(ErrorKind::WriteZero));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for ReactorStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &mut self.tls {
+            return Self::read_tls(tls, &mut self.socket, buf);
+        }
+
+        self.socket.read(buf)
+    }
+}
+
+impl Write for ReactorStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &mut self.tls {
+            loop {
+                // the records are not piled up in the memory when the client reads slowly
+                Self::write_tls(tls, &mut self.socket)?;
+
+                let nbytes = tls.writer().write(buf)?;
+                if nbytes > 0 || buf.is_empty() {
+                    match Self::write_tls(tls, &mut self.socket) {
+                        Err(error) if error.kind() != ErrorKind::WouldBlock => return Err(error),
+                        _ => return Ok(nbytes),
+                    }
+                }
+            }
+        }
+
+        self.socket.write(buf)
+    }
+
+    // the response is not completely sent until the pending records are written
+    fn flush(&mut self) -> io::Result<()> {
+        #[cfg(feature = "tls")]
+        if let Some(tls) = &mut self.tls {
+            return Self::write_tls(tls, &mut self.socket);
+        }
+
+        self.socket.flush()
+    }
+}
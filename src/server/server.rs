@@ -1,27 +1,38 @@
 use std::io::Error;
-use std::net::TcpListener;
+use std::net::{SocketAddr, TcpListener};
 use std::sync::Arc;
+use std::sync::atomic::AtomicUsize;
+use std::thread;
+use std::sync::mpsc::TrySendError;
 
+use crate::http::{HandlerError, HttpRequest, HttpResponse};
 use crate::route::route::Route;
-use crate::route::RouterBuilder;
-use crate::server::ServerConfig;
-use crate::worker::HttpTask;
-use crate::worker::worker_manager::WorkerManager;
+use crate::route::{Middleware, Router, RouterBuilder};
+use crate::server::{IoMode, ServerConfig, ShutdownHandle, TlsAcceptor};
+use crate::rt::Executor;
+use crate::server::reactor::Reactor;
+use crate::worker::{HttpTask, QueueMetrics};
+use crate::worker::worker_manager::{Message, WorkerManager};
 
 pub struct Server {
-    server_config: ServerConfig,
+    server_config: Arc<ServerConfig>,
     worker_manager: WorkerManager,
     router_builder: RouterBuilder,
+    listener: Option<TcpListener>,
+    shutdown_handle: ShutdownHandle,
 }
 
 impl Server {
     pub fn new(server_config: ServerConfig) -> Self {
-        let thread_count = server_config.thread_count;
+        let worker_manager =
+            WorkerManager::new(server_config.thread_count, server_config.queue_capacity);
 
         Server {
-            server_config,
-            worker_manager: WorkerManager::new(thread_count),
+            server_config: Arc::new(server_config),
+            worker_manager,
             router_builder: RouterBuilder::new(),
+            listener: None,
+            shutdown_handle: ShutdownHandle::new(),
         }
     }
 
@@ -30,6 +41,57 @@ impl Server {
         self
     }
 
+    // register a shared state (e.g. database pool, config), handlers get it by 'HttpRequest::get_state'
+    pub fn manage_state<T: Send + Sync + 'static>(mut self, state: T) -> Self {
+        self.router_builder.insert_state(state);
+        self
+    }
+
+    // turn errors returned by handlers into responses, instead of '500 Internal Server Error'
+    pub fn error_handler<F>(mut self, error_handler: F) -> Self
+        where
+            F: Fn(&HttpRequest, HandlerError) -> HttpResponse + Send + Sync + 'static,
+    {
+        self.router_builder.set_error_handler(Box::new(error_handler));
+        self
+    }
+
+    pub fn mount_middleware<T: 'static + Middleware>(mut self, middleware: T) -> Self {
+        self.router_builder
+            .append_middleware(Box::new(middleware) as Box<dyn Middleware + 'static>);
+        self
+    }
+
+    // handle to stop 'run' from another thread
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown_handle.clone()
+    }
+
+    // depth of the job queue, it can be read while the server is running
+    pub fn queue_metrics(&self) -> Arc<QueueMetrics> {
+        self.worker_manager.get_queue_metrics()
+    }
+
+    // bind the listener before 'run', e.g. to find out the port when the port number is 0
+    pub fn bind(&mut self) -> Result<SocketAddr, Error> {
+        if let Some(listener) = &self.listener {
+            return listener.local_addr();
+        }
+
+        let ip_addr = format!(
+            "{}:{}",
+            self.server_config.ip_addr, self.server_config.port_num
+        );
+        let listener = TcpListener::bind(ip_addr)?;
+        let local_addr = listener.local_addr()?;
+
+        self.shutdown_handle.set_local_addr(local_addr);
+        self.listener = Some(listener);
+
+        Ok(local_addr)
+    }
+
+    // serve until the shutdown is requested, in-flight requests are finished before returning
     pub fn run(&mut self) -> Result<(), Error> {
         // ref
         // https://doc.rust-lang.org/book/ch20-01-single-threaded.html
@@ -38,13 +100,76 @@ impl Server {
         let router = Arc::new(self.router_builder.build());
 
         println!("I'm running on {:?}\n", self.server_config);
-        let ip_addr = format!(
-            "{}:{}",
-            self.server_config.ip_addr, self.server_config.port_num
-        );
-        let listener = TcpListener::bind(ip_addr)?;
+        let tls_acceptor = self.make_tls_acceptor()?;
+        self.bind()?;
+        let listener = self.listener.take().expect("listener is bound");
+
+        if self.server_config.handle_signals {
+            self.shutdown_handle.register_signals()?;
+        }
+
+        if self.shutdown_handle.is_shutdown() {
+            println!("shutdown is requested before running");
+            return Ok(());
+        }
+
+        // the certificates are reloaded while the server runs
+        #[cfg(feature = "tls")]
+        let _cert_watcher = tls_acceptor
+            .as_ref()
+            .map(|tls_acceptor| tls_acceptor.watch(self.server_config.handle_signals))
+            .transpose()?;
 
+        match self.server_config.io_mode {
+            IoMode::ThreadPerConnection => {
+                self.accept_connections(&listener, &router, tls_acceptor.as_ref())
+            }
+            IoMode::Reactor => self.run_reactors(&listener, &router, tls_acceptor.as_ref())?,
+        }
+
+        // stop accepting before draining
+        drop(listener);
+
+        println!("waiting for in-flight requests");
+        if !self
+            .worker_manager
+            .shutdown(Some(self.server_config.shutdown_timeout))
+        {
+            eprintln!("[error] some requests are not finished before the shutdown timeout");
+        }
+        println!("server is stopped");
+
+        Ok(())
+    }
+
+    // the certificates are loaded when the server runs, so a broken file fails 'run'
+    #[cfg(feature = "tls")]
+    fn make_tls_acceptor(&self) -> Result<Option<TlsAcceptor>, Error> {
+        self.server_config
+            .tls
+            .as_ref()
+            .map(|tls_config| TlsAcceptor::new(tls_config, self.server_config.http2))
+            .transpose()
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn make_tls_acceptor(&self) -> Result<Option<TlsAcceptor>, Error> {
+        Ok(None)
+    }
+
+    // each connection is handed to a worker, which serves it until it is closed
+    fn accept_connections(
+        &self,
+        listener: &TcpListener,
+        router: &Arc<Router>,
+        tls_acceptor: Option<&TlsAcceptor>,
+    ) {
         for stream in listener.incoming() {
+            // the connection which has woken up the loop is dropped here as well
+            if self.shutdown_handle.is_shutdown() {
+                break;
+            }
+
             let stream = if let Ok(stream) = stream {
                 stream
             } else {
@@ -60,21 +185,1192 @@ impl Server {
 
             println!("get incoming from {}", peer_addr);
 
-            let http_task = if let Ok(http_task) = HttpTask::new(stream, router.clone()) {
+            let http_task = if let Ok(http_task) = HttpTask::new(
+                stream,
+                router.clone(),
+                self.server_config.clone(),
+                self.shutdown_handle.clone(),
+                tls_acceptor,
+            ) {
                 http_task
             } else {
                 eprintln!("[error] fail to make a HttpTask");
                 continue;
             };
 
-            self.worker_manager
-                .request(Box::new(http_task))
-                .unwrap_or_else(|_error| {
+            match self.worker_manager.request(Box::new(http_task)) {
+                Ok(()) => {}
+                // shed the load instead of piling up connections
+                Err(TrySendError::Full(Message::Job(mut task))) => {
+                    eprintln!("[error] the job queue is full, reject {}", peer_addr);
+                    task.on_rejected();
+                }
+                Err(error) => {
                     eprintln!("error occurs while request task from {}", peer_addr);
-                    eprintln!("{}", _error);
-                });
+                    eprintln!("{}", error);
+                }
+            }
+        }
+    }
+
+    // the reactors serve connections, and hand complete requests to the workers
+    fn run_reactors(
+        &self,
+        listener: &TcpListener,
+        router: &Arc<Router>,
+        tls_acceptor: Option<&TlsAcceptor>,
+    ) -> Result<(), Error> {
+        let executor = Arc::new(Executor::new(self.server_config.executor_thread_count));
+        let upgraded_count = Arc::new(AtomicUsize::new(0));
+
+        // every reactor polls its own clone of the listener
+        let mut reactors = vec![];
+        for id in 0..self.server_config.reactor_thread_count.max(1) {
+            reactors.push(Reactor::new(
+                id,
+                listener.try_clone()?,
+                router.clone(),
+                self.server_config.clone(),
+                self.shutdown_handle.clone(),
+                &self.worker_manager,
+                executor.clone(),
+                tls_acceptor.cloned(),
+                upgraded_count.clone(),
+            )?);
         }
 
+        thread::scope(|scope| {
+            let handles: Vec<_> = reactors
+                .into_iter()
+                .map(|reactor| scope.spawn(move || reactor.run()))
+                .collect();
+
+            for handle in handles {
+                if handle.join().is_err() {
+                    eprintln!("[error] a reactor has died with a panic");
+                }
+            }
+        });
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::{mpsc, Mutex};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use crate::http::{HttpStatus, ReadHeaderAs};
+    use crate::route::ActionRoute;
+    use crate::server::{IoMode, ServerBuilder};
+
+    use super::*;
+
+    // run a server on a random port
+    fn start(server: Server) -> (SocketAddr, ShutdownHandle, thread::JoinHandle<Result<(), Error>>) {
+        let mut server = server;
+        let local_addr = server.bind().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let join_handle = thread::spawn(move || server.run());
+
+        (local_addr, shutdown_handle, join_handle)
+    }
+
+    fn build_server() -> Server {
+        build_server_with(ServerBuilder::default())
+    }
+
+    fn build_reactor_server() -> Server {
+        build_server_with(ServerBuilder::default().io_mode(IoMode::Reactor).reactor_thread_count(2))
+    }
+
+    fn build_server_with(server_builder: ServerBuilder) -> Server {
+        server_builder
+            .port_num(0)
+            .thread_count(2)
+            .shutdown_timeout(Duration::from_secs(5))
+            .build()
+            .mount_route(ActionRoute::get("/hello", || "hello").unwrap())
+            .mount_route(
+                ActionRoute::get("/slow", || {
+                    thread::sleep(Duration::from_millis(300));
+                    "slow"
+                })
+                    .unwrap(),
+            )
+            .mount_route(
+                ActionRoute::get_async("/sleep", |_| async {
+                    crate::rt::sleep(Duration::from_millis(200)).await;
+                    HttpResponse::new_with(HttpStatus::OK)
+                })
+                    .unwrap(),
+            )
+            .mount_route(
+                ActionRoute::get_async("/panic_async", |_| async {
+                    crate::rt::sleep(Duration::from_millis(10)).await;
+                    panic!("async handler panicked")
+                })
+                    .unwrap(),
+            )
+            .mount_route(
+                ActionRoute::get("/panic", || -> &'static str { panic!("handler panicked") }).unwrap(),
+            )
+    }
+
+    // read a response whose body length is given by Content-Length
+    fn read_response(stream: &mut TcpStream) -> String {
+        let (head, body) = read_raw_response(stream);
+
+        head + &String::from_utf8(body).unwrap()
+    }
+
+    // the head and the body which may not be utf-8
+    fn read_raw_response(stream: &mut TcpStream) -> (String, Vec<u8>) {
+        let mut raw = vec![];
+        let mut buffer = [0_u8; 1];
+
+        while !raw.ends_with(b"\r\n\r\n") {
+            if stream.read(&mut buffer).unwrap() == 0 {
+                break;
+            }
+            raw.push(buffer[0]);
+        }
+
+        let head = String::from_utf8(raw).unwrap();
+        let content_length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map(|length| length.parse::<usize>().unwrap())
+            .unwrap_or(0);
+        let mut body = vec![0_u8; content_length];
+        stream.read_exact(&mut body).unwrap();
+
+        (head, body)
+    }
+
+    fn wait_until_stopped(join_handle: thread::JoinHandle<Result<(), Error>>) {
+        let started = Instant::now();
+        while !join_handle.is_finished() {
+            assert!(started.elapsed() < Duration::from_secs(5), "server did not stop");
+            thread::sleep(Duration::from_millis(10));
+        }
+        join_handle.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_keep_alive() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server());
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+
+        for _ in 0..2 {
+            stream.write_all(b"GET /hello HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+            let response = read_response(&mut stream);
+            assert!(response.starts_with("HTTP/1.1 200 OK"));
+            assert!(response.contains("Connection: keep-alive"));
+            assert!(response.ends_with("hello"));
+        }
+
+        stream.write_all(b"GET /hello HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        assert!(read_response(&mut stream).contains("Connection: close"));
+        assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_shutdown_drains_connections() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server());
+
+        // an idle keep-alive connection
+        let mut idle = TcpStream::connect(local_addr).unwrap();
+        idle.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+        read_response(&mut idle);
+
+        // an in-flight request
+        let mut in_flight = TcpStream::connect(local_addr).unwrap();
+        in_flight.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        shutdown_handle.shutdown();
+
+        let response = read_response(&mut in_flight);
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+        assert!(response.contains("Connection: close"));
+        assert!(response.ends_with("slow"));
+
+        idle.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(idle.read(&mut [0_u8; 1]).unwrap(), 0);
+
+        wait_until_stopped(join_handle);
+        assert!(TcpStream::connect(local_addr).is_err());
+    }
+
+    #[test]
+    fn test_shutdown_before_run() {
+        let mut server = build_server();
+        server.shutdown_handle().shutdown();
+
+        assert!(server.run().is_ok());
+    }
+
+    #[test]
+    fn test_panic_in_handler() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server());
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /panic HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::INTERNAL_SERVER_ERROR)));
+
+        // the worker is still alive
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /hello HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        assert!(read_response(&mut stream).ends_with("hello"));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_load_shedding() {
+        let (unblock_sender, unblock_receiver) = mpsc::channel::<()>();
+        let unblock_receiver = Mutex::new(unblock_receiver);
+        let server = ServerBuilder::default()
+            .port_num(0)
+            .thread_count(1)
+            .queue_capacity(1)
+            .retry_after(Duration::from_secs(3))
+            .build()
+            .mount_route(
+                ActionRoute::get("/block", move || {
+                    unblock_receiver.lock().unwrap().recv().ok();
+                    "unblocked"
+                })
+                    .unwrap(),
+            );
+        let queue_metrics = server.queue_metrics();
+        let (local_addr, shutdown_handle, join_handle) = start(server);
+
+        let wait_for = |condition: &dyn Fn() -> bool| {
+            let started = Instant::now();
+            while !condition() {
+                assert!(started.elapsed() < Duration::from_secs(5));
+                thread::sleep(Duration::from_millis(10));
+            }
+        };
+
+        // the only worker is busy with the first connection
+        let mut busy = TcpStream::connect(local_addr).unwrap();
+        busy.write_all(b"GET /block HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        wait_for(&|| queue_metrics.get_enqueued_count() == 1 && queue_metrics.get_depth() == 0);
+
+        // the second one waits in the queue
+        let mut queued = TcpStream::connect(local_addr).unwrap();
+        queued.write_all(b"GET /hello HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        wait_for(&|| queue_metrics.get_depth() == 1);
+
+        // the third one is rejected at once
+        let mut rejected = TcpStream::connect(local_addr).unwrap();
+        rejected.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut rejected);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::SERVICE_UNAVAILABLE)));
+        assert!(response.contains("Retry-After: 3"));
+        assert_eq!(queue_metrics.get_rejected_count(), 1);
+
+        unblock_sender.send(()).unwrap();
+        assert!(read_response(&mut busy).ends_with("unblocked"));
+        assert!(read_response(&mut queued).starts_with("HTTP/1.1 404"));
+        assert_eq!(queue_metrics.get_peak_depth(), 1);
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    fn build_server_with_timeouts() -> Server {
+        ServerBuilder::default()
+            .port_num(0)
+            .header_read_timeout(Duration::from_millis(200))
+            .body_read_timeout(Duration::from_millis(200))
+            .keep_alive_timeout(Duration::from_millis(200))
+            .build()
+    }
+
+    #[test]
+    fn test_slow_header() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server_with_timeouts());
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let is_responded = Arc::new(AtomicBool::new(false));
+
+        // every byte arrives in time, but the header never ends
+        let trickle = {
+            let is_responded = is_responded.clone();
+            thread::spawn(move || {
+                writer.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+                while !is_responded.load(Ordering::SeqCst) {
+                    if writer.write_all(b"X").is_err() {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(20));
+                }
+            })
+        };
+
+        let started = Instant::now();
+        let response = read_response(&mut stream);
+        is_responded.store(true, Ordering::SeqCst);
+        trickle.join().unwrap();
+
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::REQUEST_TIMEOUT)));
+        assert!(response.contains("Connection: close"));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_slow_body() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server_with_timeouts());
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+
+        stream
+            .write_all(b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n12345")
+            .unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::REQUEST_TIMEOUT)));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_keep_alive_timeout() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server_with_timeouts());
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+
+        // an idle connection is closed without a response
+        stream.set_read_timeout(Some(Duration::from_secs(2))).unwrap();
+        assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_request_limits() {
+        let server = ServerBuilder::default()
+            .port_num(0)
+            .max_header_size(1_000)
+            .max_header_count(5)
+            .max_uri_length(100)
+            .max_body_size(10)
+            .build()
+            .mount_route(ActionRoute::post("/echo", || "echo").unwrap())
+            .mount_route(ActionRoute::post("/upload", || "uploaded").unwrap().max_body_size(100));
+        let (local_addr, shutdown_handle, join_handle) = start(server);
+
+        let request = |raw: &[u8]| {
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.write_all(raw).unwrap();
+            read_response(&mut stream)
+        };
+        let status_line = |status: HttpStatus| format!("HTTP/1.1 {}", status);
+
+        let many_headers = format!("GET / HTTP/1.1\r\n{}\r\n", "X-A: a\r\n".repeat(6));
+        assert!(request(many_headers.as_bytes())
+            .starts_with(&status_line(HttpStatus::REQUEST_HEADER_FIELDS_TOO_LARGE)));
+
+        let large_header = format!("GET / HTTP/1.1\r\nX-A: {}\r\n\r\n", "a".repeat(2_000));
+        assert!(request(large_header.as_bytes())
+            .starts_with(&status_line(HttpStatus::REQUEST_HEADER_FIELDS_TOO_LARGE)));
+
+        let long_uri = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(101));
+        assert!(request(long_uri.as_bytes()).starts_with(&status_line(HttpStatus::URI_TOO_LONG)));
+
+        // answered before the body is read or allocated
+        let huge_body = b"POST /echo HTTP/1.1\r\nContent-Length: 10000000000\r\n\r\n";
+        assert!(request(huge_body).starts_with(&status_line(HttpStatus::PAYLOAD_TOO_LARGE)));
+
+        let small_body = b"POST /echo HTTP/1.1\r\nContent-Length: 5\r\nConnection: close\r\n\r\n12345";
+        assert!(request(small_body).ends_with("echo"));
+
+        // the route allows a larger body than the server
+        let upload = format!(
+            "POST /upload HTTP/1.1\r\nContent-Length: 50\r\nConnection: close\r\n\r\n{}",
+            "a".repeat(50)
+        );
+        assert!(request(upload.as_bytes()).ends_with("uploaded"));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_reactor_serves_more_connections_than_workers() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_reactor_server());
+
+        // idle connections do not occupy the 2 workers
+        let mut streams: Vec<TcpStream> =
+            (0..50).map(|_| TcpStream::connect(local_addr).unwrap()).collect();
+        for stream in streams.iter_mut().rev() {
+            stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+        }
+        for stream in streams.iter_mut() {
+            let response = read_response(stream);
+            assert!(response.contains("Connection: keep-alive"));
+            assert!(response.ends_with("hello"));
+        }
+
+        // the connection is kept, and a request can arrive in pieces
+        let stream = &mut streams[0];
+        stream.write_all(b"GET /hel").unwrap();
+        thread::sleep(Duration::from_millis(50));
+        stream.write_all(b"lo HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        assert!(read_response(stream).ends_with("hello"));
+        assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_reactor_shutdown_drains_connections() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_reactor_server());
+
+        let mut idle = TcpStream::connect(local_addr).unwrap();
+        idle.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+        read_response(&mut idle);
+
+        let mut in_flight = TcpStream::connect(local_addr).unwrap();
+        in_flight.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        shutdown_handle.shutdown();
+
+        let response = read_response(&mut in_flight);
+        assert!(response.contains("Connection: close"));
+        assert!(response.ends_with("slow"));
+
+        idle.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        assert_eq!(idle.read(&mut [0_u8; 1]).unwrap(), 0);
+
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_reactor_errors() {
+        let server = build_server_with(
+            ServerBuilder::default()
+                .io_mode(IoMode::Reactor)
+                .header_read_timeout(Duration::from_millis(200))
+                .max_uri_length(100),
+        );
+        let (local_addr, shutdown_handle, join_handle) = start(server);
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /panic HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::INTERNAL_SERVER_ERROR)));
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET / HTTP/1.1\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::REQUEST_TIMEOUT)));
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream
+            .write_all(format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(200)).as_bytes())
+            .unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::URI_TOO_LONG)));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_reactor_async_handlers() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server_with(
+            ServerBuilder::default()
+                .io_mode(IoMode::Reactor)
+                .executor_thread_count(1),
+        ));
+
+        // 100 handlers wait at the same time on 2 workers and 1 executor thread
+        let started = Instant::now();
+        let mut streams: Vec<TcpStream> =
+            (0..100).map(|_| TcpStream::connect(local_addr).unwrap()).collect();
+        for stream in streams.iter_mut() {
+            stream.write_all(b"GET /sleep HTTP/1.1\r\n\r\n").unwrap();
+        }
+        for stream in streams.iter_mut() {
+            assert!(read_response(stream).starts_with("HTTP/1.1 200 OK"));
+        }
+        assert!(started.elapsed() < Duration::from_secs(3));
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /panic_async HTTP/1.1\r\n\r\n").unwrap();
+        let response = read_response(&mut stream);
+        assert!(response.starts_with(&format!("HTTP/1.1 {}", HttpStatus::INTERNAL_SERVER_ERROR)));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_async_handler_without_reactor() {
+        let (local_addr, shutdown_handle, join_handle) = start(build_server());
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /sleep HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        assert!(read_response(&mut stream).starts_with("HTTP/1.1 200 OK"));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_static_file() {
+        let static_root = format!("/target/lite-ws-static-{}", crate::util::random::random_hex(4));
+        std::fs::create_dir_all(&static_root[1..]).unwrap();
+        let content: Vec<u8> = (0..3_000_000).map(|i| (i % 251) as u8).collect();
+        std::fs::write(format!("{}/large.png", &static_root[1..]), &content).unwrap();
+
+        for server_builder in [
+            ServerBuilder::default(),
+            ServerBuilder::default().io_mode(IoMode::Reactor),
+        ] {
+            let server = build_server_with(server_builder).mount_route(crate::route::StaticRoute::new(
+                static_root.clone(),
+                Some("/static".to_string()),
+                Some(vec![".png".to_string()]),
+            ));
+            let (local_addr, shutdown_handle, join_handle) = start(server);
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+
+            stream.write_all(b"GET /static/large.png HTTP/1.1\r\n\r\n").unwrap();
+            let (head, body) = read_raw_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 200 OK"));
+            assert!(head.contains("Accept-Ranges: bytes"));
+            assert!(body == content);
+
+            stream
+                .write_all(b"GET /static/large.png HTTP/1.1\r\nRange: bytes=1000-1999999\r\n\r\n")
+                .unwrap();
+            let (head, body) = read_raw_response(&mut stream);
+            assert!(head.starts_with(&format!("HTTP/1.1 {}", HttpStatus::PARTIAL_CONTENT)));
+            assert!(head.contains("Content-Range: bytes 1000-1999999/3000000"));
+            assert!(body == content[1000..2_000_000]);
+
+            stream
+                .write_all(b"GET /static/large.png HTTP/1.1\r\nRange: bytes=3000000-\r\n\r\n")
+                .unwrap();
+            let (head, body) = read_raw_response(&mut stream);
+            assert!(head.starts_with(&format!("HTTP/1.1 {}", HttpStatus::RANGE_NOT_SATISFIABLE)));
+            assert!(head.contains("Content-Range: bytes */3000000"));
+            assert!(body.is_empty());
+
+            shutdown_handle.shutdown();
+            wait_until_stopped(join_handle);
+        }
+
+        std::fs::remove_dir_all(&static_root[1..]).unwrap();
+    }
+
+    #[test]
+    fn test_http2() {
+        use crate::h2::test_client::{self, TestClient, GOAWAY, SETTINGS_INITIAL_WINDOW_SIZE};
+
+        for server_builder in [
+            ServerBuilder::default(),
+            ServerBuilder::default().io_mode(IoMode::Reactor),
+        ] {
+            let server = build_server_with(server_builder.http2(true))
+                .mount_route(ActionRoute::get("/big", || vec![7_u8; 200_000]).unwrap());
+            let (local_addr, shutdown_handle, join_handle) = start(server);
+
+            // with the prior knowledge, the windows are opened for the large body
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut client = TestClient::new();
+            let mut input = client.start(&[(SETTINGS_INITIAL_WINDOW_SIZE, 1 << 30)]);
+            input.extend(client.window_update(0, 1 << 30));
+            input.extend(client.request(1, "GET", "/slow", &[], b""));
+            input.extend(client.request(3, "GET", "/panic", &[], b""));
+            input.extend(client.request(5, "GET", "/big", &[], b""));
+            stream.write_all(&input).unwrap();
+
+            let response = client.read_response(&mut stream, 1);
+            assert_eq!(response.status, 200);
+            assert_eq!(response.body, b"slow");
+            assert_eq!(client.read_response(&mut stream, 3).status, 500);
+            assert_eq!(client.read_response(&mut stream, 5).body, vec![7_u8; 200_000]);
+
+            // upgraded from HTTP/1.1, the request is answered on the stream 1
+            let mut upgraded = TcpStream::connect(local_addr).unwrap();
+            upgraded.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            upgraded
+                .write_all(
+                    b"GET /hello HTTP/1.1\r\nHost: test\r\nConnection: Upgrade, HTTP2-Settings\r\n\
+                    Upgrade: h2c\r\nHTTP2-Settings: \r\n\r\n",
+                )
+                .unwrap();
+            let (head, _) = read_raw_response(&mut upgraded);
+            assert!(head.starts_with("HTTP/1.1 101 Switching Protocols"));
+            assert!(head.contains("Upgrade: h2c"));
+
+            let mut upgraded_client = TestClient::new();
+            upgraded.write_all(&upgraded_client.start(&[])).unwrap();
+            assert_eq!(upgraded_client.read_response(&mut upgraded, 1).body, b"hello");
+
+            // the streams are closed with GOAWAY
+            shutdown_handle.shutdown();
+            let mut frame_types = vec![];
+            while let Some((frame_header, _)) = test_client::read_frame(&mut stream) {
+                frame_types.push(frame_header.frame_type);
+            }
+            assert_eq!(frame_types.last(), Some(&GOAWAY));
+            wait_until_stopped(join_handle);
+        }
+    }
+
+    #[test]
+    fn test_http2_output_limit() {
+        use crate::h2::test_client::TestClient;
+
+        let server = build_server_with(ServerBuilder::default().io_mode(IoMode::Reactor).http2(true));
+        let (local_addr, shutdown_handle, join_handle) = start(server);
+
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream.set_write_timeout(Some(Duration::from_secs(1))).unwrap();
+        let mut client = TestClient::new();
+        stream.write_all(&client.start(&[])).unwrap();
+
+        // the answers to PING are not read, so the server stops reading instead of queueing them
+        let ping = client.ping([1; 8]);
+        let pings = ping.repeat(1024);
+        let mut sent = 0;
+        while sent < 64 * 1024 * 1024 {
+            match stream.write(&pings[sent % pings.len()..]) {
+                Ok(nbytes) => sent += nbytes,
+                Err(_) => break,
+            }
+        }
+        assert!(sent < 64 * 1024 * 1024);
+
+        // the reading goes on once the answers are read
+        let mut input = ping[sent % ping.len()..].to_vec();
+        input.extend(client.request(1, "GET", "/hello", &[], b""));
+        stream.set_write_timeout(None).unwrap();
+        let mut writer = stream.try_clone().unwrap();
+        let writer_handle = thread::spawn(move || writer.write_all(&input).unwrap());
+        assert_eq!(client.read_response(&mut stream, 1).body, b"hello");
+        writer_handle.join().unwrap();
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_websocket() {
+        use crate::websocket::{close_code, Message, WebSocketRoute};
+        use crate::websocket::frame::{self, BINARY, CLOSE, CONTINUATION, PING, PONG, TEXT};
+
+        let handshake = b"GET /echo/alice HTTP/1.1\r\nHost: test\r\nConnection: Upgrade\r\n\
+            Upgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+            Sec-WebSocket-Version: 13\r\n\r\n";
+
+        for server_builder in [
+            ServerBuilder::default(),
+            ServerBuilder::default().io_mode(IoMode::Reactor),
+        ] {
+            let route = WebSocketRoute::new("/echo/:name", |http_request, mut websocket| {
+                let name = http_request.get_path_param("name").unwrap().to_string();
+                websocket.set_read_timeout(Some(Duration::from_millis(50))).unwrap();
+                while !websocket.is_shutdown() {
+                    match websocket.receive() {
+                        Ok(Message::Text(text)) => websocket.send_text(&format!("{}: {}", name, text)).unwrap(),
+                        Ok(Message::Binary(data)) => websocket.send_binary(&data).unwrap(),
+                        Ok(Message::Close(_)) => break,
+                        Ok(_) => {}
+                        Err(error) if error.is_timeout() => {}
+                        Err(_) => break,
+                    }
+                }
+            })
+                .unwrap()
+                .frame_size(16_384);
+            let (local_addr, shutdown_handle, join_handle) = start(build_server_with(server_builder).mount_route(route));
+
+            let connect = || {
+                let stream = TcpStream::connect(local_addr).unwrap();
+                stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                stream
+            };
+
+            // the first frame arrives with the handshake
+            let mut stream = connect();
+            let mut input = handshake.to_vec();
+            frame::write_masked_frame(&mut input, true, TEXT, b"hi");
+            stream.write_all(&input).unwrap();
+            let (head, _) = read_raw_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 101 "));
+            assert!(head.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+            assert_eq!(frame::read_frame(&mut stream).unwrap().payload, b"alice: hi");
+
+            // a ping is answered in the middle of a fragmented message
+            let mut input = vec![];
+            frame::write_masked_frame(&mut input, false, TEXT, b"hel");
+            frame::write_masked_frame(&mut input, true, PING, b"ping");
+            frame::write_masked_frame(&mut input, true, CONTINUATION, b"lo");
+            stream.write_all(&input).unwrap();
+            let pong = frame::read_frame(&mut stream).unwrap();
+            assert_eq!((pong.opcode, pong.payload.as_slice()), (PONG, &b"ping"[..]));
+            assert_eq!(frame::read_frame(&mut stream).unwrap().payload, b"alice: hello");
+
+            // a large message is sent in the frames of 'frame_size'
+            let data: Vec<u8> = (0..100_000).map(|index| index as u8).collect();
+            let mut input = vec![];
+            frame::write_masked_frame(&mut input, true, BINARY, &data);
+            stream.write_all(&input).unwrap();
+            let mut echoed = vec![];
+            let mut opcodes = vec![];
+            loop {
+                let frame = frame::read_frame(&mut stream).unwrap();
+                assert!(frame.payload.len() <= 16_384);
+                opcodes.push(frame.opcode);
+                echoed.extend(frame.payload);
+                if frame.is_fin {
+                    break;
+                }
+            }
+            assert_eq!(opcodes[0], BINARY);
+            assert!(opcodes[1..].iter().all(|opcode| *opcode == CONTINUATION));
+            assert_eq!(echoed, data);
+
+            // the closing handshake
+            let mut input = vec![];
+            frame::write_masked_frame(&mut input, true, CLOSE, &close_code::NORMAL.to_be_bytes());
+            stream.write_all(&input).unwrap();
+            let close = frame::read_frame(&mut stream).unwrap();
+            assert_eq!((close.opcode, close.payload.as_slice()), (CLOSE, &[0x03, 0xe8][..]));
+            assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+
+            // an unmasked frame fails the connection
+            let mut stream = connect();
+            let mut input = handshake.to_vec();
+            frame::write_frame(&mut input, true, TEXT, b"hi");
+            stream.write_all(&input).unwrap();
+            read_raw_response(&mut stream);
+            let close = frame::read_frame(&mut stream).unwrap();
+            assert_eq!(close.payload, close_code::PROTOCOL_ERROR.to_be_bytes());
+
+            // a plain request is told to upgrade
+            let mut stream = connect();
+            stream.write_all(b"GET /echo/alice HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+            assert!(read_response(&mut stream).starts_with("HTTP/1.1 426 "));
+
+            // the connection is closed with 1001 when the server is shutting down
+            let mut stream = connect();
+            stream.write_all(handshake).unwrap();
+            read_raw_response(&mut stream);
+            shutdown_handle.shutdown();
+            let close = frame::read_frame(&mut stream).unwrap();
+            assert_eq!(close.payload, close_code::GOING_AWAY.to_be_bytes());
+            let mut input = vec![];
+            frame::write_masked_frame(&mut input, true, CLOSE, &close.payload);
+            stream.write_all(&input).unwrap();
+            wait_until_stopped(join_handle);
+        }
+    }
+
+    #[test]
+    fn test_event_stream() {
+        use std::sync::Arc;
+
+        use crate::extract::{Header, LastEventId};
+        use crate::http::{Event, HttpResponseBuilder};
+
+        // read an event or a comment, which ends with an empty line
+        fn read_event(stream: &mut TcpStream) -> String {
+            let mut raw = vec![];
+            let mut buffer = [0_u8; 1];
+            while !raw.ends_with(b"\n\n") {
+                assert_eq!(stream.read(&mut buffer).unwrap(), 1, "the stream is closed");
+                raw.push(buffer[0]);
+            }
+            String::from_utf8(raw).unwrap()
+        }
+
+        for server_builder in [
+            ServerBuilder::default(),
+            ServerBuilder::default().io_mode(IoMode::Reactor),
+        ] {
+            let is_stopped = Arc::new(AtomicBool::new(false));
+            let is_producer_stopped = is_stopped.clone();
+            let ticks = ActionRoute::get(
+                "/ticks",
+                move |last_event_id: Option<Header<LastEventId>>, builder: HttpResponseBuilder| {
+                    let start = last_event_id.map_or(0, |Header(LastEventId(id))| id.parse::<u32>().unwrap() + 1);
+                    let is_stopped = is_producer_stopped.clone();
+                    builder
+                        .event_stream(move |mut events| {
+                            let (sender, receiver) = mpsc::channel();
+                            thread::spawn(move || {
+                                for id in start.. {
+                                    let event = Event::new(&format!("tick {}", id)).event("tick").id(&id.to_string());
+                                    if sender.send(event).is_err() {
+                                        is_stopped.store(true, Ordering::SeqCst);
+                                        return;
+                                    }
+                                    thread::sleep(Duration::from_millis(20));
+                                }
+                            });
+                            events.forward(receiver).ok();
+                        })
+                        .build()
+                        .unwrap()
+                },
+            )
+                .unwrap();
+            let quiet = ActionRoute::get("/quiet", |builder: HttpResponseBuilder| {
+                builder
+                    .event_stream(|mut events| {
+                        events.set_keep_alive_interval(Duration::from_millis(50));
+                        let (_sender, receiver) = mpsc::channel();
+                        events.forward(receiver).ok();
+                    })
+                    .build()
+                    .unwrap()
+            })
+                .unwrap();
+            let server = build_server_with(server_builder).mount_route(ticks).mount_route(quiet);
+            let (local_addr, shutdown_handle, join_handle) = start(server);
+
+            let connect = |request: &str| {
+                let mut stream = TcpStream::connect(local_addr).unwrap();
+                stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                stream.write_all(request.as_bytes()).unwrap();
+                let (head, _) = read_raw_response(&mut stream);
+                (stream, head)
+            };
+
+            let (mut stream, head) = connect("GET /ticks HTTP/1.1\r\nHost: test\r\n\r\n");
+            assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(head.contains("Connection: close\r\n"));
+            assert!(head.contains("Content-Type: text/event-stream\r\n"));
+            assert!(head.contains("Cache-Control: no-cache\r\n"));
+            assert!(!head.contains("Content-Length"));
+            assert_eq!(read_event(&mut stream), "event: tick\nid: 0\ndata: tick 0\n\n");
+            assert_eq!(read_event(&mut stream), "event: tick\nid: 1\ndata: tick 1\n\n");
+
+            // the producer stops when the client disconnects
+            drop(stream);
+            let started = Instant::now();
+            while !is_stopped.load(Ordering::SeqCst) {
+                assert!(started.elapsed() < Duration::from_secs(5), "the producer did not stop");
+                thread::sleep(Duration::from_millis(10));
+            }
+
+            // the client resumes after the last event it has received
+            let (mut stream, _) = connect("GET /ticks HTTP/1.1\r\nHost: test\r\nLast-Event-ID: 5\r\n\r\n");
+            assert_eq!(read_event(&mut stream), "event: tick\nid: 6\ndata: tick 6\n\n");
+            drop(stream);
+
+            let (mut stream, _) = connect("GET /quiet HTTP/1.1\r\nHost: test\r\n\r\n");
+            assert_eq!(read_event(&mut stream), ": keep-alive\n\n");
+
+            // the stream ends when the server is shutting down
+            shutdown_handle.shutdown();
+            let mut rest = vec![];
+            stream.read_to_end(&mut rest).unwrap();
+            wait_until_stopped(join_handle);
+        }
+    }
+
+    #[test]
+    fn test_upgrade() {
+        use std::io::{BufRead, BufReader};
+
+        use crate::http::{HttpResponseBuilder, ReadHeaderAs};
+
+        for server_builder in [
+            ServerBuilder::default(),
+            ServerBuilder::default().io_mode(IoMode::Reactor),
+        ] {
+            // a line-based shell which echoes the commands
+            let route = ActionRoute::new_get("/shell", |http_request: HttpRequest, builder: HttpResponseBuilder| {
+                let upgrade: Option<String> = http_request.get_header("upgrade");
+                if upgrade.as_deref() != Some("debug-shell") {
+                    return (http_request, builder.set_status(HttpStatus::UPGRADE_REQUIRED).build().unwrap());
+                }
+
+                let http_response = builder
+                    .upgrade("debug-shell", |stream| {
+                        let mut reader = BufReader::new(stream);
+                        let mut line = String::new();
+                        while reader.read_line(&mut line).is_ok_and(|nbytes| nbytes > 0) {
+                            if line.trim() == "quit" {
+                                break;
+                            }
+                            let output = format!("> {}", line);
+                            reader.get_mut().write_all(output.as_bytes()).unwrap();
+                            line.clear();
+                        }
+                    })
+                    .build()
+                    .unwrap();
+                (http_request, http_response)
+            })
+                .unwrap();
+            let (local_addr, shutdown_handle, join_handle) = start(build_server_with(server_builder).mount_route(route));
+
+            // the first command arrives with the request
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            stream
+                .write_all(b"GET /shell HTTP/1.1\r\nHost: test\r\nConnection: Upgrade\r\nUpgrade: debug-shell\r\n\r\nstatus\n")
+                .unwrap();
+            let (head, _) = read_raw_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 101 "));
+            assert!(head.contains("Connection: Upgrade\r\n"));
+            assert!(head.contains("Upgrade: debug-shell\r\n"));
+            assert!(!head.contains("Content-Length"));
+
+            stream.write_all(b"uptime\nquit\n").unwrap();
+            let mut output = String::new();
+            stream.read_to_string(&mut output).unwrap();
+            assert_eq!(output, "> status\n> uptime\n");
+
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.write_all(b"GET /shell HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+            assert!(read_response(&mut stream).starts_with("HTTP/1.1 426 "));
+
+            shutdown_handle.shutdown();
+            wait_until_stopped(join_handle);
+        }
+    }
+
+    #[test]
+    fn test_upgrade_limit() {
+        use crate::http::HttpResponseBuilder;
+
+        // holds the upgraded connection until the client closes it
+        let route = ActionRoute::new_get("/hold", |http_request: HttpRequest, builder: HttpResponseBuilder| {
+            let http_response = builder
+                .upgrade("hold", |mut stream| {
+                    let mut buffer = [0_u8; 16];
+                    while stream.read(&mut buffer).is_ok_and(|nbytes| nbytes > 0) {}
+                })
+                .build()
+                .unwrap();
+            (http_request, http_response)
+        })
+            .unwrap();
+        let server_builder = ServerBuilder::default()
+            .io_mode(IoMode::Reactor)
+            .max_upgraded_connections(1);
+        let (local_addr, shutdown_handle, join_handle) = start(build_server_with(server_builder).mount_route(route));
+
+        let upgrade = || {
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            stream
+                .write_all(b"GET /hold HTTP/1.1\r\nHost: test\r\nConnection: Upgrade\r\nUpgrade: hold\r\n\r\n")
+                .unwrap();
+            let (head, _) = read_raw_response(&mut stream);
+            (head, stream)
+        };
+
+        let (head, upgraded) = upgrade();
+        assert!(head.starts_with("HTTP/1.1 101 "));
+
+        // the other worker is left for the requests
+        let (head, _) = upgrade();
+        assert!(head.starts_with(&format!("HTTP/1.1 {}", HttpStatus::SERVICE_UNAVAILABLE)));
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /hello HTTP/1.1\r\nHost: test\r\n\r\n").unwrap();
+        assert!(read_response(&mut stream).ends_with("hello"));
+
+        // the place is given back when the upgraded connection ends
+        drop(upgraded);
+        let started = Instant::now();
+        loop {
+            let (head, _) = upgrade();
+            if head.starts_with("HTTP/1.1 101 ") {
+                break;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+
+    #[test]
+    fn test_pipelining() {
+        use std::net::Shutdown;
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicUsize;
+
+        for (server_builder, max_concurrency) in [
+            (ServerBuilder::default(), 1),
+            (ServerBuilder::default().io_mode(IoMode::Reactor), 2),
+            (ServerBuilder::default().io_mode(IoMode::Reactor).max_pipelined_requests(1), 1),
+        ] {
+            // how many handlers run at the same time
+            let running = Arc::new(AtomicUsize::new(0));
+            let max_running = Arc::new(AtomicUsize::new(0));
+            let (running_in_route, max_running_in_route) = (running.clone(), max_running.clone());
+            let track = ActionRoute::get("/track", move || {
+                let count = running_in_route.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running_in_route.fetch_max(count, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(200));
+                running_in_route.fetch_sub(1, Ordering::SeqCst);
+                "track"
+            })
+                .unwrap();
+            // the handler does not look at the body
+            let ignore = ActionRoute::post("/ignore", || "ignored").unwrap();
+            let server = build_server_with(server_builder).mount_route(track).mount_route(ignore);
+            let (local_addr, shutdown_handle, join_handle) = start(server);
+
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            stream
+                .write_all(
+                    b"GET /track HTTP/1.1\r\nHost: test\r\n\r\n\
+                    GET /track HTTP/1.1\r\nHost: test\r\n\r\n\
+                    GET /hello HTTP/1.1\r\nHost: test\r\n\r\n\
+                    POST /ignore HTTP/1.1\r\nHost: test\r\nContent-Length: 19\r\n\r\n\
+                    GET /panic HTTP/1.1GET /hello HTTP/1.1\r\nHost: test\r\n\r\n",
+                )
+                .unwrap();
+            for body in ["track", "track", "hello", "ignored", "hello"] {
+                let response = read_response(&mut stream);
+                assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+                assert!(response.ends_with(body), "{}", response);
+            }
+            assert_eq!(max_running.load(Ordering::SeqCst), max_concurrency);
+
+            // the requests which have arrived before the client closes its side are answered
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            stream
+                .write_all(b"GET /slow HTTP/1.1\r\nHost: test\r\n\r\nGET /hello HTTP/1.1\r\nHost: test\r\n\r\n")
+                .unwrap();
+            stream.shutdown(Shutdown::Write).unwrap();
+            assert!(read_response(&mut stream).ends_with("slow"));
+            assert!(read_response(&mut stream).ends_with("hello"));
+            assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+
+            shutdown_handle.shutdown();
+            wait_until_stopped(join_handle);
+        }
+    }
+
+    #[test]
+    fn test_expect_continue() {
+        for server_builder in [ServerBuilder::default(), ServerBuilder::default().io_mode(IoMode::Reactor)] {
+            let upload = ActionRoute::post("/upload", || "uploaded")
+                .unwrap()
+                .check_continue(|http_request| {
+                    let token: Option<String> = http_request.get_header("authorization");
+                    if token.as_deref() == Some("Bearer secret") {
+                        None
+                    } else {
+                        Some(HttpResponse::new_with(HttpStatus::UNAUTHORIZED))
+                    }
+                });
+            let server = build_server_with(server_builder.max_body_size(100)).mount_route(upload);
+            let (local_addr, shutdown_handle, join_handle) = start(server);
+
+            let connect = || {
+                let stream = TcpStream::connect(local_addr).unwrap();
+                stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+                stream
+            };
+
+            // the body is sent after '100 Continue', then the connection is kept
+            let mut stream = connect();
+            stream
+                .write_all(
+                    b"POST /upload HTTP/1.1\r\nAuthorization: Bearer secret\r\n\
+                    Expect: 100-continue\r\nContent-Length: 5\r\n\r\n",
+                )
+                .unwrap();
+            let mut interim = [0_u8; 25];
+            stream.read_exact(&mut interim).unwrap();
+            assert_eq!(&interim, b"HTTP/1.1 100 Continue\r\n\r\n");
+            stream.write_all(b"12345").unwrap();
+            assert!(read_response(&mut stream).ends_with("uploaded"));
+            stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+            assert!(read_response(&mut stream).ends_with("hello"));
+
+            // rejected without the body, which is never read
+            let request = |raw: &str| {
+                let mut stream = connect();
+                stream.write_all(raw.as_bytes()).unwrap();
+                let response = read_response(&mut stream);
+                assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+                response
+            };
+            let status_line = |status: HttpStatus| format!("HTTP/1.1 {}", status);
+            assert!(request("POST /upload HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+                .starts_with(&status_line(HttpStatus::UNAUTHORIZED)));
+            assert!(request("POST /missing HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n")
+                .starts_with(&status_line(HttpStatus::NOT_FOUND)));
+            assert!(request("POST /upload HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 500\r\n\r\n")
+                .starts_with(&status_line(HttpStatus::PAYLOAD_TOO_LARGE)));
+            assert!(request("POST /upload HTTP/1.1\r\nExpect: something\r\nContent-Length: 5\r\n\r\n")
+                .starts_with(&status_line(HttpStatus::EXPECTATION_FAILED)));
+
+            shutdown_handle.shutdown();
+            wait_until_stopped(join_handle);
+        }
+    }
+
+    #[test]
+    fn test_strict_parsing() {
+        for server_builder in [ServerBuilder::default(), ServerBuilder::default().io_mode(IoMode::Reactor)] {
+            let (local_addr, shutdown_handle, join_handle) = start(build_server_with(server_builder));
+
+            // the smuggled request is never answered, the connection is closed after the error
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            stream
+                .write_all(
+                    b"POST /hello HTTP/1.1\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n\
+                    0\r\n\r\nGET /slow HTTP/1.1\r\n\r\n",
+                )
+                .unwrap();
+            assert!(read_response(&mut stream).starts_with("HTTP/1.1 400 "));
+            assert_eq!(stream.read(&mut [0_u8; 1]).unwrap(), 0);
+
+            let mut stream = TcpStream::connect(local_addr).unwrap();
+            stream.write_all(b"GET /hello HTTP/1.1\nHost: test\n\n").unwrap();
+            assert!(read_response(&mut stream).starts_with("HTTP/1.1 400 "));
+
+            shutdown_handle.shutdown();
+            wait_until_stopped(join_handle);
+        }
+
+        let server = build_server_with(ServerBuilder::default().lenient_parsing(true));
+        let (local_addr, shutdown_handle, join_handle) = start(server);
+        let mut stream = TcpStream::connect(local_addr).unwrap();
+        stream.write_all(b"GET /hello HTTP/1.1\nHost : test\n\n").unwrap();
+        assert!(read_response(&mut stream).ends_with("hello"));
+
+        shutdown_handle.shutdown();
+        wait_until_stopped(join_handle);
+    }
+}
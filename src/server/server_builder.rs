@@ -1,11 +1,69 @@
+use std::time::Duration;
+
 use crate::server::Server;
+#[cfg(feature = "tls")]
+use crate::server::TlsConfig;
+
+// how the server handles connections
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IoMode {
+    // each connection occupies a worker thread while it is open
+    ThreadPerConnection,
+    // connections are multiplexed on 'reactor_thread_count' threads with epoll/kqueue,
+    // and the workers only execute the handlers of complete requests.
+    // async handlers run on the executor only in this mode.
+    Reactor,
+}
 
 // responsible for configuring settings and building server instance
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ServerConfig {
     pub ip_addr: String,
     pub port_num: u16,
     pub thread_count: u16,
+    pub io_mode: IoMode,
+    pub reactor_thread_count: u16,
+    // threads which poll the futures of async handlers in the reactor mode
+    pub executor_thread_count: u16,
+    // how many accepted connections can wait for a worker, the others get '503 Service Unavailable'
+    pub queue_capacity: usize,
+    // sent as 'Retry-After' with '503 Service Unavailable'
+    pub retry_after: Duration,
+    // limits of a request, checked before anything is allocated for it
+    pub max_header_size: usize,
+    pub max_header_count: usize,
+    pub max_uri_length: usize,
+    // a route can override it with its own limit
+    pub max_body_size: usize,
+    // accept the deviations of old clients from RFC 9112 which are not ambiguous, e.g. bare LF.
+    // the conflicting lengths of a body are rejected anyway.
+    pub lenient_parsing: bool,
+    // the whole header must arrive within this time after its first byte
+    pub header_read_timeout: Duration,
+    // the whole body must arrive within this time after the header
+    pub body_read_timeout: Duration,
+    // how long a connection waits for the next request
+    pub keep_alive_timeout: Duration,
+    // how many pipelined requests of a connection are handled at once in the reactor mode,
+    // the responses are still written in the order of the requests
+    pub max_pipelined_requests: usize,
+    // how long a single write of the response can block
+    pub write_timeout: Duration,
+    // how long the shutdown waits for in-flight requests
+    pub shutdown_timeout: Duration,
+    // shutdown on SIGINT and SIGTERM
+    pub handle_signals: bool,
+    // HTTP/2 with ALPN 'h2' over TLS, and with the prior knowledge or 'Upgrade: h2c' over TCP
+    pub http2: bool,
+    // how many streams a HTTP/2 connection can open at once
+    pub max_concurrent_streams: u32,
+    // how many connections can be upgraded at once in the reactor mode, the others get
+    // '503 Service Unavailable'. an upgraded connection holds a worker until its callback
+    // returns, so set it below 'thread_count' to always leave workers for the requests.
+    pub max_upgraded_connections: usize,
+    // connections are encrypted with TLS when it is set
+    #[cfg(feature = "tls")]
+    pub tls: Option<TlsConfig>,
 }
 
 impl Default for ServerConfig {
@@ -14,6 +72,28 @@ impl Default for ServerConfig {
             ip_addr: "127.0.0.1".to_string(),
             port_num: 8888,
             thread_count: 2,
+            io_mode: IoMode::ThreadPerConnection,
+            reactor_thread_count: 1,
+            executor_thread_count: 2,
+            queue_capacity: 1024,
+            retry_after: Duration::from_secs(1),
+            max_header_size: 80_000, // 80KB
+            max_header_count: 100,
+            max_uri_length: 8_000, // 8KB
+            max_body_size: 10_000_000, // 10MB
+            lenient_parsing: false,
+            header_read_timeout: Duration::from_secs(10),
+            body_read_timeout: Duration::from_secs(30),
+            keep_alive_timeout: Duration::from_secs(5),
+            max_pipelined_requests: 16,
+            write_timeout: Duration::from_secs(10),
+            shutdown_timeout: Duration::from_secs(30),
+            handle_signals: false,
+            http2: false,
+            max_concurrent_streams: 100,
+            max_upgraded_connections: 1024,
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 }
@@ -42,6 +122,133 @@ impl ServerBuilder {
         self
     }
 
+    pub fn io_mode(mut self, io_mode: IoMode) -> Self {
+        self.server_config.io_mode = io_mode;
+
+        self
+    }
+
+    pub fn reactor_thread_count(mut self, reactor_thread_count: u16) -> Self {
+        self.server_config.reactor_thread_count = reactor_thread_count;
+
+        self
+    }
+
+    pub fn executor_thread_count(mut self, executor_thread_count: u16) -> Self {
+        self.server_config.executor_thread_count = executor_thread_count;
+
+        self
+    }
+
+    pub fn queue_capacity(mut self, queue_capacity: usize) -> Self {
+        self.server_config.queue_capacity = queue_capacity;
+
+        self
+    }
+
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.server_config.retry_after = retry_after;
+
+        self
+    }
+
+    pub fn max_header_size(mut self, max_header_size: usize) -> Self {
+        self.server_config.max_header_size = max_header_size;
+
+        self
+    }
+
+    pub fn max_header_count(mut self, max_header_count: usize) -> Self {
+        self.server_config.max_header_count = max_header_count;
+
+        self
+    }
+
+    pub fn max_uri_length(mut self, max_uri_length: usize) -> Self {
+        self.server_config.max_uri_length = max_uri_length;
+
+        self
+    }
+
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.server_config.max_body_size = max_body_size;
+
+        self
+    }
+
+    pub fn lenient_parsing(mut self, lenient_parsing: bool) -> Self {
+        self.server_config.lenient_parsing = lenient_parsing;
+
+        self
+    }
+
+    pub fn header_read_timeout(mut self, header_read_timeout: Duration) -> Self {
+        self.server_config.header_read_timeout = header_read_timeout;
+
+        self
+    }
+
+    pub fn body_read_timeout(mut self, body_read_timeout: Duration) -> Self {
+        self.server_config.body_read_timeout = body_read_timeout;
+
+        self
+    }
+
+    pub fn keep_alive_timeout(mut self, keep_alive_timeout: Duration) -> Self {
+        self.server_config.keep_alive_timeout = keep_alive_timeout;
+
+        self
+    }
+
+    pub fn max_pipelined_requests(mut self, max_pipelined_requests: usize) -> Self {
+        self.server_config.max_pipelined_requests = max_pipelined_requests;
+
+        self
+    }
+
+    pub fn write_timeout(mut self, write_timeout: Duration) -> Self {
+        self.server_config.write_timeout = write_timeout;
+
+        self
+    }
+
+    pub fn shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.server_config.shutdown_timeout = shutdown_timeout;
+
+        self
+    }
+
+    pub fn handle_signals(mut self, handle_signals: bool) -> Self {
+        self.server_config.handle_signals = handle_signals;
+
+        self
+    }
+
+    pub fn http2(mut self, http2: bool) -> Self {
+        self.server_config.http2 = http2;
+
+        self
+    }
+
+    pub fn max_concurrent_streams(mut self, max_concurrent_streams: u32) -> Self {
+        self.server_config.max_concurrent_streams = max_concurrent_streams;
+
+        self
+    }
+
+    pub fn max_upgraded_connections(mut self, max_upgraded_connections: usize) -> Self {
+        self.server_config.max_upgraded_connections = max_upgraded_connections;
+
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    pub fn tls(mut self, tls_config: TlsConfig) -> Self {
+        self.server_config.tls = Some(tls_config);
+
+        self
+    }
+
     pub fn build(self) -> Server {
         Server::new(self.server_config)
     }
@@ -0,0 +1,117 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use signal_hook::consts::{SIGINT, SIGTERM};
+#[cfg(unix)]
+use signal_hook::iterator::Signals;
+
+const WAKE_UP_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct ShutdownState {
+    is_requested: AtomicBool,
+    // address of the listener, to wake up the blocking accept loop
+    local_addr: Mutex<Option<SocketAddr>>,
+}
+
+// handle to stop a running Server.
+// the server stops accepting, lets in-flight requests finish and then returns from 'Server::run'.
+#[derive(Clone, Default)]
+pub struct ShutdownHandle {
+    state: Arc<ShutdownState>,
+}
+
+impl ShutdownHandle {
+    pub fn new() -> Self {
+        ShutdownHandle::default()
+    }
+
+    pub fn shutdown(&self) {
+        if self.state.is_requested.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        println!("shutdown is requested");
+        let local_addr = self.state.local_addr.lock().ok().and_then(|local_addr| *local_addr);
+
+        // accept() has no timeout, so a connection is made to let the accept loop check the flag
+        if let Some(local_addr) = local_addr {
+            if let Err(error) = TcpStream::connect_timeout(&wake_up_addr(local_addr), WAKE_UP_TIMEOUT) {
+                eprintln!("[error] fail to wake up the accept loop: {}", error);
+            }
+        }
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.state.is_requested.load(Ordering::SeqCst)
+    }
+
+    // shutdown the server on SIGINT or SIGTERM, the second signal exits the process immediately
+    #[cfg(unix)]
+    pub fn register_signals(&self) -> io::Result<()> {
+        let mut signals = Signals::new([SIGINT, SIGTERM])?;
+        let handle = self.clone();
+
+        thread::spawn(move || {
+            for signal in signals.forever() {
+                if handle.is_shutdown() {
+                    eprintln!("[error] signal {} is received again, exit immediately", signal);
+                    std::process::exit(1);
+                }
+
+                println!("signal {} is received", signal);
+                handle.shutdown();
+            }
+        });
+
+        Ok(())
+    }
+
+    // only a flag can be set by a signal on the other targets, so it is polled
+    #[cfg(not(unix))]
+    pub fn register_signals(&self) -> io::Result<()> {
+        let is_signaled = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(SIGINT, is_signaled.clone())?;
+        signal_hook::flag::register(SIGTERM, is_signaled.clone())?;
+        let handle = self.clone();
+
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(100));
+            if !is_signaled.swap(false, Ordering::SeqCst) {
+                continue;
+            }
+
+            if handle.is_shutdown() {
+                eprintln!("[error] a signal is received again, exit immediately");
+                std::process::exit(1);
+            }
+            println!("a signal is received");
+            handle.shutdown();
+        });
+
+        Ok(())
+    }
+
+    pub(crate) fn set_local_addr(&self, local_addr: SocketAddr) {
+        if let Ok(mut guard) = self.state.local_addr.lock() {
+            *guard = Some(local_addr);
+        }
+    }
+}
+
+// unspecified addresses (e.g. 0.0.0.0) can not be connected
+fn wake_up_addr(local_addr: SocketAddr) -> SocketAddr {
+    match local_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), local_addr.port())
+        }
+        IpAddr::V6(ip) if ip.is_unspecified() => {
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::LOCALHOST), local_addr.port())
+        }
+        _ => local_addr,
+    }
+}
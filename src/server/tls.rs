@@ -0,0 +1,812 @@
+use std::convert::TryFrom;
+use std::io::{self, ErrorKind};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use rustls::{RootCertStore, ServerConnection};
+use rustls::crypto::CryptoProvider;
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::pki_types::pem::PemObject;
+use rustls::server::WebPkiClientVerifier;
+use rustls::server::danger::ClientCertVerifier;
+use rustls::sign::CertifiedKey;
+use x509_parser::certificate::X509Certificate;
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::FromDer;
+
+use crate::http::{ClientCert, TlsInfo};
+use crate::server::cert_resolver::CertResolver;
+use crate::server::cert_watcher::CertWatcher;
+
+// certificates and options for TLS, the files are loaded when the server runs
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    // PEM file of the certificate chain, the leaf certificate first
+    cert_path: PathBuf,
+    // PEM file of the private key
+    key_path: PathBuf,
+    // offered with ALPN in the order of preference
+    alpn_protocols: Vec<String>,
+    // (server name, cert path, key path), selected by the host name sent with SNI.
+    // the default certificate is used for the other names.
+    sni_certs: Vec<(String, PathBuf, PathBuf)>,
+    // the files are checked for changes at this interval, and reloaded without a restart
+    reload_interval: Option<Duration>,
+    // PEM file of the CA certificates which verify the client certificates
+    client_ca_path: Option<PathBuf>,
+    // a client without a certificate fails the handshake
+    is_client_cert_required: bool,
+}
+
+impl TlsConfig {
+    pub fn new<P: AsRef<Path>, Q: AsRef<Path>>(cert_path: P, key_path: Q) -> Self {
+        TlsConfig {
+            cert_path: cert_path.as_ref().to_path_buf(),
+            key_path: key_path.as_ref().to_path_buf(),
+            alpn_protocols: vec!["http/1.1".to_string()],
+            sni_certs: vec![],
+            reload_interval: None,
+            client_ca_path: None,
+            is_client_cert_required: false,
+        }
+    }
+
+    // a name can be a wildcard, e.g. '*.example.com'
+    pub fn sni_cert<P: AsRef<Path>, Q: AsRef<Path>>(
+        mut self,
+        server_name: &str,
+        cert_path: P,
+        key_path: Q,
+    ) -> Self {
+        self.sni_certs.push((
+            server_name.to_lowercase(),
+            cert_path.as_ref().to_path_buf(),
+            key_path.as_ref().to_path_buf(),
+        ));
+        self
+    }
+
+    // the certificates are also reloaded on SIGHUP when the server handles signals
+    pub fn reload_interval(mut self, reload_interval: Duration) -> Self {
+        self.reload_interval = Some(reload_interval);
+        self
+    }
+
+    // a client may connect without a certificate, but a presented one has to be valid
+    pub fn request_client_cert<P: AsRef<Path>>(mut self, ca_path: P) -> Self {
+        self.client_ca_path = Some(ca_path.as_ref().to_path_buf());
+        self.is_client_cert_required = false;
+        self
+    }
+
+    pub fn require_client_cert<P: AsRef<Path>>(mut self, ca_path: P) -> Self {
+        self.client_ca_path = Some(ca_path.as_ref().to_path_buf());
+        self.is_client_cert_required = true;
+        self
+    }
+
+    pub fn alpn_protocols(mut self, alpn_protocols: &[&str]) -> Self {
+        self.alpn_protocols = alpn_protocols.iter().map(|protocol| protocol.to_string()).collect();
+        self
+    }
+
+    pub fn get_cert_path(&self) -> &Path {
+        &self.cert_path
+    }
+
+    pub fn get_key_path(&self) -> &Path {
+        &self.key_path
+    }
+
+    pub fn get_alpn_protocols(&self) -> &[String] {
+        &self.alpn_protocols
+    }
+
+    pub fn get_sni_certs(&self) -> &[(String, PathBuf, PathBuf)] {
+        &self.sni_certs
+    }
+
+    pub fn get_reload_interval(&self) -> Option<Duration> {
+        self.reload_interval
+    }
+
+    pub fn get_client_ca_path(&self) -> Option<&Path> {
+        self.client_ca_path.as_deref()
+    }
+
+    pub fn is_client_cert_required(&self) -> bool {
+        self.is_client_cert_required
+    }
+}
+
+// starts the TLS session of an accepted connection
+#[derive(Clone)]
+pub(crate) struct TlsAcceptor {
+    config: Arc<rustls::ServerConfig>,
+    cert_resolver: Arc<CertResolver>,
+}
+
+impl TlsAcceptor {
+    // 'h2' is preferred in ALPN when HTTP/2 is enabled
+    pub fn new(tls_config: &TlsConfig, is_http2_enabled: bool) -> io::Result<Self> {
+        let provider = Arc::new(default_provider());
+        let cert_resolver = Arc::new(CertResolver::new(tls_config.clone(), provider.clone())?);
+
+        let client_cert_verifier = make_client_cert_verifier(tls_config, &provider)?;
+
+        let mut config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|error| invalid_data("fail to configure TLS", error))?
+            .with_client_cert_verifier(client_cert_verifier)
+            .with_cert_resolver(cert_resolver.clone());
+        config.alpn_protocols = tls_config
+            .get_alpn_protocols()
+            .iter()
+            .map(|protocol| protocol.as_bytes().to_vec())
+            .collect();
+        if is_http2_enabled && !config.alpn_protocols.iter().any(|protocol| protocol == b"h2") {
+            config.alpn_protocols.insert(0, b"h2".to_vec());
+        }
+
+        Ok(TlsAcceptor {
+            config: Arc::new(config),
+            cert_resolver,
+        })
+    }
+
+    // the handshake is driven by the reads and the writes of the connection
+    pub fn accept(&self) -> io::Result<ServerConnection> {
+        ServerConnection::new(Arc::clone(&self.config))
+            .map_err(|error| invalid_data("fail to start a TLS session", error))
+    }
+
+    // reload the certificates while the server runs, until the watcher is dropped.
+    // none if neither the reload interval nor the signals are configured.
+    pub fn watch(&self, handle_signals: bool) -> io::Result<Option<CertWatcher>> {
+        let reload_interval = self.cert_resolver.get_tls_config().get_reload_interval();
+        if reload_interval.is_none() && !handle_signals {
+            return Ok(None);
+        }
+
+        CertWatcher::start(self.cert_resolver.clone(), reload_interval, handle_signals).map(Some)
+    }
+}
+
+// none until the handshake has finished
+pub(crate) fn get_tls_info(connection: &ServerConnection) -> Option<TlsInfo> {
+    if connection.is_handshaking() {
+        return None;
+    }
+
+    Some(TlsInfo::new(
+        connection.server_name().map(str::to_string),
+        connection
+            .alpn_protocol()
+            .map(|protocol| String::from_utf8_lossy(protocol).to_string()),
+        connection.protocol_version().map(|version| format!("{:?}", version)),
+        connection
+            .negotiated_cipher_suite()
+            .map(|cipher_suite| format!("{:?}", cipher_suite.suite())),
+        connection
+            .peer_certificates()
+            .and_then(|certs| certs.first())
+            .and_then(|cert| parse_client_cert(cert)),
+    ))
+}
+
+fn make_client_cert_verifier(
+    tls_config: &TlsConfig,
+    provider: &Arc<CryptoProvider>,
+) -> io::Result<Arc<dyn ClientCertVerifier>> {
+    let ca_path = if let Some(ca_path) = tls_config.get_client_ca_path() {
+        ca_path
+    } else {
+        return Ok(WebPkiClientVerifier::no_client_auth());
+    };
+
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(ca_path)? {
+        roots
+            .add(cert)
+            .map_err(|error| invalid_data("invalid CA certificate", error))?;
+    }
+
+    let builder = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone());
+    let builder = if tls_config.is_client_cert_required() {
+        builder
+    } else {
+        builder.allow_unauthenticated()
+    };
+
+    builder
+        .build()
+        .map_err(|error| invalid_data("fail to configure the client authentication", error))
+}
+
+// the certificate has been verified by the handshake, a failure here only loses the details
+fn parse_client_cert(cert: &CertificateDer<'_>) -> Option<ClientCert> {
+    let (_, x509) = if let Ok(parsed) = X509Certificate::from_der(cert) {
+        parsed
+    } else {
+        return None;
+    };
+
+    let subject = x509.subject();
+    let common_name = subject
+        .iter_common_name()
+        .next()
+        .and_then(|common_name| common_name.as_str().ok())
+        .map(str::to_string);
+    let mut client_cert = ClientCert::new(subject.to_string(), common_name, cert.to_vec());
+
+    if let Ok(Some(subject_alt_name)) = x509.subject_alternative_name() {
+        for general_name in &subject_alt_name.value.general_names {
+            match general_name {
+                GeneralName::DNSName(dns_name) => client_cert.add_dns_name(dns_name.to_string()),
+                GeneralName::RFC822Name(email) => client_cert.add_email_address(email.to_string()),
+                GeneralName::URI(uri) => client_cert.add_uri(uri.to_string()),
+                GeneralName::IPAddress(bytes) => {
+                    if let Some(ip_address) = parse_ip_address(bytes) {
+                        client_cert.add_ip_address(ip_address);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Some(client_cert)
+}
+
+fn parse_ip_address(bytes: &[u8]) -> Option<IpAddr> {
+    if let Ok(octets) = <[u8; 4]>::try_from(bytes) {
+        Some(IpAddr::from(octets))
+    } else if let Ok(octets) = <[u8; 16]>::try_from(bytes) {
+        Some(IpAddr::from(octets))
+    } else {
+        None
+    }
+}
+
+// the private key is checked against the certificate
+pub(crate) fn load_certified_key(
+    cert_path: &Path,
+    key_path: &Path,
+    provider: &CryptoProvider,
+) -> io::Result<CertifiedKey> {
+    let certs = load_certs(cert_path)?;
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .map_err(|error| invalid_data("fail to load the private key", error))?;
+
+    CertifiedKey::from_der(certs, key, provider)
+        .map_err(|error| invalid_data("invalid certificate or key", error))
+}
+
+fn load_certs(cert_path: &Path) -> io::Result<Vec<CertificateDer<'static>>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|error| invalid_data("fail to load the certificates", error))?;
+
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("no certificate in {}", cert_path.display()),
+        ));
+    }
+
+    Ok(certs)
+}
+
+fn invalid_data<E: std::fmt::Display>(desc: &str, error: E) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("{}: {}", desc, error))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+    use std::fs;
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread;
+    use std::time::Duration;
+
+    use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+    use crate::http::{ContentType, HttpStatus};
+    use crate::route::{ActionRoute, StaticRoute};
+    use crate::server::{IoMode, Server, ServerBuilder, ShutdownHandle};
+    use crate::util::random::random_hex;
+
+    use super::*;
+
+    struct TestCert {
+        dir: PathBuf,
+        server_name: String,
+        cert_der: CertificateDer<'static>,
+    }
+
+    impl TestCert {
+        // a self-signed certificate for 'localhost'
+        fn generate() -> Self {
+            Self::generate_for("localhost")
+        }
+
+        fn generate_for(server_name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("lite-ws-tls-{}", random_hex(8)));
+            fs::create_dir_all(&dir).unwrap();
+
+            let mut test_cert = TestCert {
+                dir,
+                server_name: server_name.to_string(),
+                cert_der: CertificateDer::from(vec![]),
+            };
+            test_cert.regenerate();
+
+            test_cert
+        }
+
+        // overwrite the files with a new certificate for the same name
+        fn regenerate(&mut self) {
+            let certified = rcgen::generate_simple_self_signed(vec![self.server_name.clone()]).unwrap();
+            fs::write(self.dir.join("cert.pem"), certified.cert.pem()).unwrap();
+            fs::write(self.dir.join("key.pem"), certified.key_pair.serialize_pem()).unwrap();
+            self.cert_der = certified.cert.der().clone();
+        }
+
+        fn tls_config(&self) -> TlsConfig {
+            TlsConfig::new(self.dir.join("cert.pem"), self.dir.join("key.pem"))
+        }
+
+        fn connect(&self, local_addr: SocketAddr) -> StreamOwned<ClientConnection, TcpStream> {
+            self.connect_with(local_addr, None)
+        }
+
+        // optionally presents a client certificate chain and its key
+        fn connect_with(
+            &self,
+            local_addr: SocketAddr,
+            client_auth: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+        ) -> StreamOwned<ClientConnection, TcpStream> {
+            self.connect_with_alpn(local_addr, client_auth, &[b"http/1.1"])
+        }
+
+        fn connect_with_alpn(
+            &self,
+            local_addr: SocketAddr,
+            client_auth: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+            alpn_protocols: &[&[u8]],
+        ) -> StreamOwned<ClientConnection, TcpStream> {
+            let mut roots = RootCertStore::empty();
+            roots.add(self.cert_der.clone()).unwrap();
+
+            let builder = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots);
+            let mut config = match client_auth {
+                Some((certs, key)) => builder.with_client_auth_cert(certs, key).unwrap(),
+                None => builder.with_no_client_auth(),
+            };
+            config.alpn_protocols = alpn_protocols.iter().map(|protocol| protocol.to_vec()).collect();
+
+            let server_name = self.server_name.clone().try_into().unwrap();
+            let connection = ClientConnection::new(Arc::new(config), server_name).unwrap();
+            StreamOwned::new(connection, TcpStream::connect(local_addr).unwrap())
+        }
+    }
+
+    impl Drop for TestCert {
+        fn drop(&mut self) {
+            fs::remove_dir_all(&self.dir).ok();
+        }
+    }
+
+    fn read_response<R: Read>(stream: &mut R) -> (String, Vec<u8>) {
+        let mut raw = vec![];
+        let mut buffer = [0_u8; 1];
+        while !raw.ends_with(b"\r\n\r\n") {
+            if stream.read(&mut buffer).unwrap() == 0 {
+                break;
+            }
+            raw.push(buffer[0]);
+        }
+
+        let head = String::from_utf8(raw).unwrap();
+        let content_length = head
+            .lines()
+            .find_map(|line| line.strip_prefix("Content-Length: "))
+            .map(|length| length.parse::<usize>().unwrap())
+            .unwrap_or(0);
+        let mut body = vec![0_u8; content_length];
+        stream.read_exact(&mut body).unwrap();
+
+        (head, body)
+    }
+
+    fn run_server(mut server: Server) -> (SocketAddr, thread::JoinHandle<()>, ShutdownHandle) {
+        let local_addr = server.bind().unwrap();
+        let shutdown_handle = server.shutdown_handle();
+        let join_handle = thread::spawn(move || server.run().unwrap());
+
+        (local_addr, join_handle, shutdown_handle)
+    }
+
+    #[test]
+    fn test_tls() {
+        let test_cert = TestCert::generate();
+        let static_root = format!("/target/lite-ws-tls-static-{}", random_hex(4));
+        fs::create_dir_all(&static_root[1..]).unwrap();
+        let content: Vec<u8> = (0..2_000_000).map(|i| (i % 251) as u8).collect();
+        fs::write(format!("{}/large.png", &static_root[1..]), &content).unwrap();
+
+        for io_mode in [IoMode::ThreadPerConnection, IoMode::Reactor] {
+            let server = ServerBuilder::default()
+                .port_num(0)
+                .io_mode(io_mode)
+                .shutdown_timeout(Duration::from_secs(5))
+                .tls(test_cert.tls_config())
+                .build()
+                .mount_route(
+                    ActionRoute::new_get("/tls", |req, builder| {
+                        let tls_info = req.get_tls_info().cloned().unwrap_or_default();
+                        let body = format!(
+                            "{:?} {:?} {:?}",
+                            tls_info.get_server_name(),
+                            tls_info.get_alpn_protocol(),
+                            tls_info.get_protocol_version(),
+                        );
+                        let response = builder
+                            .set_status(HttpStatus::OK)
+                            .body(ContentType::TEXT_PLAIN, body.into_bytes())
+                            .build()
+                            .unwrap();
+
+                        (req, response)
+                    })
+                        .unwrap(),
+                )
+                .mount_route(StaticRoute::new(
+                    static_root.clone(),
+                    Some("/static".to_string()),
+                    Some(vec![".png".to_string()]),
+                ));
+            let (local_addr, join_handle, shutdown_handle) = run_server(server);
+            let mut stream = test_cert.connect(local_addr);
+
+            // pipelined requests are decrypted together
+            stream
+                .write_all(b"GET /tls HTTP/1.1\r\n\r\nGET /static/large.png HTTP/1.1\r\n\r\n")
+                .unwrap();
+            let (head, body) = read_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 200 OK"));
+            assert_eq!(
+                String::from_utf8(body).unwrap(),
+                "Some(\"localhost\") Some(\"http/1.1\") Some(\"TLSv1_3\")"
+            );
+
+            let (head, body) = read_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 200 OK"));
+            assert!(body == content);
+
+            stream.write_all(b"GET /tls HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+            let (head, _) = read_response(&mut stream);
+            assert!(head.contains("Connection: close"));
+
+            // a plain request is not served
+            let mut plain = TcpStream::connect(local_addr).unwrap();
+            plain.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            plain.write_all(b"GET /tls HTTP/1.1\r\n\r\n").unwrap();
+            let mut received = vec![];
+            plain.read_to_end(&mut received).ok();
+            assert!(!received.starts_with(b"HTTP/1.1"));
+
+            shutdown_handle.shutdown();
+            join_handle.join().unwrap();
+        }
+
+        fs::remove_dir_all(&static_root[1..]).unwrap();
+    }
+
+    #[test]
+    fn test_http2() {
+        use crate::h2::test_client::TestClient;
+
+        let test_cert = TestCert::generate();
+
+        for io_mode in [IoMode::ThreadPerConnection, IoMode::Reactor] {
+            let server = ServerBuilder::default()
+                .port_num(0)
+                .io_mode(io_mode)
+                .http2(true)
+                .shutdown_timeout(Duration::from_secs(5))
+                .tls(test_cert.tls_config())
+                .build();
+            let (local_addr, join_handle, shutdown_handle) = run_server(mount_hello(server));
+
+            // negotiated with ALPN, the client starts with the preface
+            let mut stream = test_cert.connect_with_alpn(local_addr, None, &[b"h2", b"http/1.1"]);
+            let mut client = TestClient::new();
+            let mut input = client.start(&[]);
+            input.extend(client.request(1, "GET", "/hello", &[], b""));
+            stream.write_all(&input).unwrap();
+            let response = client.read_response(&mut stream, 1);
+            assert_eq!(stream.conn.alpn_protocol(), Some(&b"h2"[..]));
+            assert_eq!(response.status, 200);
+            assert_eq!(response.body, b"hello");
+
+            // a client without HTTP/2 still gets HTTP/1.1
+            let mut stream = test_cert.connect(local_addr);
+            stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+            let (head, body) = read_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 200 OK"));
+            assert_eq!(body, b"hello");
+
+            shutdown_handle.shutdown();
+            join_handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_websocket() {
+        use crate::websocket::{Message, WebSocketRoute};
+        use crate::websocket::frame::{self, TEXT};
+
+        let test_cert = TestCert::generate();
+
+        for io_mode in [IoMode::ThreadPerConnection, IoMode::Reactor] {
+            let route = WebSocketRoute::new("/echo", |_, mut websocket| {
+                while let Ok(message) = websocket.receive() {
+                    if let Message::Text(text) = message {
+                        websocket.send_text(&text).unwrap();
+                    }
+                }
+            })
+                .unwrap();
+            let server = ServerBuilder::default()
+                .port_num(0)
+                .io_mode(io_mode)
+                .shutdown_timeout(Duration::from_secs(5))
+                .tls(test_cert.tls_config())
+                .build()
+                .mount_route(route);
+            let (local_addr, join_handle, shutdown_handle) = run_server(server);
+
+            // the upgraded connection keeps the TLS session
+            let mut stream = test_cert.connect(local_addr);
+            stream.sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+            let mut input = b"GET /echo HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade\r\n\
+                Upgrade: websocket\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                Sec-WebSocket-Version: 13\r\n\r\n"
+                .to_vec();
+            frame::write_masked_frame(&mut input, true, TEXT, b"over tls");
+            stream.write_all(&input).unwrap();
+            let (head, _) = read_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 101 "));
+            assert_eq!(frame::read_frame(&mut stream).unwrap().payload, b"over tls");
+
+            let mut input = vec![];
+            frame::write_masked_frame(&mut input, true, TEXT, b"again");
+            stream.write_all(&input).unwrap();
+            assert_eq!(frame::read_frame(&mut stream).unwrap().payload, b"again");
+
+            drop(stream);
+            shutdown_handle.shutdown();
+            join_handle.join().unwrap();
+        }
+    }
+
+    fn get_peer_cert(stream: &StreamOwned<ClientConnection, TcpStream>) -> CertificateDer<'static> {
+        stream.conn.peer_certificates().unwrap()[0].clone().into_owned()
+    }
+
+    fn mount_hello(server: Server) -> Server {
+        server.mount_route(
+            ActionRoute::new_get("/hello", |req, builder| {
+                let response = builder
+                    .set_status(HttpStatus::OK)
+                    .body(ContentType::TEXT_PLAIN, b"hello".to_vec())
+                    .build()
+                    .unwrap();
+
+                (req, response)
+            })
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_sni_cert() {
+        let default_cert = TestCert::generate();
+        let sni_cert = TestCert::generate_for("example.test");
+        let wildcard_cert = TestCert::generate_for("www.wildcard.test");
+
+        let tls_config = default_cert
+            .tls_config()
+            .sni_cert("Example.Test", sni_cert.dir.join("cert.pem"), sni_cert.dir.join("key.pem"))
+            .sni_cert(
+                "*.wildcard.test",
+                wildcard_cert.dir.join("cert.pem"),
+                wildcard_cert.dir.join("key.pem"),
+            );
+        let server = ServerBuilder::default().port_num(0).tls(tls_config).build();
+        let (local_addr, join_handle, shutdown_handle) = run_server(mount_hello(server));
+
+        for test_cert in [&default_cert, &sni_cert, &wildcard_cert] {
+            let mut stream = test_cert.connect(local_addr);
+            stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+            let (head, body) = read_response(&mut stream);
+            assert!(head.starts_with("HTTP/1.1 200 OK"));
+            assert_eq!(body, b"hello");
+            assert!(get_peer_cert(&stream) == test_cert.cert_der);
+        }
+
+        shutdown_handle.shutdown();
+        join_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_reload_cert() {
+        let mut test_cert = TestCert::generate();
+        let tls_config = test_cert.tls_config().reload_interval(Duration::from_millis(100));
+        let server = ServerBuilder::default().port_num(0).tls(tls_config).build();
+        let (local_addr, join_handle, shutdown_handle) = run_server(mount_hello(server));
+
+        let mut old_stream = test_cert.connect(local_addr);
+        old_stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+        let (head, _) = read_response(&mut old_stream);
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        let old_cert_der = get_peer_cert(&old_stream);
+
+        // the client trusts only the new certificate
+        test_cert.regenerate();
+        let mut reloaded = false;
+        for _ in 0..50 {
+            let mut stream = test_cert.connect(local_addr);
+            if stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").is_ok() {
+                let (head, _) = read_response(&mut stream);
+                assert!(head.starts_with("HTTP/1.1 200 OK"));
+                assert!(get_peer_cert(&stream) == test_cert.cert_der);
+                reloaded = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        assert!(reloaded);
+
+        // the existing connection is kept
+        old_stream.write_all(b"GET /hello HTTP/1.1\r\n\r\n").unwrap();
+        let (head, body) = read_response(&mut old_stream);
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(body, b"hello");
+        assert!(get_peer_cert(&old_stream) == old_cert_der);
+
+        shutdown_handle.shutdown();
+        join_handle.join().unwrap();
+    }
+
+    // a CA which issues a client certificate with the subject alternative names
+    fn generate_client_cert(
+        dir: &Path,
+    ) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+        use rcgen::{
+            BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair,
+            SanType,
+        };
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(vec![]).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(DnType::CommonName, "Test CA");
+        let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+        fs::write(dir.join("ca.pem"), ca_cert.pem()).unwrap();
+
+        let client_key = KeyPair::generate().unwrap();
+        let mut client_params =
+            CertificateParams::new(vec!["service-a.internal".to_string()]).unwrap();
+        client_params.distinguished_name.push(DnType::CommonName, "service-a");
+        client_params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        client_params
+            .subject_alt_names
+            .push(SanType::Rfc822Name("ops@example.test".try_into().unwrap()));
+        client_params
+            .subject_alt_names
+            .push(SanType::URI("spiffe://example.test/service-a".try_into().unwrap()));
+        client_params
+            .subject_alt_names
+            .push(SanType::IpAddress("10.0.0.1".parse().unwrap()));
+        let client_cert = client_params.signed_by(&client_key, &ca_cert, &ca_key).unwrap();
+
+        let key = PrivateKeyDer::try_from(client_key.serialize_der()).unwrap();
+        (vec![client_cert.der().clone()], key)
+    }
+
+    fn mount_client_cert(server: Server) -> Server {
+        server.mount_route(
+            ActionRoute::new_get("/client", |req, builder| {
+                let body = match req.get_client_cert() {
+                    Some(client_cert) => format!(
+                        "{:?} {:?} {:?} {:?} {:?}",
+                        client_cert.get_common_name(),
+                        client_cert.get_dns_names(),
+                        client_cert.get_email_addresses(),
+                        client_cert.get_uris(),
+                        client_cert.get_ip_addresses(),
+                    ),
+                    None => "anonymous".to_string(),
+                };
+                let response = builder
+                    .set_status(HttpStatus::OK)
+                    .body(ContentType::TEXT_PLAIN, body.into_bytes())
+                    .build()
+                    .unwrap();
+
+                (req, response)
+            })
+                .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_client_cert() {
+        let test_cert = TestCert::generate();
+        let client_auth = generate_client_cert(&test_cert.dir);
+        let ca_path = test_cert.dir.join("ca.pem");
+        let expected = "Some(\"service-a\") [\"service-a.internal\"] [\"ops@example.test\"] \
+                        [\"spiffe://example.test/service-a\"] [10.0.0.1]";
+
+        // required
+        let tls_config = test_cert.tls_config().require_client_cert(&ca_path);
+        let server = ServerBuilder::default().port_num(0).tls(tls_config).build();
+        let (local_addr, join_handle, shutdown_handle) = run_server(mount_client_cert(server));
+
+        let mut stream = test_cert.connect_with(local_addr, Some((client_auth.0.clone(), client_auth.1.clone_key())));
+        stream.write_all(b"GET /client HTTP/1.1\r\n\r\n").unwrap();
+        let (head, body) = read_response(&mut stream);
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+        assert!(stream.conn.peer_certificates().is_some());
+
+        let mut stream = test_cert.connect(local_addr);
+        stream.sock.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        stream.write_all(b"GET /client HTTP/1.1\r\n\r\n").ok();
+        let mut received = vec![];
+        stream.read_to_end(&mut received).ok();
+        assert!(received.is_empty());
+
+        shutdown_handle.shutdown();
+        join_handle.join().unwrap();
+
+        // requested
+        let tls_config = test_cert.tls_config().request_client_cert(&ca_path);
+        let server = ServerBuilder::default().port_num(0).tls(tls_config).build();
+        let (local_addr, join_handle, shutdown_handle) = run_server(mount_client_cert(server));
+
+        let mut stream = test_cert.connect_with(local_addr, Some(client_auth));
+        stream.write_all(b"GET /client HTTP/1.1\r\n\r\n").unwrap();
+        let (_, body) = read_response(&mut stream);
+        assert_eq!(String::from_utf8(body).unwrap(), expected);
+
+        let mut stream = test_cert.connect(local_addr);
+        stream.write_all(b"GET /client HTTP/1.1\r\n\r\n").unwrap();
+        let (head, body) = read_response(&mut stream);
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+        assert_eq!(body, b"anonymous");
+
+        shutdown_handle.shutdown();
+        join_handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_invalid_certificate() {
+        let mut server = ServerBuilder::default()
+            .port_num(0)
+            .tls(TlsConfig::new("no-such-cert.pem", "no-such-key.pem"))
+            .build();
+
+        let error = server.run().err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+}
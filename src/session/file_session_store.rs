@@ -0,0 +1,191 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::session::{SessionData, SessionError, SessionStore};
+use crate::session::session::is_valid_session_id;
+use crate::util::percent::{percent_decode, percent_encode};
+use crate::util::random::random_hex;
+
+const FILE_EXTENSION: &str = "session";
+// '=' and line breaks would break the line format
+const RESERVED: &[u8] = b"=\r\n";
+
+// keeps each session in a file named after the session id.
+// the first line of a file is the expiry time (unix seconds), the others are 'key=value' lines.
+pub struct FileSessionStore {
+    directory: PathBuf,
+}
+
+impl FileSessionStore {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> Result<Self, SessionError> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(FileSessionStore { directory })
+    }
+
+    // remove every expired session file, returns the number of removed sessions
+    pub fn sweep(&self) -> Result<usize, SessionError> {
+        let now = unix_now();
+        let mut removed = 0;
+
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(FILE_EXTENSION) {
+                continue;
+            }
+
+            let is_expired = match fs::read_to_string(&path) {
+                Ok(content) => decode(&content).is_none_or(|(expires_at, _)| expires_at <= now),
+                Err(_) => false,
+            };
+
+            if is_expired && fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+
+    fn file_path(&self, session_id: &str) -> Result<PathBuf, SessionError> {
+        // the id becomes a file name, so it must not be able to escape the directory
+        if !is_valid_session_id(session_id) {
+            return Err(SessionError::InvalidSessionId);
+        }
+
+        Ok(self
+            .directory
+            .join(format!("{}.{}", session_id, FILE_EXTENSION)))
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn load(&self, session_id: &str) -> Result<Option<SessionData>, SessionError> {
+        let path = self.file_path(session_id)?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error.into()),
+        };
+
+        let (expires_at, data) = decode(&content).ok_or(SessionError::StoreCorrupted)?;
+        if expires_at <= unix_now() {
+            fs::remove_file(&path).ok();
+            return Ok(None);
+        }
+
+        Ok(Some(data))
+    }
+
+    fn save(&self, session_id: &str, data: &SessionData, ttl: Duration) -> Result<(), SessionError> {
+        let path = self.file_path(session_id)?;
+        // unique per save, so concurrent saves of a session never write the same temporary file
+        let temp_path = path.with_extension(format!("{}.{}.tmp", FILE_EXTENSION, random_hex(8)));
+
+        // write to the temporary file first, so a reader never sees a half-written session
+        fs::write(&temp_path, encode(unix_now() + ttl.as_secs(), data))?;
+        fs::rename(&temp_path, &path)?;
+
+        Ok(())
+    }
+
+    fn remove(&self, session_id: &str) -> Result<(), SessionError> {
+        match fs::remove_file(self.file_path(session_id)?) {
+            Err(error) if error.kind() != ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+fn encode(expires_at: u64, data: &SessionData) -> String {
+    let mut content = format!("{}\n", expires_at);
+
+    for (key, value) in data.iter() {
+        content.push_str(&format!(
+            "{}={}\n",
+            percent_encode(key, RESERVED),
+            percent_encode(value, RESERVED)
+        ));
+    }
+
+    content
+}
+
+fn decode(content: &str) -> Option<(u64, SessionData)> {
+    let mut lines = content.lines();
+    let expires_at = lines.next()?.parse::<u64>().ok()?;
+    let mut data = SessionData::new();
+
+    for line in lines {
+        let mut split = line.splitn(2, '=');
+        let key = percent_decode(split.next()?)?;
+        let value = percent_decode(split.next()?)?;
+        data.insert(key, value);
+    }
+
+    Some((expires_at, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn temp_store() -> FileSessionStore {
+        let directory = env::temp_dir().join(format!("lite-ws-session-{}", random_hex(8)));
+        FileSessionStore::new(directory).unwrap()
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let store = temp_store();
+        let session_id = random_hex(32);
+        let mut data = SessionData::new();
+        data.insert("greeting".to_string(), "a=b\n100%".to_string());
+
+        store.save(&session_id, &data, Duration::from_secs(60)).unwrap();
+        assert_eq!(store.load(&session_id).unwrap(), Some(data));
+
+        store.remove(&session_id).unwrap();
+        assert_eq!(store.load(&session_id).unwrap(), None);
+
+        fs::remove_dir_all(&store.directory).unwrap();
+    }
+
+    #[test]
+    fn test_expired_session() {
+        let store = temp_store();
+        let session_id = random_hex(32);
+
+        store
+            .save(&session_id, &SessionData::new(), Duration::from_secs(0))
+            .unwrap();
+        assert_eq!(store.sweep().unwrap(), 1);
+        assert_eq!(store.load(&session_id).unwrap(), None);
+
+        fs::remove_dir_all(&store.directory).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_session_id() {
+        let store = temp_store();
+
+        assert!(matches!(
+            store.load("../escape"),
+            Err(SessionError::InvalidSessionId)
+        ));
+
+        fs::remove_dir_all(&store.directory).unwrap();
+    }
+}
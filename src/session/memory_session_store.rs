@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::session::{SessionData, SessionError, SessionStore};
+
+const DEFAULT_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+struct Entry {
+    data: SessionData,
+    expires_at: Instant,
+}
+
+struct Inner {
+    entries: HashMap<String, Entry>,
+    last_sweep: Instant,
+}
+
+// keeps sessions in the process memory.
+// expired sessions are swept while the store is used, at most once per sweep interval.
+pub struct MemorySessionStore {
+    inner: Mutex<Inner>,
+    sweep_interval: Duration,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        MemorySessionStore::with_sweep_interval(DEFAULT_SWEEP_INTERVAL)
+    }
+
+    pub fn with_sweep_interval(sweep_interval: Duration) -> Self {
+        MemorySessionStore {
+            inner: Mutex::new(Inner {
+                entries: HashMap::new(),
+                last_sweep: Instant::now(),
+            }),
+            sweep_interval,
+        }
+    }
+
+    // remove every expired session, returns the number of removed sessions
+    pub fn sweep(&self) -> usize {
+        match self.inner.lock() {
+            Ok(mut inner) => sweep_expired(&mut inner),
+            Err(_) => 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().map(|inner| inner.entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Inner>, SessionError> {
+        let mut inner = self.inner.lock().map_err(|_| SessionError::StoreCorrupted)?;
+
+        if inner.last_sweep.elapsed() >= self.sweep_interval {
+            sweep_expired(&mut inner);
+        }
+
+        Ok(inner)
+    }
+}
+
+impl Default for MemorySessionStore {
+    fn default() -> Self {
+        MemorySessionStore::new()
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn load(&self, session_id: &str) -> Result<Option<SessionData>, SessionError> {
+        let inner = self.lock()?;
+
+        Ok(inner
+            .entries
+            .get(session_id)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.data.clone()))
+    }
+
+    fn save(&self, session_id: &str, data: &SessionData, ttl: Duration) -> Result<(), SessionError> {
+        let mut inner = self.lock()?;
+        inner.entries.insert(
+            session_id.to_string(),
+            Entry {
+                data: data.clone(),
+                expires_at: Instant::now() + ttl,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn remove(&self, session_id: &str) -> Result<(), SessionError> {
+        self.lock()?.entries.remove(session_id);
+
+        Ok(())
+    }
+}
+
+fn sweep_expired(inner: &mut Inner) -> usize {
+    let now = Instant::now();
+    let before = inner.entries.len();
+
+    inner.entries.retain(|_, entry| entry.expires_at > now);
+    inner.last_sweep = now;
+
+    before - inner.entries.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() {
+        let store = MemorySessionStore::new();
+        let mut data = SessionData::new();
+        data.insert("user_id".to_string(), "7".to_string());
+
+        store.save("abc", &data, Duration::from_secs(60)).unwrap();
+        assert_eq!(store.load("abc").unwrap(), Some(data));
+
+        store.remove("abc").unwrap();
+        assert_eq!(store.load("abc").unwrap(), None);
+    }
+
+    #[test]
+    fn test_expiry_sweeping() {
+        let store = MemorySessionStore::with_sweep_interval(Duration::from_millis(10));
+        let data = SessionData::new();
+
+        store.save("short", &data, Duration::from_millis(1)).unwrap();
+        store.save("long", &data, Duration::from_secs(60)).unwrap();
+        thread::sleep(Duration::from_millis(20));
+
+        assert_eq!(store.load("short").unwrap(), None);
+        // the load above has swept the expired session
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.sweep(), 0);
+    }
+}
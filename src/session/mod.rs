@@ -0,0 +1,15 @@
+pub use file_session_store::FileSessionStore;
+pub use memory_session_store::MemorySessionStore;
+pub use session::Session;
+pub use session_error::SessionError;
+pub use session_middleware::SessionMiddleware;
+pub use session_store::{SessionData, SessionStore};
+
+#[allow(clippy::module_inception)]
+mod session;
+mod session_error;
+mod session_middleware;
+mod session_store;
+
+mod file_session_store;
+mod memory_session_store;
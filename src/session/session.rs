@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use crate::session::SessionData;
+use crate::util::random::random_hex;
+
+const SESSION_ID_BYTES: usize = 32;
+
+// per-client key-value map which lives across requests.
+// handlers get it from 'HttpRequest::get_session_mut' when the SessionMiddleware is mounted.
+#[derive(Debug, Clone)]
+pub struct Session {
+    id: String,
+    data: SessionData,
+    is_new: bool,
+    is_modified: bool,
+    is_destroyed: bool,
+    // the id before 'regenerate' is called, it must be removed from the store
+    previous_id: Option<String>,
+}
+
+impl Session {
+    pub(crate) fn new() -> Self {
+        Session {
+            id: generate_session_id(),
+            data: SessionData::new(),
+            is_new: true,
+            is_modified: false,
+            is_destroyed: false,
+            previous_id: None,
+        }
+    }
+
+    pub(crate) fn restore(id: String, data: SessionData) -> Self {
+        Session {
+            id,
+            data,
+            is_new: false,
+            is_modified: false,
+            is_destroyed: false,
+            previous_id: None,
+        }
+    }
+
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn is_new(&self) -> bool {
+        self.is_new
+    }
+
+    // read a value with a specific type
+    pub fn get<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.data.get(key).and_then(|value| value.parse::<T>().ok())
+    }
+
+    pub fn insert<T: ToString>(&mut self, key: &str, value: T) {
+        self.data.insert(key.to_string(), value.to_string());
+        self.is_modified = true;
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let removed = self.data.remove(key);
+        if removed.is_some() {
+            self.is_modified = true;
+        }
+        removed
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.data.contains_key(key)
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear();
+        self.is_modified = true;
+    }
+
+    // issue a new session id while keeping the values.
+    // call this whenever the privilege changes (e.g. login) to prevent session fixation.
+    pub fn regenerate(&mut self) {
+        if self.previous_id.is_none() && !self.is_new {
+            self.previous_id = Some(self.id.clone());
+        }
+        self.id = generate_session_id();
+        self.is_modified = true;
+    }
+
+    // remove the session from the store and expire the cookie (e.g. logout)
+    pub fn destroy(&mut self) {
+        self.data.clear();
+        self.is_destroyed = true;
+    }
+
+    pub(crate) fn is_modified(&self) -> bool {
+        self.is_modified
+    }
+
+    pub(crate) fn is_destroyed(&self) -> bool {
+        self.is_destroyed
+    }
+
+    pub(crate) fn get_previous_id(&self) -> Option<&str> {
+        self.previous_id.as_deref()
+    }
+
+    pub(crate) fn get_data(&self) -> &SessionData {
+        &self.data
+    }
+}
+
+fn generate_session_id() -> String {
+    random_hex(SESSION_ID_BYTES)
+}
+
+// session ids are generated by the server, so anything else is rejected
+pub(crate) fn is_valid_session_id(session_id: &str) -> bool {
+    session_id.len() == SESSION_ID_BYTES * 2
+        && session_id
+        .bytes()
+        .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typed_values() {
+        let mut session = Session::new();
+        session.insert("user_id", 42_u32);
+        session.insert("name", "foo");
+
+        assert_eq!(session.get::<u32>("user_id"), Some(42));
+        assert_eq!(session.get::<String>("name"), Some("foo".to_string()));
+        assert_eq!(session.get::<u32>("name"), None);
+        assert!(session.is_modified());
+    }
+
+    #[test]
+    fn test_regenerate() {
+        let mut session = Session::restore(generate_session_id(), SessionData::new());
+        let old_id = session.get_id().to_string();
+        session.insert("user_id", 1);
+
+        session.regenerate();
+        session.regenerate();
+
+        assert_ne!(session.get_id(), old_id);
+        assert_eq!(session.get_previous_id(), Some(old_id.as_str()));
+        assert_eq!(session.get::<u32>("user_id"), Some(1));
+    }
+
+    #[test]
+    fn test_is_valid_session_id() {
+        assert!(is_valid_session_id(&generate_session_id()));
+        assert!(!is_valid_session_id("../../etc/passwd"));
+        assert!(!is_valid_session_id(&"A".repeat(SESSION_ID_BYTES * 2)));
+    }
+}
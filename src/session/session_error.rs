@@ -0,0 +1,14 @@
+use std::io;
+
+#[derive(Debug)]
+pub enum SessionError {
+    InvalidSessionId,
+    StoreIoError(io::Error),
+    StoreCorrupted,
+}
+
+impl From<io::Error> for SessionError {
+    fn from(error: io::Error) -> Self {
+        SessionError::StoreIoError(error)
+    }
+}
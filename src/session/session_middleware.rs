@@ -0,0 +1,271 @@
+use std::time::Duration;
+
+use crate::http::{Cookie, HttpRequest, HttpResponse, SameSite};
+use crate::route::Middleware;
+use crate::session::{Session, SessionStore};
+use crate::session::session::is_valid_session_id;
+
+const DEFAULT_COOKIE_NAME: &str = "lite_ws_session";
+const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+
+// attaches a Session to every request, and persists it after the route is executed.
+// the session id travels in a cookie which is only issued when the session holds something.
+pub struct SessionMiddleware {
+    store: Box<dyn SessionStore>,
+    cookie_name: String,
+    ttl: Duration,
+    secure: bool,
+}
+
+impl SessionMiddleware {
+    pub fn new<S: 'static + SessionStore>(store: S) -> Self {
+        SessionMiddleware {
+            store: Box::new(store),
+            cookie_name: DEFAULT_COOKIE_NAME.to_string(),
+            ttl: DEFAULT_TTL,
+            secure: false,
+        }
+    }
+
+    pub fn cookie_name(mut self, cookie_name: &str) -> Self {
+        self.cookie_name = cookie_name.to_string();
+        self
+    }
+
+    // idle time until a session expires, it is renewed on every request
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    // send the cookie over https only
+    pub fn secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    fn load_session(&self, http_request: &HttpRequest) -> Session {
+        let session_id = match http_request.get_cookie(&self.cookie_name) {
+            Some(session_id) if is_valid_session_id(&session_id) => session_id,
+            _ => return Session::new(),
+        };
+
+        match self.store.load(&session_id) {
+            Ok(Some(data)) => Session::restore(session_id, data),
+            Ok(None) => Session::new(),
+            Err(error) => {
+                eprintln!("[error] fail to load the session: {:?}", error);
+                Session::new()
+            }
+        }
+    }
+
+    fn make_cookie(&self, session_id: &str) -> Cookie {
+        Cookie::new(&self.cookie_name, session_id)
+            .path("/")
+            .max_age(self.ttl)
+            .http_only(true)
+            .secure(self.secure)
+            .same_site(SameSite::Lax)
+    }
+}
+
+impl Middleware for SessionMiddleware {
+    fn before(&self, http_request: &mut HttpRequest) -> Option<HttpResponse> {
+        let session = self.load_session(http_request);
+        http_request.set_session(session);
+
+        None
+    }
+
+    fn after(&self, http_request: &mut HttpRequest, http_response: &mut HttpResponse) {
+        let session = if let Some(session) = http_request.take_session() {
+            session
+        } else {
+            return;
+        };
+
+        // the old id must not be usable after a privilege change
+        if let Some(previous_id) = session.get_previous_id() {
+            if let Err(error) = self.store.remove(previous_id) {
+                eprintln!("[error] fail to remove the session: {:?}", error);
+            }
+        }
+
+        if session.is_destroyed() {
+            if !session.is_new() {
+                if let Err(error) = self.store.remove(session.get_id()) {
+                    eprintln!("[error] fail to remove the session: {:?}", error);
+                }
+            }
+            if http_request.get_cookie(&self.cookie_name).is_some() {
+                http_response.set_cookie(&Cookie::removal(&self.cookie_name).path("/"));
+            }
+            return;
+        }
+
+        // do not issue sessions to clients which have nothing to keep
+        if session.is_new() && !session.is_modified() {
+            return;
+        }
+
+        match self
+            .store
+            .save(session.get_id(), session.get_data(), self.ttl)
+        {
+            Ok(()) => http_response.set_cookie(&self.make_cookie(session.get_id())),
+            Err(error) => eprintln!("[error] fail to save the session: {:?}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::http::{ContentType, HttpStatus};
+    use crate::route::{ActionRoute, Router};
+    use crate::route::router::RouterExecution;
+    use crate::rt::block_on;
+    use crate::session::MemorySessionStore;
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    fn make_router(store: Arc<MemorySessionStore>) -> Router {
+        let count = ActionRoute::new_get("/count", |mut req, builder| {
+            let session = req.get_session_mut().unwrap();
+            let count = session.get::<u32>("count").unwrap_or(0) + 1;
+            session.insert("count", count);
+
+            let res = builder.body(ContentType::TEXT_PLAIN, count.to_string().into_bytes());
+            (req, res.build().unwrap())
+        })
+            .unwrap();
+        let login = ActionRoute::new_post("/login", |mut req, builder| {
+            req.get_session_mut().unwrap().regenerate();
+            (req, builder.build().unwrap())
+        })
+            .unwrap();
+        let logout = ActionRoute::new_post("/logout", |mut req, builder| {
+            req.get_session_mut().unwrap().destroy();
+            (req, builder.build().unwrap())
+        })
+            .unwrap();
+        let anonymous = ActionRoute::new_get("/", |req, builder| {
+            (req, builder.set_status(HttpStatus::OK).build().unwrap())
+        })
+            .unwrap();
+        let count_async = ActionRoute::get_async("/count_async", |mut req: HttpRequest| async move {
+            crate::rt::sleep(std::time::Duration::from_millis(1)).await;
+            let session = req.get_session_mut().unwrap();
+            let count = session.get::<u32>("count").unwrap_or(0) + 1;
+            session.insert("count", count);
+            HttpResponse::new_with(HttpStatus::OK)
+        })
+            .unwrap();
+        let login_async = ActionRoute::post_async("/login_async", |mut req: HttpRequest| async move {
+            req.get_session_mut().unwrap().regenerate();
+            HttpResponse::new_with(HttpStatus::OK)
+        })
+            .unwrap();
+
+        Router::new(
+            vec![
+                Box::new(count),
+                Box::new(login),
+                Box::new(logout),
+                Box::new(anonymous),
+                Box::new(count_async),
+                Box::new(login_async),
+            ],
+            vec![Box::new(SessionMiddleware::new(store))],
+        )
+    }
+
+    fn request(router: &Router, method: &str, path: &str, session_id: Option<&str>) -> Vec<u8> {
+        let mut raw = format!("{} {} HTTP/1.1\r\n", method, path);
+        if let Some(session_id) = session_id {
+            raw.push_str(&format!("Cookie: {}={}\r\n", DEFAULT_COOKIE_NAME, session_id));
+        }
+        let (_, mut res) = router.execute_route(make_request(&raw, None));
+
+        let mut written = vec![];
+        res.respond(&mut written);
+        written
+    }
+
+    fn find_session_id(response: &[u8]) -> Option<String> {
+        let response = String::from_utf8_lossy(response);
+        let prefix = format!("Set-Cookie: {}=", DEFAULT_COOKIE_NAME);
+        let line = response.lines().find(|line| line.starts_with(&prefix))?;
+
+        Some(line[prefix.len()..].split(';').next()?.to_string())
+    }
+
+    #[test]
+    fn test_anonymous_request_has_no_cookie() {
+        let store = Arc::new(MemorySessionStore::new());
+        let router = make_router(store.clone());
+
+        let response = request(&router, "GET", "/", None);
+        assert_eq!(find_session_id(&response), None);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_session_lives_across_requests() {
+        let store = Arc::new(MemorySessionStore::new());
+        let router = make_router(store.clone());
+
+        let response = request(&router, "GET", "/count", None);
+        let session_id = find_session_id(&response).unwrap();
+
+        let response = request(&router, "GET", "/count", Some(&session_id));
+        assert!(response.ends_with(b"\r\n\r\n2"));
+        assert_eq!(find_session_id(&response), Some(session_id));
+    }
+
+    #[test]
+    fn test_regenerate_and_destroy() {
+        let store = Arc::new(MemorySessionStore::new());
+        let router = make_router(store.clone());
+
+        let response = request(&router, "GET", "/count", None);
+        let old_id = find_session_id(&response).unwrap();
+
+        let response = request(&router, "POST", "/login", Some(&old_id));
+        let new_id = find_session_id(&response).unwrap();
+        assert_ne!(old_id, new_id);
+        assert_eq!(store.load(&old_id).unwrap(), None);
+        assert!(store.load(&new_id).unwrap().unwrap().contains_key("count"));
+
+        let response = request(&router, "POST", "/logout", Some(&new_id));
+        assert_eq!(find_session_id(&response), Some("".to_string()));
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_async_handler_changes_session() {
+        let store = Arc::new(MemorySessionStore::new());
+        let router = Arc::new(make_router(store.clone()));
+
+        let response = request(&router, "GET", "/count_async", None);
+        let old_id = find_session_id(&response).unwrap();
+        assert_eq!(store.load(&old_id).unwrap().unwrap().get("count"), Some(&"1".to_string()));
+
+        // the reactor mode runs the middlewares after the future has completed
+        let raw = format!("POST /login_async HTTP/1.1\r\nCookie: {}={}\r\n", DEFAULT_COOKIE_NAME, old_id);
+        let mut res = match router.start_route(make_request(&raw, None)) {
+            RouterExecution::Pending(future) => block_on(future),
+            RouterExecution::Ready(_) => panic!("the async handler has not been started"),
+        };
+        let mut response = vec![];
+        res.respond(&mut response);
+
+        let new_id = find_session_id(&response).unwrap();
+        assert_ne!(old_id, new_id);
+        assert_eq!(store.load(&old_id).unwrap(), None);
+        assert!(store.load(&new_id).unwrap().is_some());
+    }
+}
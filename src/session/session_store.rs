@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::session::SessionError;
+
+pub type SessionData = HashMap<String, String>;
+
+// persistence layer for sessions.
+// a store forgets a session by itself once its time to live is over.
+pub trait SessionStore: Send + Sync {
+    // returns None if the session does not exist or is expired
+    fn load(&self, session_id: &str) -> Result<Option<SessionData>, SessionError>;
+    // insert or overwrite the session, and renew its time to live
+    fn save(&self, session_id: &str, data: &SessionData, ttl: Duration) -> Result<(), SessionError>;
+    fn remove(&self, session_id: &str) -> Result<(), SessionError>;
+}
+
+// to share a store with the code outside of the middleware (e.g. to sweep it)
+impl<S: SessionStore> SessionStore for Arc<S> {
+    fn load(&self, session_id: &str) -> Result<Option<SessionData>, SessionError> {
+        self.as_ref().load(session_id)
+    }
+
+    fn save(&self, session_id: &str, data: &SessionData, ttl: Duration) -> Result<(), SessionError> {
+        self.as_ref().save(session_id, data, ttl)
+    }
+
+    fn remove(&self, session_id: &str) -> Result<(), SessionError> {
+        self.as_ref().remove(session_id)
+    }
+}
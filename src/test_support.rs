@@ -0,0 +1,19 @@
+// the fixtures shared by the tests of the modules
+use std::convert::TryInto;
+
+use crate::http::{HttpRequest, HttpRequestBody, HttpRequestHeader};
+
+// a request as the parser would give it to a route
+pub(crate) fn make_request(raw_header: &str, body: Option<&[u8]>) -> HttpRequest {
+    let header: HttpRequestHeader = raw_header.as_bytes().to_vec().try_into().unwrap();
+    let body = body.map(|body| HttpRequestBody::new(body.to_vec()));
+
+    HttpRequest::new(header, body).unwrap()
+}
+
+// a request whose header would be rejected by the parser, e.g. with a broken Content-Length
+pub(crate) fn make_unchecked_request(raw_header: &str) -> HttpRequest {
+    let header = HttpRequestHeader::parse_unchecked(raw_header.as_bytes().to_vec()).unwrap();
+
+    HttpRequest::new(header, None).unwrap()
+}
@@ -6,7 +6,7 @@ use regex::Regex;
 use crate::http::HttpRequestHeader;
 use crate::url::url_error::UrlError;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct UrlPath {
     raw: String,
     pathname: String,
@@ -41,9 +41,21 @@ impl UrlPath {
         parse_path(path_str)
     }
 
+    pub fn get_raw(&self) -> &str {
+        self.raw.as_ref()
+    }
+
     pub fn get_pathname(&self) -> &str {
         self.pathname.as_ref()
     }
+
+    pub fn get_query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    pub fn get_hash(&self) -> Option<&str> {
+        self.hash.as_deref()
+    }
 }
 
 fn parse_path(path_str: &str) -> Result<UrlPath, UrlError> {
@@ -63,17 +75,8 @@ fn parse_path(path_str: &str) -> Result<UrlPath, UrlError> {
         ));
     };
 
-    let query = if let Some(query) = captures.get(2) {
-        Some(query.as_str().to_string())
-    } else {
-        None
-    };
-
-    let hash = if let Some(hash) = captures.get(3) {
-        Some(hash.as_str().to_string())
-    } else {
-        None
-    };
+    let query = captures.get(2).map(|query| query.as_str().to_string());
+    let hash = captures.get(3).map(|hash| hash.as_str().to_string());
 
     let raw = path_str.to_string();
 
@@ -87,8 +90,6 @@ fn parse_path(path_str: &str) -> Result<UrlPath, UrlError> {
 
 #[cfg(test)]
 mod tests {
-    use crate::http::HttpError;
-
     use super::*;
 
     #[test]
@@ -0,0 +1,110 @@
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// encode with the standard alphabet and the padding
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let bits = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - index * 6)) as usize & 0x3f] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
+
+// decode the standard alphabet of base64, the padding is optional.
+// returns None when a character is out of the alphabet.
+pub fn base64_decode(encoded: &str) -> Option<Vec<u8>> {
+    decode_with(encoded, standard_value)
+}
+
+// decode the url-safe alphabet of base64, the padding is optional.
+// returns None when a character is out of the alphabet.
+pub fn base64_url_decode(encoded: &str) -> Option<Vec<u8>> {
+    decode_with(encoded, url_safe_value)
+}
+
+fn decode_with(encoded: &str, value_of: fn(u8) -> Option<u8>) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut bit_count = 0;
+
+    for byte in encoded.bytes() {
+        bits = bits << 6 | value_of(byte)? as u32;
+        bit_count += 6;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+        }
+    }
+
+    // a single character can not make a byte
+    if bit_count >= 6 {
+        return None;
+    }
+
+    Some(bytes)
+}
+
+fn standard_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'+' => Some(62),
+        b'/' => Some(63),
+        b'-' | b'_' => None,
+        _ => url_safe_value(byte),
+    }
+}
+
+fn url_safe_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'A'..=b'Z' => Some(byte - b'A'),
+        b'a'..=b'z' => Some(byte - b'a' + 26),
+        b'0'..=b'9' => Some(byte - b'0' + 52),
+        b'-' => Some(62),
+        b'_' => Some(63),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64_encode() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(&[0xfb, 0xff, 0xbf]), "+/+/");
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        assert_eq!(base64_decode("Zm9v"), Some(b"foo".to_vec()));
+        assert_eq!(base64_decode("Zm8="), Some(b"fo".to_vec()));
+        assert_eq!(base64_decode("+/+/"), Some(vec![0xfb, 0xff, 0xbf]));
+        assert_eq!(base64_decode("-_8"), None);
+    }
+
+    #[test]
+    fn test_base64_url_decode() {
+        assert_eq!(base64_url_decode("aGVsbG8"), Some(b"hello".to_vec()));
+        assert_eq!(base64_url_decode("aGVsbG8="), Some(b"hello".to_vec()));
+        assert_eq!(base64_url_decode("-_8"), Some(vec![0xfb, 0xff]));
+        assert_eq!(base64_url_decode("AAMAAABkAAQAoAAAAAIAAAAA"), Some(vec![
+            0, 3, 0, 0, 0, 100, 0, 4, 0, 160, 0, 0, 0, 2, 0, 0, 0, 0,
+        ]));
+        assert_eq!(base64_url_decode("a+b"), None);
+        assert_eq!(base64_url_decode("a"), None);
+    }
+}
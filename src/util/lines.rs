@@ -27,7 +27,7 @@ impl Iterator for Lines {
             let c = self.src[self.cursor];
 
             if c == b'\n' {
-                if self.src[self.cursor - 1] == b'\r' {
+                if self.cursor > from && self.src[self.cursor - 1] == b'\r' {
                     to = self.cursor - 1;
                 } else {
                     to = self.cursor;
@@ -61,14 +61,14 @@ mod tests {
         if let Some(line) = result {
             assert_eq!(line, "hello");
         } else {
-            assert!(false);
+            panic!();
         }
 
         let result = lines.next();
         if let Some(line) = result {
             assert_eq!(line, "world");
         } else {
-            assert!(false);
+            panic!();
         }
 
         assert_eq!(lines.next(), None);
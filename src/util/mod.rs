@@ -1 +1,6 @@
+pub mod base64;
 pub mod lines;
+pub mod percent;
+pub mod random;
+pub mod sendfile;
+pub mod sha1;
@@ -0,0 +1,63 @@
+// decode '%XX' sequences of a url component.
+// returns None when a sequence is broken or the result is not utf-8.
+pub fn percent_decode(encoded: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut iter = encoded.bytes();
+
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let high = hex_value(iter.next()?)?;
+            let low = hex_value(iter.next()?)?;
+            bytes.push(high << 4 | low);
+        } else {
+            bytes.push(byte);
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+// encode '%' and the reserved bytes to '%XX', the result is decoded by 'percent_decode'
+pub fn percent_encode(raw: &str, reserved: &[u8]) -> String {
+    let mut encoded = String::with_capacity(raw.len());
+
+    for char in raw.chars() {
+        if char == '%' || (char.is_ascii() && reserved.contains(&(char as u8))) {
+            encoded.push_str(&format!("%{:02X}", char as u8));
+        } else {
+            encoded.push(char);
+        }
+    }
+
+    encoded
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b"), Some("a b".to_string()));
+        assert_eq!(percent_decode("%ED%95%9C"), Some("한".to_string()));
+        assert_eq!(percent_decode("plain"), Some("plain".to_string()));
+        assert_eq!(percent_decode("%2"), None);
+        assert_eq!(percent_decode("%zz"), None);
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("a=b\n100%", b"=\n"), "a%3Db%0A100%25");
+        assert_eq!(percent_encode("한 a", b" "), "한%20a");
+        assert_eq!(percent_decode(&percent_encode("a=b\r\n%", b"=\r\n")), Some("a=b\r\n%".to_string()));
+    }
+}
@@ -0,0 +1,53 @@
+use std::collections::hash_map::RandomState;
+use std::fs::File;
+use std::hash::{BuildHasher, Hasher};
+use std::io::Read;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// fill the buffer with random bytes.
+// the OS generator is used when it is available, otherwise hashers with random keys are used.
+pub fn fill_random(buf: &mut [u8]) {
+    if let Ok(mut urandom) = File::open("/dev/urandom") {
+        if urandom.read_exact(buf).is_ok() {
+            return;
+        }
+    }
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos() as u64)
+        .unwrap_or(0);
+
+    for chunk in buf.chunks_mut(8) {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(nanos);
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        let bytes = hasher.finish().to_le_bytes();
+        chunk.copy_from_slice(&bytes[..chunk.len()]);
+    }
+}
+
+// make a random token encoded as lowercase hex
+pub fn random_hex(byte_len: usize) -> String {
+    let mut buf = vec![0_u8; byte_len];
+    fill_random(&mut buf);
+
+    buf.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_hex() {
+        let token = random_hex(16);
+
+        assert_eq!(token.len(), 32);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, random_hex(16));
+    }
+}
@@ -0,0 +1,72 @@
+use std::fs::File;
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+
+// a socket which a file can be sent to, sendfile(2) needs its descriptor on unix
+#[cfg(unix)]
+pub trait FileSocket: AsRawFd + Write {}
+#[cfg(unix)]
+impl<S: AsRawFd + Write> FileSocket for S {}
+
+#[cfg(not(unix))]
+pub trait FileSocket: Write {}
+#[cfg(not(unix))]
+impl<S: Write> FileSocket for S {}
+
+// write 'count' bytes of the file from 'offset' to the socket, returns the number of written bytes.
+// the bytes do not pass through the userspace on linux.
+#[cfg(target_os = "linux")]
+pub fn send_file<S: FileSocket>(
+    socket: &mut S,
+    file: &File,
+    offset: u64,
+    count: usize,
+) -> io::Result<usize> {
+    let mut offset = offset as libc::off_t;
+    // sendfile(2) refuses a count which is larger than this
+    let count = count.min(0x7fff_f000);
+    let written =
+        unsafe { libc::sendfile(socket.as_raw_fd(), file.as_raw_fd(), &mut offset, count) };
+
+    if written < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(written as usize)
+    }
+}
+
+// a buffered copy where sendfile(2) is not available
+#[cfg(not(target_os = "linux"))]
+pub fn send_file<S: FileSocket>(
+    socket: &mut S,
+    file: &File,
+    offset: u64,
+    count: usize,
+) -> io::Result<usize> {
+    let mut buffer = vec![0_u8; count.min(64 * 1024)];
+    let nbytes = read_at(file, &mut buffer, offset)?;
+    if nbytes == 0 {
+        return Ok(0);
+    }
+
+    socket.write(&buffer[..nbytes])
+}
+
+// read from 'offset' without moving the cursor of the file
+#[cfg(unix)]
+pub fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::os::unix::fs::FileExt;
+
+    file.read_at(buffer, offset)
+}
+
+// the cursor of the file is moved, a file body is never read from two places at once
+#[cfg(not(unix))]
+pub fn read_at(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<usize> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = file;
+    file.seek(SeekFrom::Start(offset))?;
+    file.read(buffer)
+}
@@ -0,0 +1,197 @@
+use crate::websocket::close_code;
+
+// the opcodes, ref <https://www.rfc-editor.org/rfc/rfc6455#section-5.2>
+pub(crate) const CONTINUATION: u8 = 0x0;
+pub(crate) const TEXT: u8 = 0x1;
+pub(crate) const BINARY: u8 = 0x2;
+pub(crate) const CLOSE: u8 = 0x8;
+pub(crate) const PING: u8 = 0x9;
+pub(crate) const PONG: u8 = 0xa;
+
+const FLAG_FIN: u8 = 0x80;
+const FLAG_MASK: u8 = 0x80;
+const MAX_CONTROL_PAYLOAD_SIZE: usize = 125;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub is_fin: bool,
+    pub opcode: u8,
+    pub payload: Vec<u8>,
+}
+
+pub(crate) fn is_control(opcode: u8) -> bool {
+    opcode & 0x8 != 0
+}
+
+// parse a frame of a client, which must be masked.
+// returns the frame and its size, or None when the frame has not arrived completely.
+// an error is the status code to close the connection with.
+pub(crate) fn parse_frame(src: &[u8], max_payload_size: usize) -> Result<Option<(Frame, usize)>, u16> {
+    if src.len() < 2 {
+        return Ok(None);
+    }
+
+    // no extension is negotiated, so the reserved bits must be zero
+    if src[0] & 0x70 != 0 {
+        return Err(close_code::PROTOCOL_ERROR);
+    }
+    let is_fin = src[0] & FLAG_FIN != 0;
+    let opcode = src[0] & 0x0f;
+    if !matches!(opcode, CONTINUATION | TEXT | BINARY | CLOSE | PING | PONG) {
+        return Err(close_code::PROTOCOL_ERROR);
+    }
+    if src[1] & FLAG_MASK == 0 {
+        return Err(close_code::PROTOCOL_ERROR);
+    }
+
+    let (length, mut offset) = match src[1] & 0x7f {
+        126 => {
+            if src.len() < 4 {
+                return Ok(None);
+            }
+            (u16::from_be_bytes([src[2], src[3]]) as u64, 4)
+        }
+        127 => {
+            if src.len() < 10 {
+                return Ok(None);
+            }
+            let mut bytes = [0_u8; 8];
+            bytes.copy_from_slice(&src[2..10]);
+            (u64::from_be_bytes(bytes), 10)
+        }
+        length => (length as u64, 2),
+    };
+
+    if is_control(opcode) {
+        // a control frame is never fragmented
+        if !is_fin || length > MAX_CONTROL_PAYLOAD_SIZE as u64 {
+            return Err(close_code::PROTOCOL_ERROR);
+        }
+    } else if length > max_payload_size as u64 {
+        return Err(close_code::MESSAGE_TOO_BIG);
+    }
+    let length = length as usize;
+
+    if src.len() < offset + 4 + length {
+        return Ok(None);
+    }
+    let mask = [src[offset], src[offset + 1], src[offset + 2], src[offset + 3]];
+    offset += 4;
+
+    let payload = src[offset..offset + length]
+        .iter()
+        .enumerate()
+        .map(|(index, byte)| byte ^ mask[index % 4])
+        .collect();
+
+    Ok(Some((Frame { is_fin, opcode, payload }, offset + length)))
+}
+
+// a frame of the server, which is not masked
+pub(crate) fn write_frame(dst: &mut Vec<u8>, is_fin: bool, opcode: u8, payload: &[u8]) {
+    dst.push(if is_fin { FLAG_FIN | opcode } else { opcode });
+    write_length(dst, 0, payload.len());
+    dst.extend_from_slice(payload);
+}
+
+// a frame of a client, for the tests
+#[cfg(test)]
+pub(crate) fn write_masked_frame(dst: &mut Vec<u8>, is_fin: bool, opcode: u8, payload: &[u8]) {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    dst.push(if is_fin { FLAG_FIN | opcode } else { opcode });
+    write_length(dst, FLAG_MASK, payload.len());
+    dst.extend_from_slice(&mask);
+    dst.extend(payload.iter().enumerate().map(|(index, byte)| byte ^ mask[index % 4]));
+}
+
+// read a frame of the server, for the tests
+#[cfg(test)]
+pub(crate) fn read_frame<R: std::io::Read>(reader: &mut R) -> Option<Frame> {
+    let mut header = [0_u8; 2];
+    reader.read_exact(&mut header).ok()?;
+    let length = match header[1] & 0x7f {
+        126 => {
+            let mut bytes = [0_u8; 2];
+            reader.read_exact(&mut bytes).ok()?;
+            u16::from_be_bytes(bytes) as usize
+        }
+        127 => {
+            let mut bytes = [0_u8; 8];
+            reader.read_exact(&mut bytes).ok()?;
+            u64::from_be_bytes(bytes) as usize
+        }
+        length => length as usize,
+    };
+    let mut payload = vec![0_u8; length];
+    reader.read_exact(&mut payload).ok()?;
+
+    Some(Frame {
+        is_fin: header[0] & FLAG_FIN != 0,
+        opcode: header[0] & 0x0f,
+        payload,
+    })
+}
+
+fn write_length(dst: &mut Vec<u8>, flag: u8, length: usize) {
+    if length < 126 {
+        dst.push(flag | length as u8);
+    } else if length <= u16::MAX as usize {
+        dst.push(flag | 126);
+        dst.extend_from_slice(&(length as u16).to_be_bytes());
+    } else {
+        dst.push(flag | 127);
+        dst.extend_from_slice(&(length as u64).to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_frame() {
+        // the example of a masked "Hello" in RFC 6455
+        let src = [0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58];
+        let expected = Frame { is_fin: true, opcode: TEXT, payload: b"Hello".to_vec() };
+        assert_eq!(parse_frame(&src, 1024), Ok(Some((expected, src.len()))));
+        assert_eq!(parse_frame(&src[..10], 1024), Ok(None));
+
+        for length in [0, 125, 126, 65_535, 65_536] {
+            let payload = vec![b'a'; length];
+            let mut src = vec![];
+            write_masked_frame(&mut src, false, BINARY, &payload);
+            let (frame, size) = parse_frame(&src, usize::MAX).unwrap().unwrap();
+            assert_eq!(size, src.len());
+            assert!(!frame.is_fin);
+            assert_eq!(frame.payload, payload);
+        }
+    }
+
+    #[test]
+    fn test_parse_frame_error() {
+        // not masked
+        assert_eq!(parse_frame(&[0x81, 0x00], 1024), Err(close_code::PROTOCOL_ERROR));
+        // a reserved bit
+        assert_eq!(parse_frame(&[0xc1, 0x80], 1024), Err(close_code::PROTOCOL_ERROR));
+        // an unknown opcode
+        assert_eq!(parse_frame(&[0x83, 0x80], 1024), Err(close_code::PROTOCOL_ERROR));
+        // a fragmented ping
+        assert_eq!(parse_frame(&[0x09, 0x80], 1024), Err(close_code::PROTOCOL_ERROR));
+        // a too long ping
+        assert_eq!(parse_frame(&[0x89, 0xfe, 0x00, 0x7e], 1024), Err(close_code::PROTOCOL_ERROR));
+        // the message is too large, which is known before the payload arrives
+        assert_eq!(parse_frame(&[0x82, 0xfe, 0x04, 0x01], 1024), Err(close_code::MESSAGE_TOO_BIG));
+    }
+
+    #[test]
+    fn test_write_frame() {
+        let mut dst = vec![];
+        write_frame(&mut dst, true, TEXT, b"Hello");
+        assert_eq!(dst, [0x81, 0x05, 0x48, 0x65, 0x6c, 0x6c, 0x6f]);
+
+        let mut dst = vec![];
+        write_frame(&mut dst, true, BINARY, &[0; 256]);
+        assert_eq!(dst[..4], [0x82, 0x7e, 0x01, 0x00]);
+        assert_eq!(read_frame(&mut dst.as_slice()).unwrap().payload, [0; 256]);
+    }
+}
@@ -0,0 +1,11 @@
+// a message of WebSocket, a fragmented message is received as a whole
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    // a Ping is answered with a Pong before it is received
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    // the status code and the reason, none if the peer has sent no code
+    Close(Option<(u16, String)>),
+}
@@ -0,0 +1,13 @@
+pub use message::Message;
+pub use websocket::WebSocket;
+pub use websocket_error::WebSocketError;
+pub use websocket_route::WebSocketRoute;
+
+pub mod close_code;
+
+pub(crate) mod frame;
+mod message;
+#[allow(clippy::module_inception)]
+mod websocket;
+mod websocket_error;
+mod websocket_route;
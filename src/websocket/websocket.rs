@@ -0,0 +1,301 @@
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::http::UpgradedStream;
+use crate::websocket::{close_code, Message, WebSocketError};
+use crate::websocket::frame::{self, Frame, BINARY, CLOSE, CONTINUATION, PING, PONG, TEXT};
+
+const READ_BUFFER_SIZE: usize = 16_384;
+const MAX_CONTROL_PAYLOAD_SIZE: usize = 125;
+// how long the Close of the peer is waited for, when a handler returns without the closing handshake
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// a connection of WebSocket, which is given to the handler of a WebSocketRoute.
+// the handler owns it on a worker thread, the connection is closed when it is dropped.
+pub struct WebSocket {
+    // none after the connection is closed
+    stream: Option<UpgradedStream>,
+    protocol: Option<String>,
+    max_message_size: usize,
+    frame_size: usize,
+    // the bytes of a frame which has not arrived completely
+    input: Vec<u8>,
+    // the opcode and the payloads of a fragmented message
+    fragments: Option<(u8, Vec<u8>)>,
+    is_close_sent: bool,
+}
+
+impl WebSocket {
+    pub(crate) fn new(
+        stream: UpgradedStream,
+        protocol: Option<String>,
+        max_message_size: usize,
+        frame_size: usize,
+    ) -> Self {
+        WebSocket {
+            stream: Some(stream),
+            protocol,
+            max_message_size,
+            frame_size,
+            input: vec![],
+            fragments: None,
+            is_close_sent: false,
+        }
+    }
+
+    // the subprotocol which has been selected in the handshake
+    pub fn get_protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    // a handler should close the connection when the server is shutting down
+    pub fn is_shutdown(&self) -> bool {
+        self.stream.as_ref().is_none_or(|stream| stream.is_shutdown())
+    }
+
+    // 'receive' fails with a timeout error, e.g. to check 'is_shutdown' periodically
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        if let Some(stream) = &self.stream {
+            stream.set_read_timeout(timeout)
+        } else {
+            Ok(())
+        }
+    }
+
+    // wait for the next message. a Close of the peer is answered and returned,
+    // then the connection is closed and 'receive' fails with 'WebSocketError::Closed'.
+    pub fn receive(&mut self) -> Result<Message, WebSocketError> {
+        loop {
+            let received = self.fragments.as_ref().map_or(0, |(_, payload)| payload.len());
+            let max_payload_size = self.max_message_size.saturating_sub(received);
+
+            match frame::parse_frame(&self.input, max_payload_size) {
+                Ok(Some((frame, size))) => {
+                    self.input.drain(..size);
+                    if let Some(message) = self.handle_frame(frame)? {
+                        return Ok(message);
+                    }
+                }
+                Ok(None) => self.fill_input()?,
+                Err(code) => return Err(self.fail(code)),
+            }
+        }
+    }
+
+    pub fn send(&mut self, message: Message) -> Result<(), WebSocketError> {
+        match message {
+            Message::Text(text) => self.send_data(TEXT, text.as_bytes()),
+            Message::Binary(data) => self.send_data(BINARY, &data),
+            Message::Ping(payload) => self.send_control(PING, &payload),
+            Message::Pong(payload) => self.send_control(PONG, &payload),
+            Message::Close(Some((code, reason))) => self.close(code, &reason),
+            Message::Close(None) => {
+                self.send_control(CLOSE, &[])?;
+                self.is_close_sent = true;
+                Ok(())
+            }
+        }
+    }
+
+    pub fn send_text(&mut self, text: &str) -> Result<(), WebSocketError> {
+        self.send_data(TEXT, text.as_bytes())
+    }
+
+    pub fn send_binary(&mut self, data: &[u8]) -> Result<(), WebSocketError> {
+        self.send_data(BINARY, data)
+    }
+
+    // start the closing handshake, the messages which are still sent by the peer
+    // are received until its Close arrives
+    pub fn close(&mut self, code: u16, reason: &str) -> Result<(), WebSocketError> {
+        let mut payload = code.to_be_bytes().to_vec();
+        payload.extend_from_slice(reason.as_bytes());
+        self.send_control(CLOSE, &payload)?;
+        self.is_close_sent = true;
+
+        Ok(())
+    }
+
+    fn handle_frame(&mut self, frame: Frame) -> Result<Option<Message>, WebSocketError> {
+        let Frame { is_fin, opcode, payload } = frame;
+
+        match opcode {
+            PING => {
+                if !self.is_close_sent {
+                    self.send_control(PONG, &payload)?;
+                }
+                Ok(Some(Message::Ping(payload)))
+            }
+            PONG => Ok(Some(Message::Pong(payload))),
+            CLOSE => self.handle_close(payload).map(Some),
+            CONTINUATION => {
+                let (opcode, mut message) = if let Some(fragments) = self.fragments.take() {
+                    fragments
+                } else {
+                    return Err(self.fail(close_code::PROTOCOL_ERROR));
+                };
+                message.extend_from_slice(&payload);
+                if is_fin {
+                    self.make_message(opcode, message).map(Some)
+                } else {
+                    self.fragments = Some((opcode, message));
+                    Ok(None)
+                }
+            }
+            _ => {
+                // a new message must not start in the middle of a fragmented one
+                if self.fragments.is_some() {
+                    return Err(self.fail(close_code::PROTOCOL_ERROR));
+                }
+                if is_fin {
+                    self.make_message(opcode, payload).map(Some)
+                } else {
+                    self.fragments = Some((opcode, payload));
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    fn make_message(&mut self, opcode: u8, payload: Vec<u8>) -> Result<Message, WebSocketError> {
+        if opcode == BINARY {
+            return Ok(Message::Binary(payload));
+        }
+
+        match String::from_utf8(payload) {
+            Ok(text) => Ok(Message::Text(text)),
+            Err(_) => Err(self.fail(close_code::INVALID_PAYLOAD)),
+        }
+    }
+
+    // the Close is answered with the same code, then the connection is closed
+    fn handle_close(&mut self, payload: Vec<u8>) -> Result<Message, WebSocketError> {
+        let close = match payload.len() {
+            0 => None,
+            1 => return Err(self.fail(close_code::PROTOCOL_ERROR)),
+            _ => {
+                let code = u16::from_be_bytes([payload[0], payload[1]]);
+                if !close_code::is_valid(code) {
+                    return Err(self.fail(close_code::PROTOCOL_ERROR));
+                }
+                match String::from_utf8(payload[2..].to_vec()) {
+                    Ok(reason) => Some((code, reason)),
+                    Err(_) => return Err(self.fail(close_code::INVALID_PAYLOAD)),
+                }
+            }
+        };
+
+        if !self.is_close_sent {
+            let code = close.as_ref().map_or(vec![], |(code, _)| code.to_be_bytes().to_vec());
+            self.send_control(CLOSE, &code).ok();
+            self.is_close_sent = true;
+        }
+        self.close_stream();
+
+        Ok(Message::Close(close))
+    }
+
+    // the peer is told the reason of the failure, then the connection is closed
+    fn fail(&mut self, code: u16) -> WebSocketError {
+        if !self.is_close_sent {
+            self.close(code, "").ok();
+        }
+        self.close_stream();
+
+        WebSocketError::Protocol(code)
+    }
+
+    // a large message is split into the frames of 'frame_size'
+    fn send_data(&mut self, opcode: u8, data: &[u8]) -> Result<(), WebSocketError> {
+        if self.is_close_sent {
+            return Err(WebSocketError::Closed);
+        }
+
+        let mut output = vec![];
+        let mut chunks = data.chunks(self.frame_size.max(1)).peekable();
+        let mut opcode = opcode;
+        if chunks.peek().is_none() {
+            frame::write_frame(&mut output, true, opcode, &[]);
+        }
+        while let Some(chunk) = chunks.next() {
+            frame::write_frame(&mut output, chunks.peek().is_none(), opcode, chunk);
+            opcode = CONTINUATION;
+        }
+
+        self.write_output(&output)
+    }
+
+    fn send_control(&mut self, opcode: u8, payload: &[u8]) -> Result<(), WebSocketError> {
+        if self.is_close_sent {
+            return Err(WebSocketError::Closed);
+        }
+        if payload.len() > MAX_CONTROL_PAYLOAD_SIZE {
+            return Err(WebSocketError::Io(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the payload of a control frame is too large",
+            )));
+        }
+
+        let mut output = vec![];
+        frame::write_frame(&mut output, true, opcode, payload);
+        self.write_output(&output)
+    }
+
+    fn write_output(&mut self, output: &[u8]) -> Result<(), WebSocketError> {
+        let stream = if let Some(stream) = &mut self.stream {
+            stream
+        } else {
+            return Err(WebSocketError::Closed);
+        };
+
+        stream.write_all(output)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    fn fill_input(&mut self) -> Result<(), WebSocketError> {
+        let stream = if let Some(stream) = &mut self.stream {
+            stream
+        } else {
+            return Err(WebSocketError::Closed);
+        };
+
+        let mut buffer = [0_u8; READ_BUFFER_SIZE];
+        let nbytes = stream.read(&mut buffer)?;
+        if nbytes == 0 {
+            // the peer has gone without the closing handshake
+            self.stream = None;
+            return Err(WebSocketError::Closed);
+        }
+        self.input.extend_from_slice(&buffer[..nbytes]);
+
+        Ok(())
+    }
+
+    fn close_stream(&mut self) {
+        if let Some(stream) = self.stream.take() {
+            stream.close();
+        }
+    }
+}
+
+impl Drop for WebSocket {
+    // the handler has returned, the closing handshake is done for it
+    fn drop(&mut self) {
+        if self.stream.is_none() {
+            return;
+        }
+
+        if !self.is_close_sent {
+            let code = if self.is_shutdown() { close_code::GOING_AWAY } else { close_code::NORMAL };
+            if self.close(code, "").is_err() {
+                self.close_stream();
+                return;
+            }
+        }
+
+        self.set_read_timeout(Some(CLOSE_TIMEOUT)).ok();
+        while self.receive().is_ok() {}
+        self.close_stream();
+    }
+}
@@ -0,0 +1,41 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum WebSocketError {
+    // e.g. the read timeout, the received frames are kept for the next call
+    Io(io::Error),
+    // the peer has violated the protocol, the connection is closed with the status code
+    Protocol(u16),
+    // the closing handshake has been done, or the connection is lost
+    Closed,
+}
+
+impl WebSocketError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(
+            self,
+            WebSocketError::Io(error)
+                if matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+        )
+    }
+}
+
+impl fmt::Display for WebSocketError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WebSocketError::Io(error) => write!(f, "fail to read or write the stream: {}", error),
+            WebSocketError::Protocol(code) => write!(f, "the peer has violated the protocol: {}", code),
+            WebSocketError::Closed => f.write_str("the connection is closed"),
+        }
+    }
+}
+
+impl Error for WebSocketError {}
+
+impl From<io::Error> for WebSocketError {
+    fn from(error: io::Error) -> Self {
+        WebSocketError::Io(error)
+    }
+}
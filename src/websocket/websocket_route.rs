@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use crate::http::{HttpRequest, HttpResponse, HttpStatus, ReadHeaderAs};
+use crate::http::method::HttpMethod;
+use crate::route::{RouteError, RoutePath};
+use crate::route::route::{ExecutionResult, Route};
+use crate::util::base64::{base64_decode, base64_encode};
+use crate::util::sha1::sha1;
+use crate::websocket::WebSocket;
+
+type WebSocketHandler = Arc<dyn Fn(HttpRequest, WebSocket) + Send + Sync>;
+
+// appended to the key of the client to make Sec-WebSocket-Accept
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const VERSION: &str = "13";
+const DEFAULT_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+const DEFAULT_FRAME_SIZE: usize = 64 * 1024;
+
+// answers the opening handshake of WebSocket, then the handler owns the connection
+// on a worker thread until it returns.
+// ref <https://www.rfc-editor.org/rfc/rfc6455#section-4.2>
+pub struct WebSocketRoute {
+    route_path: RoutePath,
+    handler: WebSocketHandler,
+    // the subprotocols in the order of the preference
+    protocols: Vec<String>,
+    max_message_size: usize,
+    frame_size: usize,
+}
+
+impl WebSocketRoute {
+    pub fn new<F>(path: &str, handler: F) -> Result<WebSocketRoute, RouteError>
+        where
+            F: Fn(HttpRequest, WebSocket) + Send + Sync + 'static,
+    {
+        let route_path = if let Ok(route_path) = path.parse::<RoutePath>() {
+            route_path
+        } else {
+            return Err(RouteError::RoutePathParseError);
+        };
+
+        Ok(WebSocketRoute {
+            route_path,
+            handler: Arc::new(handler),
+            protocols: vec![],
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            frame_size: DEFAULT_FRAME_SIZE,
+        })
+    }
+
+    // the first of them which is offered by the client is selected
+    pub fn protocols(mut self, protocols: &[&str]) -> Self {
+        self.protocols = protocols.iter().map(|protocol| protocol.to_string()).collect();
+        self
+    }
+
+    // a larger message of the client fails the connection with 1009
+    pub fn max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    // a larger message of the server is sent in several frames
+    pub fn frame_size(mut self, frame_size: usize) -> Self {
+        self.frame_size = frame_size;
+        self
+    }
+
+    fn set_path_params(&self, http_request: &mut HttpRequest) {
+        let pathname = http_request.get_req_path().get_pathname();
+        if let Some(path_params) = self.route_path.captures(pathname) {
+            http_request.set_path_params(path_params);
+        }
+    }
+
+    fn select_protocol(&self, http_request: &HttpRequest) -> Option<String> {
+        let offered: String = http_request.get_header("sec-websocket-protocol")?;
+        let offered: Vec<&str> = offered.split(',').map(|protocol| protocol.trim()).collect();
+
+        self.protocols
+            .iter()
+            .find(|protocol| offered.contains(&protocol.as_str()))
+            .cloned()
+    }
+}
+
+impl Route for WebSocketRoute {
+    fn is_path_matching(&self, method: HttpMethod, pathname: &str) -> bool {
+        method == HttpMethod::GET && self.route_path.is_match(pathname)
+    }
+
+    fn execute(&self, mut http_request: HttpRequest) -> ExecutionResult {
+        self.set_path_params(&mut http_request);
+
+        let accept = match get_accept(&http_request) {
+            Ok(accept) => accept,
+            Err(status) => {
+                let mut http_response = HttpResponse::new_with(status);
+                http_response.append_header("Sec-WebSocket-Version", VERSION);
+                return (http_request, http_response);
+            }
+        };
+
+        let protocol = self.select_protocol(&http_request);
+        let mut http_response = HttpResponse::new_with(HttpStatus::SWITCHING_PROTOCOL);
+        http_response.append_header("Upgrade", "websocket");
+        http_response.append_header("Sec-WebSocket-Accept", &accept);
+        if let Some(protocol) = &protocol {
+            http_response.append_header("Sec-WebSocket-Protocol", protocol);
+        }
+
+        let snapshot = http_request.snapshot();
+        let handler = self.handler.clone();
+        let (max_message_size, frame_size) = (self.max_message_size, self.frame_size);
+        http_response.set_upgrade(Box::new(move |stream| {
+            handler(snapshot, WebSocket::new(stream, protocol, max_message_size, frame_size));
+        }));
+
+        (http_request, http_response)
+    }
+}
+
+// check the handshake of the client and make Sec-WebSocket-Accept.
+// a plain request is answered with 426, a broken handshake with 400.
+fn get_accept(http_request: &HttpRequest) -> Result<String, HttpStatus> {
+    let has_token = |value: &str, token: &str| {
+        value.split(',').any(|item| item.trim().eq_ignore_ascii_case(token))
+    };
+
+    let upgrade: Option<String> = http_request.get_header("upgrade");
+    if !upgrade.is_some_and(|upgrade| has_token(&upgrade, "websocket")) {
+        return Err(HttpStatus::UPGRADE_REQUIRED);
+    }
+
+    let version = http_request.get_request_header().get_version();
+    let connection: Option<String> = http_request.get_header("connection");
+    let key: Option<String> = http_request.get_header("sec-websocket-key");
+    let client_version: Option<String> = http_request.get_header("sec-websocket-version");
+    let key = match (connection, key) {
+        (Some(connection), Some(key))
+            if has_token(&connection, "upgrade")
+                && (version.get_major(), version.get_minor()) >= (1, 1)
+                && base64_decode(key.trim()).is_some_and(|nonce| nonce.len() == 16) =>
+        {
+            key
+        }
+        _ => return Err(HttpStatus::BAD_REQUEST),
+    };
+
+    // the supported version is told by the header of the error response
+    if client_version.as_deref().map(str::trim) != Some(VERSION) {
+        return Err(HttpStatus::UPGRADE_REQUIRED);
+    }
+
+    Ok(make_accept(key.trim()))
+}
+
+fn make_accept(key: &str) -> String {
+    base64_encode(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_support::make_request;
+
+    use super::*;
+
+    const KEY: &str = "dGhlIHNhbXBsZSBub25jZQ==";
+
+    fn execute(route: &WebSocketRoute, headers: &[&str]) -> (String, bool) {
+        let raw_header = format!("GET /chat HTTP/1.1\r\nHost: localhost\r\n{}\r\n\r\n", headers.join("\r\n"));
+        let (_, mut http_response) = route.execute(make_request(&raw_header, None));
+        let has_upgrade = http_response.take_upgrade().is_some();
+        let mut written = vec![];
+        http_response.respond(&mut written);
+
+        (String::from_utf8(written).unwrap(), has_upgrade)
+    }
+
+    #[test]
+    fn test_make_accept() {
+        // the example of RFC 6455
+        assert_eq!(make_accept(KEY), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_handshake() {
+        let route = WebSocketRoute::new("/chat", |_, _| {}).unwrap().protocols(&["chat", "superchat"]);
+        assert!(route.is_path_matching(HttpMethod::GET, "/chat"));
+        assert!(!route.is_path_matching(HttpMethod::POST, "/chat"));
+
+        let key = format!("Sec-WebSocket-Key: {}", KEY);
+        let (response, has_upgrade) = execute(&route, &[
+            "Upgrade: websocket",
+            "Connection: keep-alive, Upgrade",
+            &key,
+            "Sec-WebSocket-Version: 13",
+            "Sec-WebSocket-Protocol: superchat, chat",
+        ]);
+        assert!(has_upgrade);
+        assert!(response.starts_with("HTTP/1.1 101 "));
+        assert!(response.contains("Upgrade: websocket\r\n"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+        assert!(response.contains("Sec-WebSocket-Protocol: chat\r\n"));
+        assert!(!response.contains("Content-Length"));
+    }
+
+    #[test]
+    fn test_handshake_error() {
+        let route = WebSocketRoute::new("/chat", |_, _| {}).unwrap();
+        let key = format!("Sec-WebSocket-Key: {}", KEY);
+
+        let (response, has_upgrade) = execute(&route, &[]);
+        assert!(!has_upgrade);
+        assert!(response.starts_with("HTTP/1.1 426 "));
+        assert!(response.contains("Sec-WebSocket-Version: 13\r\n"));
+
+        let (response, _) = execute(&route, &["Upgrade: websocket", "Connection: Upgrade", &key]);
+        assert!(response.starts_with("HTTP/1.1 426 "));
+
+        let (response, _) = execute(&route, &[
+            "Upgrade: websocket",
+            "Connection: Upgrade",
+            "Sec-WebSocket-Key: c2hvcnQ=",
+            "Sec-WebSocket-Version: 13",
+        ]);
+        assert!(response.starts_with("HTTP/1.1 400 "));
+
+        let (response, _) = execute(&route, &["Upgrade: websocket", &key, "Sec-WebSocket-Version: 13"]);
+        assert!(response.starts_with("HTTP/1.1 400 "));
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+
+#[cfg(feature = "tls")]
+use rustls::{ServerConnection, StreamOwned};
+
+use crate::http::{HttpResponse, TlsInfo};
+use crate::server::TlsAcceptor;
+#[cfg(feature = "tls")]
+use crate::server::get_tls_info;
+
+// the stream of a connection served by a worker, which may be encrypted by TLS
+pub(crate) enum ConnectionStream {
+    Plain(TcpStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<StreamOwned<ServerConnection, TcpStream>>),
+}
+
+impl ConnectionStream {
+    pub fn new(socket: TcpStream, tls_acceptor: Option<&TlsAcceptor>) -> io::Result<Self> {
+        match tls_acceptor {
+            None => Ok(ConnectionStream::Plain(socket)),
+            #[cfg(feature = "tls")]
+            Some(tls_acceptor) => Ok(ConnectionStream::Tls(Box::new(StreamOwned::new(
+                tls_acceptor.accept()?,
+                socket,
+            )))),
+            #[cfg(not(feature = "tls"))]
+            Some(tls_acceptor) => match *tls_acceptor {},
+        }
+    }
+
+    // to set timeouts and to peek
+    pub fn get_socket(&self) -> &TcpStream {
+        match self {
+            ConnectionStream::Plain(socket) => socket,
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => &stream.sock,
+        }
+    }
+
+    pub fn is_tls(&self) -> bool {
+        !matches!(self, ConnectionStream::Plain(_))
+    }
+
+    // decrypted bytes which are not read yet, they can not be found by peeking the socket
+    pub fn has_buffered_data(&mut self) -> bool {
+        match self {
+            ConnectionStream::Plain(_) => false,
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => stream
+                .conn
+                .process_new_packets()
+                .is_ok_and(|io_state| io_state.plaintext_bytes_to_read() > 0),
+        }
+    }
+
+    pub fn get_tls_info(&self) -> Option<TlsInfo> {
+        match self {
+            ConnectionStream::Plain(_) => None,
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => get_tls_info(&stream.conn),
+        }
+    }
+
+    // a file body is sent with sendfile(2) only over plain TCP, TLS has to encrypt it
+    pub fn respond(&mut self, http_response: &mut HttpResponse) {
+        match self {
+            ConnectionStream::Plain(socket) => http_response.respond_to_socket(socket),
+            // nothing can be sent to a client which has failed the handshake
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) if stream.conn.is_handshaking() => {}
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => http_response.respond(stream),
+        }
+    }
+
+    // closing with unread data resets the connection, which may discard the response
+    pub fn close_gracefully(&mut self) {
+        #[cfg(feature = "tls")]
+        if let ConnectionStream::Tls(stream) = self {
+            // the records are written without 'flush', which may wait for the handshake
+            stream.conn.send_close_notify();
+            while stream.conn.wants_write() {
+                if !matches!(stream.conn.write_tls(&mut stream.sock), Ok(nbytes) if nbytes > 0) {
+                    break;
+                }
+            }
+        }
+
+        let socket = self.get_socket();
+        socket.shutdown(Shutdown::Write).ok();
+        if socket.set_nonblocking(true).is_ok() {
+            let mut socket = socket;
+            let mut buffer = [0_u8; 1024];
+            while let Ok(nbytes) = socket.read(&mut buffer) {
+                if nbytes == 0 {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Read for ConnectionStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ConnectionStream::Plain(socket) => socket.read(buf),
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for ConnectionStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ConnectionStream::Plain(socket) => socket.write(buf),
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ConnectionStream::Plain(socket) => socket.flush(),
+            #[cfg(feature = "tls")]
+            ConnectionStream::Tls(stream) => stream.flush(),
+        }
+    }
+}
@@ -1,131 +1,387 @@
-use std::convert::TryInto;
-use std::io::{Error, Read};
+use std::io::{Error, ErrorKind, Read, Write};
 use std::net::TcpStream;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+use crate::h2::{self, H2Connection, H2Response};
+use crate::http::upgraded_stream::Upgrade;
 use crate::http::{
-    HttpError, HttpRequest, HttpRequestBody, HttpRequestHeader, HttpResponseBuilder, HttpStatus,
+    HttpError, HttpRequest, HttpResponse, HttpResponseBuilder, HttpStatus, UpgradedStream,
 };
 use crate::route::Router;
+use crate::server::{ServerConfig, ShutdownHandle, TlsAcceptor};
+use crate::worker::connection_stream::ConnectionStream;
+use crate::worker::request_parser::{self, is_keep_alive_requested, RequestParser};
 use crate::worker::task;
 
+// what has arrived on the connection, it is matched right after it is made
+#[allow(clippy::large_enum_variant)]
+enum Incoming {
+    Request(HttpRequest),
+    // the client has started HTTP/2 with the prior knowledge
+    Http2Preface,
+    // the request has been rejected before its body was sent, after 'Expect: 100-continue'
+    Rejected(HttpResponse),
+}
+
 pub struct HttpTask {
-    buf_reader: TcpStream,
+    stream: ConnectionStream,
     router: Arc<Router>,
+    server_config: Arc<ServerConfig>,
+    shutdown_handle: ShutdownHandle,
+    request_parser: RequestParser,
+    // a response must not be written over a half-written response
+    is_responding: bool,
 }
 
 impl task::Task for HttpTask {
     fn execute(&mut self) {
         self.handle_connection();
     }
+
+    // a handler has panicked, so the client gets '500 Internal Server Error' instead of a dropped connection
+    fn on_panic(&mut self) {
+        if self.is_responding {
+            return;
+        }
+
+        self.is_responding = true;
+        self.stream.respond(&mut HttpResponse::new_with(HttpStatus::INTERNAL_SERVER_ERROR));
+    }
+
+    // the job queue is full, so the client is told to come back later without reading the request
+    fn on_rejected(&mut self) {
+        // the TLS handshake would block the accepting thread, so the connection is just closed
+        if self.stream.is_tls() {
+            return;
+        }
+
+        let mut http_response = HttpResponse::new_with(HttpStatus::SERVICE_UNAVAILABLE);
+        http_response.append_header(
+            "Retry-After",
+            &self.server_config.retry_after.as_secs().to_string(),
+        );
+        self.stream.respond(&mut http_response);
+        self.stream.close_gracefully();
+    }
 }
 
-// TODO: move to config
-const MAX_HEADER_SIZE: usize = 80_000; // 80KB
+// how often an idle connection checks the shutdown
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const READ_BUFFER_SIZE: usize = 8 * 1024;
 
 impl HttpTask {
-    pub fn new(stream: TcpStream, router: Arc<Router>) -> Result<HttpTask, Error> {
-        // delete because of the WouldBlock error on chromium base browsers
-        // stream.set_read_timeout(Some(Duration::from_millis(1000)))?;
+    pub fn new(
+        stream: TcpStream,
+        router: Arc<Router>,
+        server_config: Arc<ServerConfig>,
+        shutdown_handle: ShutdownHandle,
+        tls_acceptor: Option<&TlsAcceptor>,
+    ) -> Result<HttpTask, Error> {
+        // read timeouts are set for each phase, an idle connection (e.g. preconnected by chromium) is
+        // closed quietly by 'wait_for_request' instead of failing a read with 'WouldBlock'
+        stream.set_write_timeout(Some(server_config.write_timeout))?;
+
         Ok(HttpTask {
-            buf_reader: stream,
+            stream: ConnectionStream::new(stream, tls_acceptor)?,
+            request_parser: RequestParser::new(server_config.clone(), router.clone()),
             router,
+            server_config,
+            shutdown_handle,
+            is_responding: false,
         })
     }
 
+    // serve requests until the client or the server closes the connection
     fn handle_connection(&mut self) {
+        while self.wait_for_request() {
+            if !self.handle_request() {
+                break;
+            }
+        }
+    }
+
+    // returns whether the connection can be used for the next request
+    fn handle_request(&mut self) -> bool {
+        self.is_responding = false;
+
         match self.make_http_request() {
-            Ok(http_request) => {
+            Ok(Incoming::Http2Preface) => {
+                let mut h2_connection =
+                    H2Connection::new(self.server_config.clone(), self.router.clone());
+                h2_connection.push(&self.request_parser.take_buffer());
+                self.serve_http2(h2_connection);
+
+                false
+            }
+            Ok(Incoming::Rejected(mut http_response)) => {
+                // the body may still be sent after the client stops waiting, it is never read
+                http_response.set_keep_alive(false);
+                self.is_responding = true;
+                self.stream.respond(&mut http_response);
+                self.stream.close_gracefully();
+
+                false
+            }
+            Ok(Incoming::Request(mut http_request)) => {
+                http_request.set_tls_info(self.stream.get_tls_info());
+                if let Some(mut h2_connection) = self.upgrade_to_http2(&http_request) {
+                    self.is_responding = true;
+                    if self.stream.write_all(h2::SWITCHING_TO_H2C).is_ok() {
+                        h2_connection.open_upgraded_stream(http_request);
+                        h2_connection.push(&self.request_parser.take_buffer());
+                        self.serve_http2(h2_connection);
+                    }
+
+                    return false;
+                }
+
+                let keep_alive = is_keep_alive_requested(&http_request);
                 // find the Route for url, and execute handler.
                 let (_, mut http_response) = self.router.execute_route(http_request);
+                // the server which is shutting down closes connections after the response
+                let keep_alive = keep_alive && !self.shutdown_handle.is_shutdown();
+                http_response.set_keep_alive(keep_alive);
                 // response to the client
-                http_response.respond(&mut self.buf_reader);
+                self.is_responding = true;
+                self.stream.respond(&mut http_response);
+
+                if let Some(upgrade) = http_response.take_upgrade() {
+                    self.upgrade(upgrade);
+                    return false;
+                }
+
+                keep_alive
             }
             Err(error) => {
                 if let Ok(mut http_response) = HttpResponseBuilder::new()
-                    .set_status(HttpStatus::BAD_REQUEST)
+                    .set_status(error.get_status())
                     .build()
                 {
                     println!("try to send response");
-                    http_response.respond(&mut self.buf_reader);
+                    self.is_responding = true;
+                    self.stream.respond(&mut http_response);
+                    self.stream.close_gracefully();
                 } else {
                     // what should i do?
                     eprintln!("[error] error occurs while building response: {:?}", error);
                 }
+
+                // the rest of the stream can not be trusted
+                false
+            }
+        }
+    }
+
+    // wait until the next request arrives.
+    // returns false if the connection is closed, idle for too long, or the server is shutting down.
+    fn wait_for_request(&mut self) -> bool {
+        if self.request_parser.has_buffered_data() || self.stream.has_buffered_data() {
+            return true;
+        }
+
+        let idle_since = Instant::now();
+        let mut buffer = [0_u8; 1];
+
+        loop {
+            // a connection accepted before the shutdown is still served if its request has arrived
+            let poll_interval = if self.shutdown_handle.is_shutdown() {
+                Duration::from_millis(1)
+            } else {
+                IDLE_POLL_INTERVAL
+            };
+
+            let socket = self.stream.get_socket();
+            if socket.set_read_timeout(Some(poll_interval)).is_err() {
+                return false;
+            }
+
+            let result = socket.peek(&mut buffer);
+            match result {
+                Ok(0) => return false,
+                Ok(_) => {
+                    return socket.set_read_timeout(None).is_ok();
+                }
+                Err(error) if is_timeout(&error) => {
+                    if self.shutdown_handle.is_shutdown()
+                        || idle_since.elapsed() >= self.server_config.keep_alive_timeout
+                    {
+                        return false;
+                    }
+                }
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return false,
             }
         }
     }
 
-    fn make_http_request(&mut self) -> Result<HttpRequest, HttpError> {
-        // parse http header
-        let raw_request_header = self.get_raw_request_header()?;
-        let request_header: HttpRequestHeader = raw_request_header.try_into()?;
-
-        // get body content if method is post
-        let request_body = match request_header.get_content_length() {
-            Some(content_length) if content_length > 0 => Some(HttpRequestBody::new(
-                self.get_raw_request_body(content_length)?,
-            )),
-            _ => None,
+    // hand the connection over after '101 Switching Protocols', on this worker
+    fn upgrade(&mut self, upgrade: Upgrade) {
+        // the task keeps a duplicate of the socket, the handler has returned before the task closes it
+        let socket = match self.stream.get_socket().try_clone() {
+            Ok(socket) => socket,
+            Err(error) => {
+                eprintln!("[error] fail to take over the connection: {}", error);
+                return;
+            }
         };
+        let stream = std::mem::replace(&mut self.stream, ConnectionStream::Plain(socket));
+        if stream.get_socket().set_read_timeout(None).is_err() {
+            return;
+        }
 
-        Ok(HttpRequest::new(request_header, request_body)?)
+        let buffered = self.request_parser.take_buffer();
+        upgrade(UpgradedStream::new(stream, buffered, self.shutdown_handle.clone()));
     }
 
-    fn get_raw_request_header(&mut self) -> Result<Vec<u8>, HttpError> {
-        let mut header = vec![0_u8; 1024];
-        let mut header_size: usize = 0;
+    // TLS negotiates HTTP/2 with ALPN instead
+    fn upgrade_to_http2(&self, http_request: &HttpRequest) -> Option<H2Connection> {
+        if self.stream.is_tls() {
+            return None;
+        }
 
-        let stream = &mut self.buf_reader;
-        let mut buffer = [0u8; 1];
-        let mut last_new_line_index: usize = 0;
+        H2Connection::upgrade(self.server_config.clone(), self.router.clone(), http_request)
+    }
+
+    // serve the streams of a HTTP/2 connection until it is closed
+    fn serve_http2(&mut self, mut h2_connection: H2Connection) {
+        // the panic of a handler is answered on its stream, not as HTTP/1
+        self.is_responding = true;
+        let tls_info = self.stream.get_tls_info();
+        let mut buffer = [0_u8; READ_BUFFER_SIZE];
+        let mut idle_since = Instant::now();
 
         loop {
-            match stream.read_exact(&mut buffer) {
-                Ok(()) => {
-                    header.push(buffer[0]);
-                    header_size += 1;
-
-                    if buffer[0] == b'\n' {
-                        let gap_with_last = header_size - last_new_line_index;
-                        if gap_with_last == 1 || gap_with_last == 2 {
-                            break;
-                        } else {
-                            last_new_line_index = header_size;
-                        }
-                    }
+            for (stream_id, mut http_request) in h2_connection.take_requests() {
+                http_request.set_tls_info(tls_info.clone());
+                h2_connection.respond(stream_id, self.execute_route(http_request));
+            }
+
+            if self.shutdown_handle.is_shutdown() {
+                h2_connection.go_away();
+            }
+            if h2_connection.is_idle() {
+                if idle_since.elapsed() >= self.server_config.keep_alive_timeout {
+                    h2_connection.go_away();
                 }
-                Err(error) => {
-                    eprintln!("[error] error while read stream: {:?}\n", error);
-                    return Err(HttpError::ReadStreamError);
+            } else {
+                idle_since = Instant::now();
+            }
+
+            loop {
+                let output = h2_connection.poll_output();
+                if output.is_empty() {
+                    break;
+                }
+                if let Err(error) = self.stream.write_all(&output) {
+                    eprintln!("[error] error while writing HTTP/2 frames: {:?}", error);
+                    return;
                 }
             }
+            if h2_connection.is_finished() {
+                break;
+            }
 
-            if header_size >= MAX_HEADER_SIZE {
-                return Err(HttpError::ExceedCapacity);
+            if self.stream.get_socket().set_read_timeout(Some(IDLE_POLL_INTERVAL)).is_err() {
+                return;
+            }
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return,
+                Ok(nbytes) => h2_connection.push(&buffer[..nbytes]),
+                Err(error) if is_timeout(&error) => {}
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(_) => return,
             }
         }
 
-        Ok(header[header.len() - header_size..].to_vec())
+        self.stream.close_gracefully();
+    }
+
+    // a handler which panics fails only its stream
+    fn execute_route(&self, http_request: HttpRequest) -> H2Response {
+        let result =
+            panic::catch_unwind(AssertUnwindSafe(|| self.router.execute_route(http_request)));
+        match result {
+            Ok((_, mut http_response)) => H2Response::new(&mut http_response).unwrap_or_else(|error| {
+                eprintln!("[error] error occurs while building response: {:?}", error);
+                H2Response::new_with(HttpStatus::INTERNAL_SERVER_ERROR)
+            }),
+            Err(_) => H2Response::new_with(HttpStatus::INTERNAL_SERVER_ERROR),
+        }
     }
 
-    fn get_raw_request_body(&mut self, content_length: usize) -> Result<Vec<u8>, HttpError> {
-        let mut body_buffer = vec![0_u8; content_length];
-        let mut offset = 0_usize;
+    fn make_http_request(&mut self) -> Result<Incoming, HttpError> {
+        let mut buffer = [0_u8; READ_BUFFER_SIZE];
+        // a client which trickles bytes can not hold the worker longer than the timeout
+        let mut deadline = Instant::now() + self.server_config.header_read_timeout;
+        let mut is_reading_body = false;
 
-        // TODO: make the maximum content size settable
         loop {
-            if let Ok(nbytes) = self.buf_reader.read(&mut body_buffer) {
-                offset += nbytes;
+            if self.server_config.http2 && self.request_parser.is_http2_preface() {
+                return Ok(Incoming::Http2Preface);
+            }
+            if let Some(http_request) = self.request_parser.parse()? {
+                return Ok(Incoming::Request(http_request));
+            }
 
-                if nbytes == 0 || offset >= content_length {
-                    break;
+            // the client waits until the request is accepted by its header
+            if let Some(mut http_request) = self.request_parser.take_expectation() {
+                http_request.set_tls_info(self.stream.get_tls_info());
+                if let Some(http_response) = self.router.check_continue(&http_request) {
+                    return Ok(Incoming::Rejected(http_response));
                 }
-            } else {
-                return Err(HttpError::BodyReadError);
-            };
+                if self.stream.write_all(request_parser::CONTINUE).is_err() {
+                    return Err(HttpError::ReadStreamError);
+                }
+            }
+
+            // the header has arrived, the body has its own deadline
+            if !is_reading_body && self.request_parser.is_reading_body() {
+                is_reading_body = true;
+                deadline = Instant::now() + self.server_config.body_read_timeout;
+            }
+
+            let nbytes = self.read_before(&mut buffer, deadline)?;
+            if nbytes == 0 {
+                eprintln!("[error] the connection is closed while reading the request\n");
+                return Err(if is_reading_body {
+                    HttpError::BodyReadError
+                } else {
+                    HttpError::ReadStreamError
+                });
+            }
+
+            self.request_parser.push(&buffer[..nbytes]);
         }
+    }
 
-        Ok(body_buffer)
+    // read from the stream, fails with 'HttpError::Timeout' when the deadline has passed
+    fn read_before(&mut self, buffer: &mut [u8], deadline: Instant) -> Result<usize, HttpError> {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(HttpError::Timeout);
+            }
+
+            if self.stream.get_socket().set_read_timeout(Some(remaining)).is_err() {
+                return Err(HttpError::ReadStreamError);
+            }
+
+            match self.stream.read(buffer) {
+                Ok(nbytes) => return Ok(nbytes),
+                // the deadline is checked again, a timeout may fire a little early
+                Err(error) if is_timeout(&error) => {}
+                Err(error) if error.kind() == ErrorKind::Interrupted => {}
+                Err(error) => {
+                    eprintln!("[error] error while read stream: {:?}\n", error);
+                    return Err(HttpError::ReadStreamError);
+                }
+            }
+        }
     }
 }
+
+fn is_timeout(error: &Error) -> bool {
+    error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut
+}
@@ -1,8 +1,14 @@
+pub(crate) use connection_stream::ConnectionStream;
 pub use http_task::HttpTask;
+pub use queue_metrics::QueueMetrics;
 pub use task::Task;
 
 pub mod worker_manager;
+#[allow(clippy::module_inception)]
 mod worker;
 
 mod task;
 mod http_task;
+mod connection_stream;
+pub(crate) mod request_parser;
+mod queue_metrics;
@@ -0,0 +1,68 @@
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+// counters of the job queue between the accept loop and the workers
+#[derive(Debug)]
+pub struct QueueMetrics {
+    capacity: usize,
+    depth: AtomicUsize,
+    peak_depth: AtomicUsize,
+    enqueued_count: AtomicU64,
+    rejected_count: AtomicU64,
+}
+
+impl QueueMetrics {
+    pub fn new(capacity: usize) -> Self {
+        QueueMetrics {
+            capacity,
+            depth: AtomicUsize::new(0),
+            peak_depth: AtomicUsize::new(0),
+            enqueued_count: AtomicU64::new(0),
+            rejected_count: AtomicU64::new(0),
+        }
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // the number of jobs waiting for a worker
+    pub fn get_depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    // the highest depth since the server has started
+    pub fn get_peak_depth(&self) -> usize {
+        self.peak_depth.load(Ordering::Relaxed)
+    }
+
+    pub fn get_enqueued_count(&self) -> u64 {
+        self.enqueued_count.load(Ordering::Relaxed)
+    }
+
+    // the number of jobs which are shed because the queue was full
+    pub fn get_rejected_count(&self) -> u64 {
+        self.rejected_count.load(Ordering::Relaxed)
+    }
+
+    // counted before sending the job, so a worker never takes a job which is not counted yet
+    pub(crate) fn on_enqueueing(&self) {
+        self.depth.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn on_enqueued(&self) {
+        self.peak_depth.fetch_max(self.get_depth(), Ordering::Relaxed);
+        self.enqueued_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    // the job could not be sent after 'on_enqueueing'
+    pub(crate) fn on_enqueue_failed(&self, is_rejected: bool) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+        if is_rejected {
+            self.rejected_count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn on_dequeued(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,382 @@
+use std::convert::TryInto;
+use std::sync::Arc;
+
+use crate::http::{HttpError, HttpRequest, HttpRequestBody, HttpRequestHeader, ReadHeaderAs};
+use crate::http::method::HttpMethod;
+use crate::route::Router;
+use crate::server::ServerConfig;
+use crate::url::url_path::UrlPath;
+
+// room for the method and the version in the request line
+const REQUEST_LINE_OVERHEAD: usize = 32;
+// the interim response which lets a client with 'Expect: 100-continue' send the body
+pub(crate) const CONTINUE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+// the header has been parsed, and the body is being received
+struct PendingRequest {
+    header: HttpRequestHeader,
+    header_size: usize,
+    content_length: usize,
+    // the client waits for '100 Continue' before sending the body
+    expects_continue: bool,
+}
+
+// makes requests from bytes which arrive in pieces.
+// the limits of 'ServerConfig' are checked as the bytes arrive, so nothing is allocated for
+// a request which exceeds them.
+pub(crate) struct RequestParser {
+    server_config: Arc<ServerConfig>,
+    router: Arc<Router>,
+    buffer: Vec<u8>,
+    // the header is scanned only once, even if it arrives byte by byte
+    scanned_size: usize,
+    last_new_line_index: Option<usize>,
+    line_count: usize,
+    pending_request: Option<PendingRequest>,
+}
+
+impl RequestParser {
+    pub fn new(server_config: Arc<ServerConfig>, router: Arc<Router>) -> Self {
+        RequestParser {
+            server_config,
+            router,
+            buffer: vec![],
+            scanned_size: 0,
+            last_new_line_index: None,
+            line_count: 0,
+            pending_request: None,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    // the bytes of the request being received and the header of the next one have arrived.
+    // the rest is left in the socket until they are parsed, so the limits of 'ServerConfig' are
+    // checked before the buffer grows any more.
+    pub fn is_full(&self) -> bool {
+        let request_size = match &self.pending_request {
+            Some(pending) => pending.header_size + pending.content_length,
+            None => 0,
+        };
+
+        self.buffer.len() >= request_size + self.server_config.max_header_size
+    }
+
+    // some bytes of the next request have arrived already
+    pub fn has_buffered_data(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    // the connection preface of HTTP/2 with the prior knowledge, which looks like a 'PRI' request
+    pub fn is_http2_preface(&self) -> bool {
+        self.pending_request.is_none() && self.buffer.starts_with(b"PRI * HTTP/2.0\r\n")
+    }
+
+    // the bytes which are not parsed, e.g. the frames after the upgrade to HTTP/2
+    pub fn take_buffer(&mut self) -> Vec<u8> {
+        self.scanned_size = 0;
+        self.last_new_line_index = None;
+        self.line_count = 0;
+        std::mem::take(&mut self.buffer)
+    }
+
+    // whether the next request can be handled together with the ones before it, only GET is safe
+    pub fn is_next_request_safe(&self) -> bool {
+        match &self.pending_request {
+            Some(pending) => pending.header.get_method() == HttpMethod::GET,
+            None => self.buffer.starts_with(b"GET "),
+        }
+    }
+
+    // the header of a request which waits for '100 Continue', given only once before its body.
+    // the server answers '100 Continue', or rejects the request without reading the body.
+    pub fn take_expectation(&mut self) -> Option<HttpRequest> {
+        let pending = self.pending_request.as_mut()?;
+        if !pending.expects_continue {
+            return None;
+        }
+
+        pending.expects_continue = false;
+        // the client has started to send the body without waiting
+        if self.buffer.len() > pending.header_size {
+            return None;
+        }
+
+        HttpRequest::new(pending.header.clone(), None).ok()
+    }
+
+    pub fn is_reading_body(&self) -> bool {
+        self.pending_request.is_some()
+    }
+
+    // returns a request when all of its bytes have arrived, the rest is kept for the next one
+    pub fn parse(&mut self) -> Result<Option<HttpRequest>, HttpError> {
+        if self.pending_request.is_none() {
+            let header_size = if let Some(header_size) = self.scan_header()? {
+                header_size
+            } else {
+                return Ok(None);
+            };
+            self.pending_request = Some(self.parse_header(header_size)?);
+        }
+
+        let (header_size, content_length) = match &self.pending_request {
+            Some(pending) => (pending.header_size, pending.content_length),
+            None => return Ok(None),
+        };
+        if self.buffer.len() < header_size + content_length {
+            return Ok(None);
+        }
+
+        let body = if content_length > 0 {
+            Some(HttpRequestBody::new(
+                self.buffer[header_size..header_size + content_length].to_vec(),
+            ))
+        } else {
+            None
+        };
+        self.buffer.drain(..header_size + content_length);
+
+        let pending = self.pending_request.take().ok_or(HttpError::HeaderParseError)?;
+        self.scanned_size = 0;
+        self.last_new_line_index = None;
+        self.line_count = 0;
+
+        HttpRequest::new(pending.header, body).map(Some)
+    }
+
+    // find the end of the header, returns the size of the header including the empty line
+    fn scan_header(&mut self) -> Result<Option<usize>, HttpError> {
+        while self.scanned_size < self.buffer.len() {
+            let byte = self.buffer[self.scanned_size];
+            self.scanned_size += 1;
+
+            if byte == b'\n' {
+                if let Some(last_new_line_index) = self.last_new_line_index {
+                    let gap_with_last = self.scanned_size - last_new_line_index;
+                    if gap_with_last == 1 || gap_with_last == 2 {
+                        return Ok(Some(self.scanned_size));
+                    }
+                }
+
+                self.last_new_line_index = Some(self.scanned_size);
+                self.line_count += 1;
+
+                // the first line is the request line
+                if self.line_count > self.server_config.max_header_count + 1 {
+                    return Err(HttpError::HeaderTooLarge);
+                }
+            }
+
+            // still in the request line, the exact uri length is checked after parsing
+            if self.line_count == 0
+                && self.scanned_size > self.server_config.max_uri_length + REQUEST_LINE_OVERHEAD
+            {
+                return Err(HttpError::UriTooLong);
+            }
+
+            if self.scanned_size >= self.server_config.max_header_size {
+                return Err(HttpError::HeaderTooLarge);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn parse_header(&self, header_size: usize) -> Result<PendingRequest, HttpError> {
+        let header = HttpRequestHeader::parse(
+            self.buffer[..header_size].to_vec(),
+            self.server_config.lenient_parsing,
+        )?;
+        if header.get_req_url().len() > self.server_config.max_uri_length {
+            return Err(HttpError::UriTooLong);
+        }
+
+        // the length is given by the client, so it is checked before receiving the body
+        let content_length = header.get_content_length().unwrap_or(0);
+        if content_length > 0
+            && content_length > get_max_body_size(&self.router, &self.server_config, &header)?
+        {
+            return Err(HttpError::PayloadTooLarge);
+        }
+
+        let expects_continue = content_length > 0 && get_expects_continue(&header)?;
+
+        Ok(PendingRequest {
+            header,
+            header_size,
+            content_length,
+            expects_continue,
+        })
+    }
+}
+
+// the limit of the route which will handle the request, or the server-wide limit
+pub(crate) fn get_max_body_size(
+    router: &Router,
+    server_config: &ServerConfig,
+    header: &HttpRequestHeader,
+) -> Result<usize, HttpError> {
+    let req_path: UrlPath = header.try_into().map_err(|_| HttpError::HeaderParseError)?;
+    let max_body_size = router.find_max_body_size(header.get_method(), req_path.get_pathname());
+
+    Ok(max_body_size.unwrap_or(server_config.max_body_size))
+}
+
+// only '100-continue' is supported, the expectations of HTTP/1.0 are ignored.
+// ref <https://www.rfc-editor.org/rfc/rfc9110#section-10.1.1>
+fn get_expects_continue(header: &HttpRequestHeader) -> Result<bool, HttpError> {
+    let version = header.get_version();
+    if (version.get_major(), version.get_minor()) < (1, 1) {
+        return Ok(false);
+    }
+
+    let expect: Option<String> = header.get_header("expect");
+    match expect {
+        Some(expect) if expect.trim().eq_ignore_ascii_case("100-continue") => Ok(true),
+        Some(_) => Err(HttpError::ExpectationFailed),
+        None => Ok(false),
+    }
+}
+
+// HTTP/1.1 keeps the connection by default, HTTP/1.0 only when the client asks
+pub(crate) fn is_keep_alive_requested(http_request: &HttpRequest) -> bool {
+    let connection: Option<String> = http_request.get_header("connection");
+    let connection = connection.map(|connection| connection.to_lowercase());
+
+    if let Some(connection) = connection {
+        let mut tokens = connection.split(',').map(str::trim);
+        if tokens.clone().any(|token| token == "close") {
+            return false;
+        }
+        if tokens.any(|token| token == "keep-alive") {
+            return true;
+        }
+    }
+
+    let version = http_request.get_request_header().get_version();
+    (version.get_major(), version.get_minor()) >= (1, 1)
+}
+
+// whether the requests behind it can be handled before it is answered.
+// a request which may change the state, or which ends the connection, is handled alone.
+pub(crate) fn can_pipeline_after(http_request: &HttpRequest) -> bool {
+    let upgrade: Option<String> = http_request.get_header("upgrade");
+
+    http_request.get_method() == HttpMethod::GET
+        && upgrade.is_none()
+        && is_keep_alive_requested(http_request)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_parser() -> RequestParser {
+        let server_config = ServerConfig {
+            max_header_count: 2,
+            max_body_size: 10,
+            ..ServerConfig::default()
+        };
+
+        RequestParser::new(Arc::new(server_config), Arc::new(Router::default()))
+    }
+
+    #[test]
+    fn test_parse_in_pieces() {
+        let mut parser = make_parser();
+        let raw = b"POST /a HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcGET /b HTTP/1.1\r\n\r\n";
+
+        for byte in raw[..raw.len() - 1].iter() {
+            parser.push(&[*byte]);
+            if let Some(request) = parser.parse().unwrap() {
+                assert_eq!(request.get_req_path().get_pathname(), "/a");
+                assert_eq!(request.get_body().unwrap().get_raw(), b"abc");
+            }
+        }
+        assert!(parser.parse().unwrap().is_none());
+
+        parser.push(&raw[raw.len() - 1..]);
+        let request = parser.parse().unwrap().unwrap();
+        assert_eq!(request.get_req_path().get_pathname(), "/b");
+        assert!(!parser.has_buffered_data());
+    }
+
+    #[test]
+    fn test_pipelining() {
+        let mut parser = make_parser();
+        parser.push(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\nUpgrade: h2c\r\n\r\nPOST /c HTTP/1.1\r\n\r\n");
+
+        assert!(parser.is_next_request_safe());
+        assert!(can_pipeline_after(&parser.parse().unwrap().unwrap()));
+        assert!(parser.is_next_request_safe());
+        assert!(!can_pipeline_after(&parser.parse().unwrap().unwrap()));
+        assert!(!parser.is_next_request_safe());
+        assert!(!can_pipeline_after(&parser.parse().unwrap().unwrap()));
+
+        parser.push(b"GET /d HTTP/1.0\r\n\r\n");
+        assert!(!can_pipeline_after(&parser.parse().unwrap().unwrap()));
+    }
+
+    #[test]
+    fn test_expectation() {
+        let mut parser = make_parser();
+        parser.push(b"POST /a HTTP/1.1\r\nExpect: 100-Continue\r\nContent-Length: 3\r\n\r\n");
+        assert!(parser.parse().unwrap().is_none());
+        assert_eq!(parser.take_expectation().unwrap().get_req_path().get_pathname(), "/a");
+        assert!(parser.take_expectation().is_none());
+        parser.push(b"abc");
+        assert_eq!(parser.parse().unwrap().unwrap().get_body().unwrap().get_raw(), b"abc");
+
+        // the body has been sent without waiting
+        parser.push(b"POST /b HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\na");
+        assert!(parser.parse().unwrap().is_none());
+        assert!(parser.take_expectation().is_none());
+
+        let mut parser = make_parser();
+        parser.push(b"POST /c HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\n");
+        assert!(parser.parse().unwrap().is_none());
+        assert!(parser.take_expectation().is_none());
+
+        let mut parser = make_parser();
+        parser.push(b"POST /d HTTP/1.1\r\nExpect: something\r\nContent-Length: 3\r\n\r\n");
+        assert!(matches!(parser.parse(), Err(HttpError::ExpectationFailed)));
+    }
+
+    #[test]
+    fn test_limits() {
+        let mut parser = make_parser();
+        parser.push(b"GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n");
+        assert!(matches!(parser.parse(), Err(HttpError::HeaderTooLarge)));
+
+        let mut parser = make_parser();
+        parser.push(b"POST / HTTP/1.1\r\nContent-Length: 11\r\n\r\n");
+        assert!(matches!(parser.parse(), Err(HttpError::PayloadTooLarge)));
+    }
+
+    #[test]
+    fn test_is_full() {
+        let mut parser = make_parser();
+        let max_header_size = parser.server_config.max_header_size;
+
+        // a header which never ends is rejected before more than the limit is buffered
+        parser.push(&vec![b'a'; max_header_size - 1]);
+        assert!(!parser.is_full());
+        parser.push(b"a");
+        assert!(parser.is_full());
+        assert!(matches!(parser.parse(), Err(HttpError::UriTooLong)));
+
+        // the body and the header of the next request are buffered
+        let mut parser = make_parser();
+        let header = b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n";
+        parser.push(header);
+        assert!(parser.parse().unwrap().is_none());
+        parser.push(&vec![b'a'; 10 + max_header_size - 1]);
+        assert!(!parser.is_full());
+        parser.push(b"a");
+        assert!(parser.is_full());
+        assert_eq!(parser.parse().unwrap().unwrap().get_body().unwrap().get_raw(), &[b'a'; 10]);
+    }
+}
@@ -1,3 +1,9 @@
 pub trait Task: Send {
     fn execute(&mut self);
+
+    // called by the worker when 'execute' panics, the task can tell its client what happened
+    fn on_panic(&mut self) {}
+
+    // called instead of 'execute' when the job queue is full, the task can tell its client to retry later
+    fn on_rejected(&mut self) {}
 }
@@ -1,9 +1,11 @@
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, mpsc, Mutex};
 use std::thread;
 
-use crate::worker::worker_manager::Message;
+use crate::worker::QueueMetrics;
+use crate::worker::worker_manager::{Message, Supervision};
 
-type SharedReceiver = Arc<Mutex<mpsc::Receiver<Message>>>;
+pub(crate) type SharedReceiver = Arc<Mutex<mpsc::Receiver<Message>>>;
 
 pub struct Worker {
     pub id: u16,
@@ -11,15 +13,53 @@ pub struct Worker {
 }
 
 impl Worker {
-    pub fn new(id: u16, receiver: SharedReceiver) -> Worker {
+    pub fn new(
+        id: u16,
+        receiver: SharedReceiver,
+        queue_metrics: Arc<QueueMetrics>,
+        supervision_sender: mpsc::Sender<Supervision>,
+    ) -> Worker {
+        let death_notice = DeathNotice {
+            id,
+            sender: supervision_sender,
+            is_terminated: false,
+        };
         let mut worker = Worker { id, thread: None };
-        worker.thread = Some(make_thread(worker.id, receiver));
+        worker.thread = Some(make_thread(worker.id, receiver, queue_metrics, death_notice));
 
         worker
     }
 }
 
-fn make_thread(id: u16, receiver: SharedReceiver) -> thread::JoinHandle<()> {
+// tells the supervisor that the thread has stopped although nobody asked it to terminate,
+// it is dropped even when the thread unwinds
+struct DeathNotice {
+    id: u16,
+    sender: mpsc::Sender<Supervision>,
+    is_terminated: bool,
+}
+
+impl DeathNotice {
+    fn on_terminated(&mut self) {
+        self.is_terminated = true;
+    }
+}
+
+impl Drop for DeathNotice {
+    fn drop(&mut self) {
+        if !self.is_terminated {
+            // the supervisor has stopped with the server
+            self.sender.send(Supervision::Died(self.id)).ok();
+        }
+    }
+}
+
+fn make_thread(
+    id: u16,
+    receiver: SharedReceiver,
+    queue_metrics: Arc<QueueMetrics>,
+    mut death_notice: DeathNotice,
+) -> thread::JoinHandle<()> {
     // to unlock the mutex, it needs to separate the lifetime of 'receiver' and 'message'
     fn get_message(id: u16, receiver: &SharedReceiver) -> Option<Message> {
         let mutex = if let Ok(mutex) = receiver.lock() {
@@ -41,20 +81,28 @@ fn make_thread(id: u16, receiver: SharedReceiver) -> thread::JoinHandle<()> {
     }
     thread::spawn(move || {
         loop {
+            // the channel is closed, nobody will send a message anymore
             let message = if let Some(message) = get_message(id, &receiver) {
                 message
             } else {
-                continue
+                break
             };
 
             match message {
                 Message::Job(mut task) => {
-                    task.execute();
+                    queue_metrics.on_dequeued();
+                    // a panic of a task must not kill the worker thread
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| task.execute()));
+                    if result.is_err() {
+                        eprintln!("[thread {}] task panicked, the worker keeps running", id);
+                        task.on_panic();
+                    }
+                },
+                Message::Terminate => {
+                    death_notice.on_terminated();
+                    break
                 },
-                Message::Terminate => break,
             }
         }
     })
 }
-
-
@@ -1,85 +1,336 @@
 //! ref <https://doc.rust-lang.org/book/ch20-02-multithreaded.html>
 
-use std::sync::{Arc, mpsc, Mutex};
-use std::sync::mpsc::SendError;
+use std::sync::{Arc, mpsc, Mutex, MutexGuard};
+use std::sync::mpsc::TrySendError;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::worker::task::Task;
+use crate::worker::{QueueMetrics, Task};
 
-use super::worker::Worker;
+use super::worker::{SharedReceiver, Worker};
 
 pub enum Message {
     Job(Box<dyn Task>),
     Terminate,
 }
 
+// sent to the supervisor of the workers
+pub(crate) enum Supervision {
+    Died(u16),
+    Stop,
+}
+
 pub struct WorkerManager {
-    workers: Vec<Worker>,
-    sender: mpsc::Sender<Message>,
+    workers: Arc<Mutex<Vec<Worker>>>,
+    sender: mpsc::SyncSender<Message>,
+    queue_metrics: Arc<QueueMetrics>,
+    supervision_sender: mpsc::Sender<Supervision>,
+    supervisor: Option<thread::JoinHandle<()>>,
+    is_terminated: bool,
 }
 
 impl WorkerManager {
-    pub fn new(worker_count: u16) -> WorkerManager {
-        let (sender, receiver) = mpsc::channel();
+    // at most 'queue_capacity' tasks wait for a worker, 0 means a task is only handed to an idle worker
+    pub fn new(worker_count: u16, queue_capacity: usize) -> WorkerManager {
+        let (sender, receiver) = mpsc::sync_channel(queue_capacity);
         let receiver = Arc::new(Mutex::new(receiver));
+        let queue_metrics = Arc::new(QueueMetrics::new(queue_capacity));
+        let (supervision_sender, supervision_receiver) = mpsc::channel();
 
-        let workers: Vec<Worker> = Vec::with_capacity(worker_count as usize);
-        let mut workers_manager = WorkerManager { workers, sender };
+        let mut workers: Vec<Worker> = Vec::with_capacity(worker_count as usize);
         for i in 0..worker_count {
-            workers_manager
-                .workers
-                .push(Worker::new(i, Arc::clone(&receiver)));
+            workers.push(Worker::new(
+                i,
+                Arc::clone(&receiver),
+                Arc::clone(&queue_metrics),
+                supervision_sender.clone(),
+            ));
         }
+        let workers = Arc::new(Mutex::new(workers));
 
-        workers_manager
+        let supervisor = {
+            let workers = Arc::clone(&workers);
+            let queue_metrics = Arc::clone(&queue_metrics);
+            let supervision_sender = supervision_sender.clone();
+            thread::spawn(move || {
+                supervise(workers, supervision_receiver, supervision_sender, receiver, queue_metrics)
+            })
+        };
+
+        WorkerManager {
+            workers,
+            sender,
+            queue_metrics,
+            supervision_sender,
+            supervisor: Some(supervisor),
+            is_terminated: false,
+        }
     }
 
-    pub fn request(&self, task: Box<dyn Task>) -> Result<(), SendError<Message>> {
-        self.sender.send(Message::Job(task))?;
+    // never blocks, the task is given back in 'TrySendError::Full' when the queue is full
+    pub fn request(&self, task: Box<dyn Task>) -> Result<(), TrySendError<Message>> {
+        self.queue_metrics.on_enqueueing();
+        if let Err(error) = self.sender.try_send(Message::Job(task)) {
+            self.queue_metrics
+                .on_enqueue_failed(matches!(error, TrySendError::Full(_)));
+            return Err(error);
+        }
+        self.queue_metrics.on_enqueued();
 
         Ok(())
     }
-}
 
-impl Drop for WorkerManager {
-    fn drop(&mut self) {
+    pub fn get_queue_metrics(&self) -> Arc<QueueMetrics> {
+        Arc::clone(&self.queue_metrics)
+    }
+
+    // let the workers finish the queued tasks and stop.
+    // returns false if some workers are still busy after the timeout, they are left behind.
+    pub fn shutdown(&mut self, timeout: Option<Duration>) -> bool {
+        if self.is_terminated {
+            return true;
+        }
+        self.is_terminated = true;
+
+        // the workers which stop from now on are not respawned
+        self.supervision_sender.send(Supervision::Stop).ok();
+        if let Some(supervisor) = self.supervisor.take() {
+            supervisor.join().ok();
+        }
+
         println!("sending terminate message to all workers");
+        let mut workers = lock_workers(&self.workers);
 
-        for _ in &self.workers {
-            self.sender.send(Message::Terminate).unwrap();
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+        // terminate messages are queued behind the tasks, so the queued tasks are handled first
+        for _ in workers.iter() {
+            if !send_terminate(&self.sender, deadline) {
+                eprintln!("[error] fail to send the terminate message");
+            }
         }
+        let mut is_drained = true;
+
+        for worker in workers.iter_mut() {
+            let thread = if let Some(thread) = worker.thread.take() {
+                thread
+            } else {
+                continue;
+            };
+
+            if let Some(deadline) = deadline {
+                while !thread.is_finished() && Instant::now() < deadline {
+                    thread::sleep(Duration::from_millis(10));
+                }
+
+                if !thread.is_finished() {
+                    eprintln!("[error] worker {} is still busy, leave it behind", worker.id);
+                    is_drained = false;
+                    continue;
+                }
+            }
 
-        for worker in &mut self.workers {
             println!("shutting down worker: {}", worker.id);
-            if let Some(thread) = worker.thread.take() {
-                thread.join().unwrap();
+            if thread.join().is_err() {
+                eprintln!("[error] worker {} has died with a panic", worker.id);
+            }
+        }
+
+        is_drained
+    }
+}
+
+fn lock_workers(workers: &Mutex<Vec<Worker>>) -> MutexGuard<'_, Vec<Worker>> {
+    match workers.lock() {
+        Ok(workers) => workers,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+// replaces the workers which report that their thread has stopped unexpectedly,
+// so the pool never shrinks
+fn supervise(
+    workers: Arc<Mutex<Vec<Worker>>>,
+    supervision_receiver: mpsc::Receiver<Supervision>,
+    supervision_sender: mpsc::Sender<Supervision>,
+    receiver: SharedReceiver,
+    queue_metrics: Arc<QueueMetrics>,
+) {
+    while let Ok(Supervision::Died(id)) = supervision_receiver.recv() {
+        let mut workers = lock_workers(&workers);
+        let worker = if let Some(worker) = workers.iter_mut().find(|worker| worker.id == id) {
+            worker
+        } else {
+            continue;
+        };
+
+        if let Some(thread) = worker.thread.take() {
+            if thread.join().is_err() {
+                eprintln!("[error] worker {} has died with a panic", id);
+            } else {
+                eprintln!("[error] worker {} has exited unexpectedly", id);
+            }
+        }
+
+        println!("respawning worker: {}", id);
+        *worker = Worker::new(
+            id,
+            Arc::clone(&receiver),
+            Arc::clone(&queue_metrics),
+            supervision_sender.clone(),
+        );
+    }
+}
+
+// the queue may be full, so it waits for a room until the deadline
+fn send_terminate(sender: &mpsc::SyncSender<Message>, deadline: Option<Instant>) -> bool {
+    let deadline = if let Some(deadline) = deadline {
+        deadline
+    } else {
+        return sender.send(Message::Terminate).is_ok();
+    };
+
+    loop {
+        match sender.try_send(Message::Terminate) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(_)) if Instant::now() < deadline => {
+                thread::sleep(Duration::from_millis(10));
             }
+            Err(_) => return false,
         }
     }
 }
 
+impl Drop for WorkerManager {
+    fn drop(&mut self) {
+        self.shutdown(None);
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::mpsc::Sender;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
     use super::*;
 
+    struct PanicTask {
+        panics_again: bool,
+        on_panic_called: Sender<()>,
+    }
+
+    impl Task for PanicTask {
+        fn execute(&mut self) {
+            panic!("panic in a task");
+        }
+
+        fn on_panic(&mut self) {
+            self.on_panic_called.send(()).unwrap();
+            if self.panics_again {
+                // escapes the worker loop, so the thread exits
+                panic!("panic in on_panic");
+            }
+        }
+    }
+
+    struct SendTask(Sender<u32>, u32);
+
+    impl Task for SendTask {
+        fn execute(&mut self) {
+            self.0.send(self.1).unwrap();
+        }
+    }
+
+    // blocks the worker until something is sent
+    struct BlockTask {
+        started: Sender<()>,
+        unblock: mpsc::Receiver<()>,
+    }
+
+    impl Task for BlockTask {
+        fn execute(&mut self) {
+            self.started.send(()).unwrap();
+            self.unblock.recv().unwrap();
+        }
+    }
+
+    fn get_thread_id(worker_manager: &WorkerManager) -> Option<thread::ThreadId> {
+        let workers = worker_manager.workers.lock().unwrap();
+        workers[0].thread.as_ref().map(|thread| thread.thread().id())
+    }
+
     #[test]
     fn test_creation() {
         let worker_count = 16;
-        let worker_manager = WorkerManager::new(16);
-        assert_eq!(worker_manager.workers.len(), worker_count)
+        let worker_manager = WorkerManager::new(16, 16);
+        let workers = worker_manager.workers.lock().unwrap();
+        assert_eq!(workers.len(), worker_count);
     }
 
     #[test]
-    #[should_panic]
     fn test_request() {
-        struct PanicTask();
+        let (sender, receiver) = mpsc::channel();
+        let (on_panic_sender, on_panic_receiver) = mpsc::channel();
 
-        impl Task for PanicTask {
-            fn execute(&mut self) {
-                panic!();
-            }
+        let worker_manager = WorkerManager::new(1, 8);
+        worker_manager
+            .request(Box::new(PanicTask { panics_again: false, on_panic_called: on_panic_sender }))
+            .unwrap();
+        worker_manager.request(Box::new(SendTask(sender, 1))).unwrap();
+
+        // the only worker survives the panic and handles the next task
+        on_panic_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+    }
+
+    #[test]
+    fn test_respawn_dead_workers() {
+        let (sender, receiver) = mpsc::channel();
+        let (on_panic_sender, _on_panic_receiver) = mpsc::channel();
+
+        let worker_manager = WorkerManager::new(1, 8);
+        let thread_id = get_thread_id(&worker_manager);
+        worker_manager
+            .request(Box::new(PanicTask { panics_again: true, on_panic_called: on_panic_sender }))
+            .unwrap();
+
+        // the dead worker is replaced without waiting for the next task
+        let started = Instant::now();
+        while get_thread_id(&worker_manager) == thread_id {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            thread::sleep(Duration::from_millis(10));
         }
 
-        let worker_manager = WorkerManager::new(1);
-        worker_manager.request(Box::new(PanicTask {}));
+        worker_manager.request(Box::new(SendTask(sender, 2))).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(2));
+    }
+
+    #[test]
+    fn test_full_queue() {
+        let (started_sender, started_receiver) = mpsc::channel();
+        let (unblock_sender, unblock_receiver) = mpsc::channel();
+        let (sender, receiver) = mpsc::channel();
+
+        let worker_manager = WorkerManager::new(1, 1);
+        let queue_metrics = worker_manager.get_queue_metrics();
+        worker_manager
+            .request(Box::new(BlockTask { started: started_sender, unblock: unblock_receiver }))
+            .unwrap();
+        started_receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        worker_manager.request(Box::new(SendTask(sender.clone(), 1))).unwrap();
+        assert_eq!(queue_metrics.get_depth(), 1);
+
+        // the task is given back instead of being queued
+        let result = worker_manager.request(Box::new(SendTask(sender, 2)));
+        assert!(matches!(result, Err(TrySendError::Full(Message::Job(_)))));
+        assert_eq!(queue_metrics.get_rejected_count(), 1);
+
+        unblock_sender.send(()).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(5)), Ok(1));
+        assert_eq!(queue_metrics.get_depth(), 0);
+        assert_eq!(queue_metrics.get_peak_depth(), 1);
+        assert_eq!(queue_metrics.get_enqueued_count(), 2);
     }
 }